@@ -0,0 +1,63 @@
+//! helpers shared by the example binaries
+
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use tap::vfile::VFileBuilder;
+
+use tap_plugin_ntfs::ntfs::Ntfs;
+use tap_plugin_ntfs::options::NtfsOptions;
+use tap_plugin_ntfs::pathvfile::PathVFileBuilder;
+
+/// command line with the image path and the remaining arguments, `--mft` select an extracted MFT instead of a partition
+pub struct Args
+{
+  pub mft : bool,
+  pub image : String,
+  pub args : Vec<String>,
+}
+
+pub fn parse_args(usage : &str) -> Result<Args>
+{
+  let mut mft = false;
+  let mut positional = Vec::new();
+  for arg in std::env::args().skip(1)
+  {
+    match arg.as_str()
+    {
+      "--mft" => mft = true,
+      "-h" | "--help" => return Err(anyhow!("usage : {}", usage)),
+      _ => positional.push(arg),
+    }
+  }
+  if positional.is_empty()
+  {
+    return Err(anyhow!("usage : {}", usage))
+  }
+  let image = positional.remove(0);
+  Ok(Args{ mft, image, args : positional })
+}
+
+pub fn open(args : &Args) -> Result<Ntfs>
+{
+  match args.mft
+  {
+    true =>
+    {
+      let builder : Arc<dyn VFileBuilder> = Arc::new(PathVFileBuilder::new(Path::new(&args.image))?);
+      Ntfs::from_mft(builder, None, None, NtfsOptions::new())
+    },
+    false => Ntfs::from_path(Path::new(&args.image), NtfsOptions::new()),
+  }
+}
+
+/// entry id from a number or a path
+pub fn resolve(ntfs : &Ntfs, target : &str) -> Result<u64>
+{
+  match target.parse::<u64>()
+  {
+    Ok(entry_id) => Ok(entry_id),
+    Err(_) => Ok(ntfs.open_path(target)?.0),
+  }
+}
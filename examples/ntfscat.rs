@@ -0,0 +1,35 @@
+//! write the content of a data stream to stdout
+//!
+//! cargo run --example ntfscat --features cli -- [--mft] image path|entry_id [stream name]
+
+mod common;
+
+use std::io::{self, Read, Write};
+
+use anyhow::{anyhow, Result};
+
+use tap_plugin_ntfs::ntfsattributes::NtfsAttributeType;
+
+const USAGE : &str = "ntfscat [--mft] image path|entry_id [stream name]";
+
+fn main() -> Result<()>
+{
+  let args = common::parse_args(USAGE)?;
+  let target = args.args.first().ok_or_else(|| anyhow!("usage : {}", USAGE))?;
+  let ntfs = common::open(&args)?;
+  let entry_id = common::resolve(&ntfs, target)?;
+
+  let builder = ntfs.attribute_builder(entry_id, NtfsAttributeType::Data, args.args.get(1).map(String::as_str))?;
+  let mut file = builder.open()?;
+  let mut stdout = io::stdout().lock();
+  let mut buffer = vec![0u8; 1024*1024];
+  loop
+  {
+    match file.read(&mut buffer)?
+    {
+      0 => break,
+      read => stdout.write_all(&buffer[..read])?,
+    }
+  }
+  Ok(())
+}
@@ -0,0 +1,35 @@
+//! list a directory, or every entry of the MFT with -r
+//!
+//! cargo run --example ntfsls --features cli -- [--mft] image [-r | path | entry id]
+
+mod common;
+
+use anyhow::Result;
+
+use tap_plugin_ntfs::ntfs::walk::WalkFilter;
+
+const USAGE : &str = "ntfsls [--mft] image [-r | path | entry id]";
+
+fn main() -> Result<()>
+{
+  let args = common::parse_args(USAGE)?;
+  let ntfs = common::open(&args)?;
+
+  match args.args.first().map(String::as_str)
+  {
+    Some("-r") => for entry in ntfs.walk(&WalkFilter::new())
+    {
+      println!("{}{}\t{}\t{}{}", if entry.is_directory { "d" } else { "r" }, if entry.is_deleted { " *" } else { "" },
+               entry.entry_id, entry.path, if entry.is_directory { "/" } else { "" });
+    },
+    target =>
+    {
+      let entry_id = common::resolve(&ntfs, target.unwrap_or("/"))?;
+      for entry in ntfs.directory_entries(entry_id)?
+      {
+        println!("{}\t{}", entry.file_reference.entry, entry.file_name.file_name);
+      }
+    },
+  }
+  Ok(())
+}
@@ -0,0 +1,28 @@
+//! print the record header and the parsed attributes of an entry as JSON
+//!
+//! cargo run --example ntfsstat --features cli -- [--mft] image path|entry_id
+
+mod common;
+
+use anyhow::{anyhow, Result};
+
+const USAGE : &str = "ntfsstat [--mft] image path|entry_id";
+
+fn main() -> Result<()>
+{
+  let args = common::parse_args(USAGE)?;
+  let target = args.args.first().ok_or_else(|| anyhow!("usage : {}", USAGE))?;
+  let ntfs = common::open(&args)?;
+  let entry_id = common::resolve(&ntfs, target)?;
+
+  let entry = ntfs.mft_entries().entry(entry_id)?;
+  let attributes = entry.read_attributes(Some(ntfs.mft_entries()));
+  println!("{}", entry);
+  if let Ok(path) = ntfs.entry_path(entry_id)
+  {
+    println!("path : {}", path);
+  }
+  println!("{}", serde_json::to_string_pretty(&entry)?);
+  println!("{}", serde_json::to_string_pretty(&attributes)?);
+  Ok(())
+}
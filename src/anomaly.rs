@@ -0,0 +1,55 @@
+use chrono::{DateTime, Utc, Timelike};
+
+#[cfg(feature = "plugin")]
+use tap::value::Value;
+#[cfg(feature = "plugin")]
+use tap::reflect::ReflectStruct;
+#[cfg(feature = "plugin")]
+use tap_derive::Reflect;
+use serde::{Serialize, Deserialize};
+
+use crate::attributes::standard::StandardInformation;
+use crate::attributes::filename::FileName;
+
+/**
+ *  Timestomp indicators
+ *  Result of the comparison of $STANDARD_INFORMATION and $FILE_NAME timestamps
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "plugin", derive(Reflect))]
+pub struct Timestomp
+{
+  /// $SI creation time is earlier than $FN creation time
+  pub si_before_fn : bool,
+  /// $SI creation or modification time have no sub-second precision
+  pub zero_subsecond : bool,
+  /// a record or name was changed before being created
+  pub impossible_order : bool,
+}
+
+impl Timestomp
+{
+  /// return the indicators found or None if timestamps look consistent
+  pub fn detect(standard_information : &StandardInformation, file_name : &FileName) -> Option<Timestomp>
+  {
+    let si_before_fn = standard_information.creation_time < file_name.creation_time;
+
+    //tools setting time with second resolution leave the sub-second part empty
+    let zero_subsecond = (is_truncated(&standard_information.creation_time) || is_truncated(&standard_information.altered_time))
+                         && !is_truncated(&file_name.creation_time);
+
+    let impossible_order = standard_information.mft_altered_time < standard_information.creation_time
+                           || file_name.mft_modification_time < file_name.creation_time;
+
+    match si_before_fn || zero_subsecond || impossible_order
+    {
+      true => Some(Timestomp{ si_before_fn, zero_subsecond, impossible_order }),
+      false => None,
+    }
+  }
+}
+
+fn is_truncated(time : &DateTime<Utc>) -> bool
+{
+  time.nanosecond() == 0
+}
@@ -1,17 +1,16 @@
-use std::io::SeekFrom;
-
-use tap::vfile::{VFile, read_utf16_exact};
+use std::fmt;
 
 use crate::error::NtfsError;
 use crate::ntfsattributes::NtfsAttributeType;
-use crate::attributecontent::{Resident, NonResident, ResidentType};
+use crate::attributecontent::{Resident, NonResident, ResidentType, record_slice, record_utf16};
 
 use anyhow::Result;
+use serde::{Serialize, Deserialize};
 use byteorder::{ByteOrder, LittleEndian};
 use num_traits::FromPrimitive;
 
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct MftAttribute
 {
   pub type_id           : NtfsAttributeType,
@@ -26,43 +25,41 @@ pub struct MftAttribute
 }
 impl MftAttribute
 {
-  pub fn from_file<T : VFile>(mut file : &mut T, offset : u32) -> Result<MftAttribute>
+  /// parse the attribute header at `offset` of a record buffer
+  pub fn from_slice(record : &[u8], entry : u64, offset : u32) -> Result<MftAttribute>
   {
-    file.seek(SeekFrom::Start(offset as u64))?;
-    let mut data = [0;16]; 
-    file.read_exact(&mut data)?;
-
-    let type_id = LittleEndian::read_u32(&data[0..4]);
+    //the end marker can be the last 4 bytes of the record
+    let type_id = LittleEndian::read_u32(record_slice(record, offset as usize, 4)?);
     if type_id == 0xffffffff
     {
       return Err(NtfsError::MftAttributesEnd.into()); //return specific error as need to be catched
     }
+    let data = record_slice(record, offset as usize, 16)?;
 
     let type_id = match NtfsAttributeType::from_u32(type_id) 
     {
       Some(attribute_type) => attribute_type,
-      None => return Err(NtfsError::MftAttributeUnknownType(type_id).into())
+      None => return Err(NtfsError::MftAttributeUnknownType{ type_id, entry, offset }.into())
     };
 
     let length = LittleEndian::read_u32(&data[4..8]);
     let non_resident_flag = data[8];
     let name_size = data[9];
     let name_offset = LittleEndian::read_u16(&data[10..12]);
-    let flags = LittleEndian::read_u16(&data[12..15]);
+    let flags = LittleEndian::read_u16(&data[12..14]);
     let id = LittleEndian::read_u16(&data[14..16]);
 
     let data = match non_resident_flag
     {
-      0 => ResidentType::Resident(Resident::from_file(&mut file)?),
-      1 => ResidentType::NonResident(NonResident::from_file(&mut file, offset)?),
-      _ => return Err(NtfsError::MftAttributeDataType.into()),
+      0 => ResidentType::Resident(Resident::from_slice(record, offset as usize + 16)?),
+      1 => ResidentType::NonResident(NonResident::from_slice(record, offset, offset as usize + 16)?),
+      flag => return Err(NtfsError::MftAttributeDataType{ type_id, entry, offset, flag }.into()),
     };
 
     let name = match name_size 
     {
       0 => None, 
-      size => { file.seek(SeekFrom::Start(offset as u64 + name_offset as u64))?;
-                Some(read_utf16_exact(file, size as usize * 2)?) }
+      size => Some(record_utf16(record, offset as usize + name_offset as usize, size as usize * 2)?),
     };
 
     Ok(MftAttribute{
@@ -106,3 +103,20 @@ impl MftAttribute
   }
 }
 
+impl fmt::Display for MftAttribute
+{
+  fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result
+  {
+    write!(f, "{:?}", self.type_id)?;
+    if let Some(name) = &self.name
+    {
+      write!(f, " \"{}\"", name)?;
+    }
+    match &self.data
+    {
+      ResidentType::Resident(resident) => write!(f, " id {} resident {} bytes", self.id, resident.content_size),
+      ResidentType::NonResident(non_resident) => write!(f, " id {} non resident {} bytes vcn {}-{} in {} runs", self.id, non_resident.content_actual_size,
+                                                        non_resident.vnc_start, non_resident.vnc_end, non_resident.runs.len()),
+    }
+  }
+}
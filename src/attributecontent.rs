@@ -1,15 +1,20 @@
+use std::io::Read;
 use std::sync::Arc;
-use std::io::SeekFrom;
 
-use tap::vfile::{VFile, VFileBuilder};
-use tap::mappedvfile::{MappedVFileBuilder,FileRanges};
+use tap::vfile::VFileBuilder;
 
 use crate::attribute::{MftAttribute};
 use crate::error::NtfsError;
+use crate::mapping::{MappedRange, StreamRuns, LazyVFileBuilder, resolve, mapping_size, to_builder, map_offset, unmap_offset};
 
 use anyhow::Result;
+use serde::{Serialize, Deserialize};
 use byteorder::{ByteOrder, LittleEndian};
 
+/// attribute contents read whole in memory like attribute lists or $UpCase are much smaller,
+/// a bigger size comes from a damaged or crafted record
+pub const MAX_CONTENT_SIZE : u64 = 64 * 1024 * 1024;
+
 #[inline]
 pub fn pad_u64(data : &[u8]) -> u64
 {
@@ -32,28 +37,63 @@ pub fn pad_i64(data : &[u8]) -> i64
   LittleEndian::read_i64(&padded[0..8])
 }
 
-#[derive(Debug)]
+/// read the whole content of a builder, up to `MAX_CONTENT_SIZE`
+pub fn read_builder(builder : Arc<dyn VFileBuilder>) -> Result<Vec<u8>>
+{
+  let size = builder.size();
+  if size > MAX_CONTENT_SIZE
+  {
+    return Err(NtfsError::ContentTooLarge{ size, max : MAX_CONTENT_SIZE }.into())
+  }
+  let mut content = vec![0u8; size as usize];
+  builder.open()?.read_exact(&mut content)?;
+  Ok(content)
+}
+
+/// return `size` bytes of a record buffer starting at `offset` or an error if it's past the end
+#[inline]
+pub fn record_slice(data : &[u8], offset : usize, size : usize) -> Result<&[u8]>
+{
+  offset.checked_add(size).and_then(|end| data.get(offset..end))
+        .ok_or_else(|| NtfsError::MftRecordOutOfBounds{ offset : offset as u64, size : size as u64 }.into())
+}
+
+/// decode an UTF-16 string of `size` bytes from a record buffer
+pub fn record_utf16(data : &[u8], offset : usize, size : usize) -> Result<String>
+{
+  let data = record_slice(data, offset, size)?;
+  let utf16 : Vec<u16> = data.chunks_exact(2).map(LittleEndian::read_u16).collect();
+  Ok(String::from_utf16(&utf16)?)
+}
+
+#[derive(Debug, Serialize)]
 pub struct MftAttributeContent
 {
+  pub entry : u64, //id of the entry containing this attribute
   pub offset : u32,
   pub mft_attribute : MftAttribute,
-  pub mft_entry_builder : Arc<dyn VFileBuilder>,
+  #[serde(skip)]
+  pub record_mapping : Arc<Vec<MappedRange>>, //fixup aware mapping of the record
+
+  #[serde(skip)]
   pub partition_builder: Option<Arc<dyn VFileBuilder>>,
+  #[serde(skip)]
   pub zero_builder: Option<Arc<dyn VFileBuilder>>,
-  pub cluster_size : Option<u32>,
+  pub cluster_size : Option<u64>,
 }
 
 impl MftAttributeContent
 {
-  pub fn from_file<T : VFile>(file : &mut T, offset : u32, mft_entry_builder : Arc<dyn VFileBuilder>, partition_builder : &Option<Arc<dyn VFileBuilder>>, zero_builder : &Option<Arc<dyn VFileBuilder>>, cluster_size : Option<u32>) -> Result<Self>
+  /// parse the attribute at `offset` of a record buffer, fixups must already be applied
+  pub fn from_slice(data : &[u8], entry : u64, offset : u32, record_mapping : Arc<Vec<MappedRange>>, partition_builder : &Option<Arc<dyn VFileBuilder>>, zero_builder : &Option<Arc<dyn VFileBuilder>>, cluster_size : Option<u64>) -> Result<Self>
   {
-     let mft_attribute = MftAttribute::from_file(file, offset)?;
+     let mft_attribute = MftAttribute::from_slice(data, entry, offset)?;
      let zero_builder = zero_builder.as_ref().cloned();
 
      match partition_builder
      {
-       Some(partition_builder) => Ok(MftAttributeContent{offset, mft_attribute, mft_entry_builder, partition_builder : Some(partition_builder.clone()), zero_builder, cluster_size}),
-       None => Ok(MftAttributeContent{offset, mft_attribute, mft_entry_builder, partition_builder : None, zero_builder, cluster_size}),
+       Some(partition_builder) => Ok(MftAttributeContent{entry, offset, mft_attribute, record_mapping, partition_builder : Some(partition_builder.clone()), zero_builder, cluster_size}),
+       None => Ok(MftAttributeContent{entry, offset, mft_attribute, record_mapping, partition_builder : None, zero_builder, cluster_size}),
      }
   }
 
@@ -61,39 +101,117 @@ impl MftAttributeContent
   {
     match &self.mft_attribute.data
     {
-      ResidentType::Resident(resident) => Ok(self.resident_builder(resident)?),
-      ResidentType::NonResident(non_resident) => 
-        match &self.partition_builder
-        {
-           Some(partition_builder) =>  Ok(self.non_resident_builder(non_resident, partition_builder.clone())?),
-           None => Err(NtfsError::NonResidentData{}.into()),
-        }
+      ResidentType::Resident(_) => Ok(to_builder(&self.mapping()?)),
+      //runs are only mapped when the stream is read
+      ResidentType::NonResident(non_resident) => Ok(Arc::new(LazyVFileBuilder::from_runs(self.stream_runs(non_resident)?, non_resident.actual_size())?)),
+    }
+  }
+
+  /// size of the content, the slack of the last cluster of a non-resident stream is not counted,
+  /// 0 for the following extents of an attribute as the size is stored in the first one
+  pub fn size(&self) -> u64
+  {
+    match &self.mft_attribute.data
+    {
+      ResidentType::Resident(resident) => resident.content_size as u64,
+      ResidentType::NonResident(non_resident) => non_resident.actual_size().unwrap_or(0),
+    }
+  }
+
+  /// size of the clusters used by a non-resident stream, the content size if resident,
+  /// 0 for the following extents of an attribute
+  pub fn allocated_size(&self) -> u64
+  {
+    match &self.mft_attribute.data
+    {
+      ResidentType::Resident(resident) => resident.content_size as u64,
+      ResidentType::NonResident(non_resident) if non_resident.vnc_start == 0 => non_resident.content_allocated_size,
+      ResidentType::NonResident(_) => 0,
+    }
+  }
+
+  /// ranges of the content resolved down to the MFT or partition builder
+  pub fn mapping(&self) -> Result<Vec<MappedRange>>
+  {
+    match &self.mft_attribute.data
+    {
+      ResidentType::Resident(resident) => self.resident_mapping(resident),
+      ResidentType::NonResident(non_resident) => self.stream_runs(non_resident)?.mapping(),
+    }
+  }
+
+  /// partition offset of the byte at `offset` of a non-resident stream, 
+  /// None if the attribute is resident, sparse at this offset or smaller
+  pub fn stream_offset_to_offset(&self, offset : u64) -> Option<u64>
+  {
+    let partition_builder = self.partition_builder.as_ref()?;
+    if !matches!(self.mft_attribute.data, ResidentType::NonResident(_))
+    {
+      return None
+    }
+
+    let mapping = self.mapping().ok()?;
+    match map_offset(&mapping, offset)?
+    {
+      (mapped, offset) if Arc::ptr_eq(&mapped.builder, partition_builder) => Some(offset),
+      _ => None,
+    }
+  }
+
+  /// offset in the stream of the byte at partition `offset`, None if the stream doesn't use it
+  pub fn offset_to_stream_offset(&self, offset : u64) -> Option<u64>
+  {
+    let partition_builder = self.partition_builder.as_ref()?;
+    if !matches!(self.mft_attribute.data, ResidentType::NonResident(_))
+    {
+      return None
     }
+
+    unmap_offset(&self.mapping().ok()?, partition_builder, offset)
+  }
+
+  /// logical (physical) cluster of the virtual cluster `vcn` of a non-resident stream
+  pub fn vcn_to_lcn(&self, vcn : u64) -> Option<u64>
+  {
+    let cluster_size = self.cluster_size.filter(|cluster_size| *cluster_size != 0)?;
+    self.stream_offset_to_offset(vcn.checked_mul(cluster_size)?).map(|offset| offset / cluster_size)
   }
 
-  fn resident_builder(&self, resident : &Resident) -> Result<Arc<dyn VFileBuilder>>
+  /// virtual cluster of a non-resident stream stored in the logical cluster `lcn`
+  pub fn lcn_to_vcn(&self, lcn : u64) -> Option<u64>
+  {
+    let cluster_size = self.cluster_size.filter(|cluster_size| *cluster_size != 0)?;
+    self.offset_to_stream_offset(lcn.checked_mul(cluster_size)?).map(|offset| offset / cluster_size)
+  }
+
+  fn resident_mapping(&self, resident : &Resident) -> Result<Vec<MappedRange>>
   {
-    let mut file_ranges = FileRanges::new();
-  
     let offset = self.offset as u64 + resident.content_offset as u64;
     let content_size = resident.content_size as u64;
-    let range = 0.. content_size;
+    let record_size = mapping_size(&self.record_mapping);
     
-    if offset > self.mft_entry_builder.size()
+    if offset > record_size
     {
-      return Err(NtfsError::ResidentAttributeOffsetTooLarge.into())
+      return Err(NtfsError::ResidentAttributeOffsetTooLarge{ type_id : self.mft_attribute.type_id.clone(), entry : self.entry, offset }.into())
     }
-    if offset + content_size > self.mft_entry_builder.size()
+    if offset + content_size > record_size
     {
-      return Err(NtfsError::ResidentAttributeContentTooLarge.into())
+      return Err(NtfsError::ResidentAttributeContentTooLarge{ type_id : self.mft_attribute.type_id.clone(), entry : self.entry, offset, size : content_size }.into())
     }
 
-    file_ranges.push(range, offset, self.mft_entry_builder.clone());
-    Ok(Arc::new(MappedVFileBuilder::new(file_ranges)))
+    //content is mapped directly to the record storage rather than to the record builder
+    Ok(resolve(&self.record_mapping, 0, offset, content_size))
   }
 
-  fn non_resident_builder(&self, non_resident : &NonResident, partition_builder : Arc<dyn VFileBuilder>) -> Result<Arc<dyn VFileBuilder>>
+  /// runs of a non-resident attribute with the builders needed to map them
+  fn stream_runs(&self, non_resident : &NonResident) -> Result<StreamRuns>
   {
+    let partition_builder = match &self.partition_builder
+    {
+      Some(partition_builder) => partition_builder,
+      None => return Err(NtfsError::NonResidentData{}.into()),
+    };
+
     let zero_builder = match &self.zero_builder
     {
       Some(zero_builder) => zero_builder,
@@ -106,37 +224,19 @@ impl MftAttributeContent
       None => return Err(NtfsError::NonResidentAttributeClusterSize.into()),
     };
 
-    let mut file_ranges = FileRanges::new();
-    let mut total_size : u64 = non_resident.vnc_start * cluster_size as u64;
-    for run in non_resident.runs.iter()
-    {
-      if run.offset == 0 //sparse
-      {
-        let range = total_size..total_size + (run.length as u64 * cluster_size as u64);
-        file_ranges.push(range, 0 , zero_builder.clone());
-      }
-      else
-      {
-        let run_offset = run.offset as u64;
-        let run_length = run.length as u64;
-        let cluster_size = cluster_size as u64;
-
-        let range = total_size..total_size + (run_length * cluster_size);
-
-        if run_offset * cluster_size > partition_builder.size()
-        {
-          return Err(NtfsError::NonResidentAttributeOffsetTooLarge.into()) 
-        }
-        //check if range is valid before pushing !
-        file_ranges.push(range, run_offset * cluster_size, partition_builder.clone());
-      }
-      total_size += run.length * cluster_size as u64;
-    }
-    Ok(Arc::new(MappedVFileBuilder::new(file_ranges)))
+    Ok(StreamRuns{
+      entry : self.entry,
+      type_id : self.mft_attribute.type_id.clone(),
+      vcn_start : non_resident.vnc_start,
+      runs : non_resident.runs.iter().map(|run| (run.offset, run.length)).collect(),
+      cluster_size,
+      partition_builder : partition_builder.clone(),
+      zero_builder : zero_builder.clone(),
+    })
   }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub enum ResidentType
 {
   Resident(Resident),
@@ -146,7 +246,7 @@ pub enum ResidentType
 /**
  *  Resident & Non Resident 
  */
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Resident
 {
   pub content_size      : u32,
@@ -155,10 +255,10 @@ pub struct Resident
 
 impl Resident
 {
-  pub fn from_file<T : VFile>(file : &mut T) -> Result<Self>
+  /// parse the resident header following the common attribute header at `offset`
+  pub fn from_slice(data : &[u8], offset : usize) -> Result<Self>
   {
-    let mut data = [0;6];
-    file.read_exact(&mut data)?;
+    let data = record_slice(data, offset, 6)?;
     
     let content_size = LittleEndian::read_u32(&data[0..4]);
     let content_offset = LittleEndian::read_u16(&data[4..6]);
@@ -166,14 +266,14 @@ impl Resident
   }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct RunList
 {
   pub offset : i64,
   pub length : u64,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct NonResident
 {
   pub vnc_start                : u64,
@@ -189,10 +289,17 @@ pub struct NonResident
 
 impl NonResident
 {
-  pub fn from_file<T : VFile>(file : &mut T, offset : u32) -> Result<Self>
+  /// sizes are only set in the first extent of an attribute, the following extents are read up to their last cluster
+  pub fn actual_size(&self) -> Option<u64>
   {
-    let mut data = [0;48];
-    file.read_exact(&mut data)?;
+    (self.vnc_start == 0).then_some(self.content_actual_size)
+  }
+
+  /// parse the non-resident header of the attribute starting at `attribute_offset`
+  /// and its run list, `offset` is the position following the common attribute header
+  pub fn from_slice(record : &[u8], attribute_offset : u32, offset : usize) -> Result<Self>
+  {
+    let data = record_slice(record, offset, 48)?;
 
     let vnc_start = LittleEndian::read_u64(&data[0..8]);
     let vnc_end = LittleEndian::read_u64(&data[8..16]);
@@ -203,15 +310,15 @@ impl NonResident
     let content_actual_size = LittleEndian::read_u64(&data[32..40]);
     let content_initialized_size = LittleEndian::read_u64(&data[40..48]);
 
-    
-    file.seek(SeekFrom::Start(offset as u64 + run_list_offset as u64))?;
+
+    let mut position = attribute_offset as usize + run_list_offset as usize;
     let mut runs : Vec<RunList> = Vec::new();
     let mut run_previous_offset : i64 = 0;
 
     loop
     {
-      let mut byte  = [0; 1];
-      file.read_exact(&mut byte)?;
+      let byte = record_slice(record, position, 1)?;
+      position += 1;
 
       //The first byte is split into two nibbles (4-bit values). The low-order bits tell you the number of bytes in the run length; the high-order bits tell you the numer of bytes in the offset to the run.
       let length_size = byte[0] & 0xf;
@@ -230,31 +337,21 @@ impl NonResident
         break
       }
     
-      let mut run_length = vec![0; length_size as usize];
-      file.read_exact(&mut run_length)?;
-      let run_length = pad_u64(&run_length);
+      let run_length = pad_u64(record_slice(record, position, length_size as usize)?);
+      position += length_size as usize;
 
       let run_offset = match offset_size
-      {
-        0 => vec![0; 0],
-        _ => {
-          let mut run_offset = vec![0; offset_size as usize];
-          file.read_exact(&mut run_offset)?;
-          run_offset
-        }
-      };
-
-      let run_offset = match run_offset.len()
       {
         0 => 0,
-        _ => pad_i64(&run_offset),
+        _ => pad_i64(record_slice(record, position, offset_size as usize)?),
       };
+      position += offset_size as usize;
 
       if run_length == 0
       {
         break
       }
-      run_previous_offset += run_offset;
+      run_previous_offset = run_previous_offset.checked_add(run_offset).ok_or(NtfsError::Overflow("run list offset"))?;
 
       let run_list = match run_offset 
       {
@@ -1,58 +1,332 @@
+use std::io::SeekFrom;
+use std::ops::Range;
 use std::sync::Arc;
 
 use tap::vfile::VFileBuilder;
 
 use anyhow::Result;
+use serde::{Serialize, Deserialize};
 
+/// size of the buffer used to read large bitmap like the volume $Bitmap
+const BITMAP_CHUNK_SIZE : u64 = 1024 * 1024;
 
-#[derive(Debug)]
+/**
+ *  RangeSet
+ *  Sorted set of disjoint and non adjacent ranges, used for the allocated and free clusters of a bitmap
+ */
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RangeSet
+{
+  ranges : Vec<Range<u64>>,
+}
+
+impl RangeSet
+{
+  pub fn new() -> Self
+  {
+    RangeSet::default()
+  }
+
+  /// add a range, merged with the ranges it overlaps or touches
+  pub fn insert(&mut self, range : Range<u64>)
+  {
+    if range.start >= range.end
+    {
+      return
+    }
+    //ranges are mostly added in order when reading a bitmap or a sorted list of runs
+    match self.ranges.last_mut()
+    {
+      Some(last) if range.start >= last.start && range.start <= last.end => last.end = last.end.max(range.end),
+      Some(last) if range.start > last.end => self.ranges.push(range),
+      None => self.ranges.push(range),
+      Some(_) =>
+      {
+        let first = self.ranges.partition_point(|other| other.end < range.start);
+        let end = self.ranges.partition_point(|other| other.start <= range.end);
+        let merged = match first < end
+        {
+          true => self.ranges[first].start.min(range.start)..self.ranges[end - 1].end.max(range.end),
+          false => range,
+        };
+        self.ranges.splice(first..end, std::iter::once(merged));
+      },
+    }
+  }
+
+  /// ranges in increasing order
+  pub fn ranges(&self) -> &[Range<u64>]
+  {
+    &self.ranges
+  }
+
+  pub fn iter(&self) -> std::slice::Iter<'_, Range<u64>>
+  {
+    self.ranges.iter()
+  }
+
+  /// number of ranges
+  pub fn len(&self) -> usize
+  {
+    self.ranges.len()
+  }
+
+  pub fn is_empty(&self) -> bool
+  {
+    self.ranges.is_empty()
+  }
+
+  /// number of values in the ranges, e.g. clusters
+  pub fn count(&self) -> u64
+  {
+    self.ranges.iter().map(|range| range.end - range.start).sum()
+  }
+
+  /// size of the biggest range
+  pub fn largest(&self) -> u64
+  {
+    self.ranges.iter().map(|range| range.end - range.start).max().unwrap_or(0)
+  }
+
+  pub fn contains(&self, value : u64) -> bool
+  {
+    let index = self.ranges.partition_point(|range| range.end <= value);
+    self.ranges.get(index).is_some_and(|range| range.start <= value)
+  }
+
+  /// values in `0..total` that are not in the set
+  pub fn invert(&self, total : u64) -> RangeSet
+  {
+    let mut inverted = RangeSet::new();
+    let mut start = 0;
+    for range in self.ranges.iter().take_while(|range| range.start < total)
+    {
+      inverted.insert(start..range.start);
+      start = range.end;
+    }
+    inverted.insert(start..total);
+    inverted
+  }
+
+  /// values in one of the sets
+  pub fn union(&self, other : &RangeSet) -> RangeSet
+  {
+    let mut ranges : Vec<Range<u64>> = self.ranges.iter().chain(other.ranges.iter()).cloned().collect();
+    ranges.sort_unstable_by_key(|range| range.start);
+    ranges.into_iter().collect()
+  }
+
+  /// values of this set that are not in `other`
+  pub fn difference(&self, other : &RangeSet) -> RangeSet
+  {
+    let total = self.ranges.last().map(|range| range.end).unwrap_or(0);
+    self.intersection(&other.invert(total))
+  }
+
+  /// values in both sets
+  pub fn intersection(&self, other : &RangeSet) -> RangeSet
+  {
+    let mut result = RangeSet::new();
+    let (mut left, mut right) = (self.ranges.iter().peekable(), other.ranges.iter().peekable());
+    while let (Some(a), Some(b)) = (left.peek(), right.peek())
+    {
+      result.insert(a.start.max(b.start)..a.end.min(b.end));
+      match a.end < b.end
+      {
+        true => left.next(),
+        false => right.next(),
+      };
+    }
+    result
+  }
+}
+
+impl FromIterator<Range<u64>> for RangeSet
+{
+  fn from_iter<I : IntoIterator<Item = Range<u64>>>(iter : I) -> Self
+  {
+    let mut set = RangeSet::new();
+    for range in iter
+    {
+      set.insert(range);
+    }
+    set
+  }
+}
+
+impl<'a> IntoIterator for &'a RangeSet
+{
+  type Item = &'a Range<u64>;
+  type IntoIter = std::slice::Iter<'a, Range<u64>>;
+
+  fn into_iter(self) -> Self::IntoIter
+  {
+    self.ranges.iter()
+  }
+}
+
+impl IntoIterator for RangeSet
+{
+  type Item = Range<u64>;
+  type IntoIter = std::vec::IntoIter<Range<u64>>;
+
+  fn into_iter(self) -> Self::IntoIter
+  {
+    self.ranges.into_iter()
+  }
+}
+
+/// allocated and free clusters of a volume bitmap, together they cover every cluster of the volume
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClusterAllocation
+{
+  pub allocated : RangeSet,
+  pub free : RangeSet,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Bitmap
 {
 }
 
 impl Bitmap
 {
-  #[allow(clippy::needless_range_loop)]
-  pub fn new(content : Arc<dyn VFileBuilder>) -> Result<Vec<std::ops::Range<u64>>>
+  /// return the allocated and free clusters of the `total_clusters` of the volume, the bitmap is read by chunk
+  /// so huge volumes don't need to load it fully in memory. The padding bits after the last cluster are ignored
+  /// and the clusters not covered by the bitmap are free
+  pub fn ranges(content : Arc<dyn VFileBuilder>, total_clusters : u64) -> Result<ClusterAllocation>
   {
-    let mut unallocated = Vec::new(); 
+    let mut allocation = ClusterAllocation::default();
     let mut file = content.open()?;
-
-    //check max size or read by chunk !
-    let mut bitmap  = vec![0u8; content.size() as usize]; 
-    file.read_exact(&mut bitmap)?;
+    let mut remaining = std::cmp::min(content.size(), total_clusters.div_ceil(8));
+    let mut bitmap = vec![0u8; std::cmp::min(remaining, BITMAP_CHUNK_SIZE) as usize];
    
-    let mut cluster_start = 0;
-    let mut cluster_end = 0;
+    //first cluster and state of the current range
+    let mut range_start = 0;
+    let mut range_allocated = false;
     let mut current_cluster = 0;
 
-    for idx in 0..bitmap.len()
+    while remaining > 0
     {
-      let byte =  bitmap[idx];
-      for i in 0..8
+      let chunk_size = std::cmp::min(remaining, BITMAP_CHUNK_SIZE) as usize;
+      let chunk = &mut bitmap[..chunk_size];
+      file.read_exact(chunk)?;
+      remaining -= chunk_size as u64;
+
+      for byte in chunk.iter()
       {
-        if (byte >> i) & 1 != 0
+        for i in 0..8
         {
-          if cluster_start != 0
+          if current_cluster >= total_clusters
           {
-            unallocated.push(cluster_start..cluster_end);
-            cluster_start = 0;
-            cluster_end = 0;
+            break
           }
-        }
-        else
-        {
-          if cluster_start == 0
+          let allocated = (byte >> i) & 1 != 0;
+          if allocated != range_allocated
           {
-            cluster_start = current_cluster;
+            match range_allocated
+            {
+              true => allocation.allocated.insert(range_start..current_cluster),
+              false => allocation.free.insert(range_start..current_cluster),
+            }
+            range_start = current_cluster;
+            range_allocated = allocated;
           }
-          cluster_end = current_cluster;
+          current_cluster += 1;
+        }
+      }
+    }
+
+    //a volume bigger than its bitmap coverage ends with untracked clusters
+    match range_allocated
+    {
+      true => { allocation.allocated.insert(range_start..current_cluster); allocation.free.insert(current_cluster..total_clusters) },
+      false => allocation.free.insert(range_start..total_clusters),
+    }
+    Ok(allocation)
+  }
+
+  /// state of the cluster `lcn`, the clusters not covered by the bitmap are free
+  pub fn is_allocated(content : Arc<dyn VFileBuilder>, lcn : u64) -> Result<bool>
+  {
+    //u64::MAX can't start a one cluster range and is past any bitmap
+    match lcn.checked_add(1)
+    {
+      Some(end) => Ok(Bitmap::count_allocated(content, lcn..end)? == 1),
+      None => Ok(false),
+    }
+  }
+
+  /// true if every cluster of `range` is allocated
+  pub fn is_range_allocated(content : Arc<dyn VFileBuilder>, range : Range<u64>) -> Result<bool>
+  {
+    let count = range.end.saturating_sub(range.start);
+    Ok(Bitmap::count_allocated(content, range)? == count)
+  }
+
+  /// true if no cluster of `range` is allocated
+  pub fn is_range_free(content : Arc<dyn VFileBuilder>, range : Range<u64>) -> Result<bool>
+  {
+    Ok(Bitmap::count_allocated(content, range)? == 0)
+  }
+
+  /// number of allocated clusters in `range`, only the bytes covering the range are read by chunk
+  pub fn count_allocated(content : Arc<dyn VFileBuilder>, range : Range<u64>) -> Result<u64>
+  {
+    let end = std::cmp::min(range.end, content.size().saturating_mul(8));
+    if range.start >= end
+    {
+      return Ok(0)
+    }
+
+    let mut file = content.open()?;
+    let (first_byte, end_byte) = (range.start / 8, (end - 1) / 8 + 1);
+    file.seek(SeekFrom::Start(first_byte))?;
+    let mut bitmap = vec![0u8; std::cmp::min(end_byte - first_byte, BITMAP_CHUNK_SIZE) as usize];
+    let mut count = 0;
+    let mut current_byte = first_byte;
+
+    while current_byte < end_byte
+    {
+      let chunk_size = std::cmp::min(end_byte - current_byte, BITMAP_CHUNK_SIZE) as usize;
+      let chunk = &mut bitmap[..chunk_size];
+      file.read_exact(chunk)?;
+
+      for (index, byte) in chunk.iter().enumerate()
+      {
+        //mask the clusters of the first and last bytes outside of the range
+        let first_cluster = (current_byte + index as u64) * 8;
+        let mut mask = 0xffu8;
+        if first_cluster < range.start
+        {
+          mask &= 0xff << (range.start - first_cluster);
+        }
+        if first_cluster + 8 > end
+        {
+          mask &= 0xff >> (first_cluster + 8 - end);
         }
-        current_cluster += 1;
+        count += (byte & mask).count_ones() as u64;
       }
+      current_byte += chunk_size as u64;
     }
+    Ok(count)
+  }
 
-    Ok(unallocated)
+  /// read a small bitmap (like $MFT:$BITMAP) in memory, only the bytes of the first `bit_count` bits are read
+  /// so a damaged size can't allocate more than the bits used
+  pub fn read(content : Arc<dyn VFileBuilder>, bit_count : u64) -> Result<Vec<u8>>
+  {
+    let size = content.size().min(bit_count.div_ceil(8));
+    let mut file = content.open()?;
+    let mut bitmap = vec![0u8; size as usize];
+    file.read_exact(&mut bitmap)?;
+    Ok(bitmap)
+  }
+
+  /// return the state of bit `index` or None if it's outside of the bitmap
+  pub fn is_set(bitmap : &[u8], index : u64) -> Option<bool>
+  {
+    bitmap.get((index / 8) as usize).map(|byte| (byte >> (index % 8)) & 1 != 0)
   }
 
 }
@@ -1,22 +1,27 @@
-use std::sync::Arc;
-use std::io::Read;
+use std::fmt;
 
+#[cfg(feature = "plugin")]
 use tap::value::Value;
+#[cfg(feature = "plugin")]
 use tap::reflect::ReflectStruct;
 use tap::datetime::WindowsTimestamp;
-use tap::vfile::{VFileBuilder, read_utf16_exact};
+#[cfg(feature = "plugin")]
 use tap_derive::Reflect;
 
 use anyhow::Result;
 use byteorder::{ByteOrder, LittleEndian};
 use num_traits::FromPrimitive;
 use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
 
-use crate::attributecontent::pad_u64;
+use crate::attributecontent::{pad_u64, record_slice, record_utf16};
 use crate::attributes::FileAttributes;
+#[cfg(feature = "plugin")]
+use crate::attributes::file_attributes_to_value;
 use crate::error::NtfsError;
 
-#[derive(FromPrimitive, Clone, Copy, Debug, PartialOrd, PartialEq)]
+#[derive(FromPrimitive, Clone, Copy, Debug, PartialOrd, PartialEq, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum NameSpace
 {
@@ -26,41 +31,87 @@ pub enum NameSpace
   DosWin32 = 3,
 }
 
-#[derive(Debug, Reflect, Clone)]
+impl NameSpace
+{
+  pub fn as_str(&self) -> &'static str
+  {
+    match self
+    {
+      NameSpace::Posix => "POSIX",
+      NameSpace::Win32 => "Win32",
+      NameSpace::Dos => "DOS",
+      NameSpace::DosWin32 => "DOS & Win32",
+    }
+  }
+}
+
+#[cfg(feature = "plugin")]
+pub fn name_space_to_value(name_space : &Option<NameSpace>) -> Option<Value>
+{
+  name_space.map(|name_space| Value::String(name_space.as_str().to_string()))
+}
+
+/// name space to prefer when choosing the name of a node
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
+pub enum NameSpacePreference
+{
+  Posix,
+  #[default]
+  Win32,
+  Dos,
+}
+
+impl NameSpacePreference
+{
+  /// lower is better
+  pub fn rank(&self, name_space : NameSpace) -> u8
+  {
+    let order = match self
+    {
+      NameSpacePreference::Posix => [NameSpace::Posix, NameSpace::Win32, NameSpace::DosWin32, NameSpace::Dos],
+      NameSpacePreference::Win32 => [NameSpace::Win32, NameSpace::DosWin32, NameSpace::Posix, NameSpace::Dos],
+      NameSpacePreference::Dos => [NameSpace::Dos, NameSpace::DosWin32, NameSpace::Win32, NameSpace::Posix],
+    };
+    order.iter().position(|current| *current == name_space).unwrap_or(order.len()) as u8
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "plugin", derive(Reflect))]
 pub struct FileName 
 {
-  #[reflect(skip)]
+  #[cfg_attr(feature = "plugin", reflect(skip))]
   pub file_name : String,
   pub parent_mft_entry_id : u64,
-  #[reflect(skip)]
+  #[cfg_attr(feature = "plugin", reflect(skip))]
   pub parent_sequence : u16,
   pub creation_time : DateTime<Utc>,
   pub modification_time : DateTime<Utc>,
   pub mft_modification_time : DateTime<Utc>,
   pub accessed_time : DateTime<Utc>,
-  #[reflect(skip)]
+  #[cfg_attr(feature = "plugin", reflect(skip))]
   pub allocated_size : u64, 
-  #[reflect(skip)]
+  #[cfg_attr(feature = "plugin", reflect(skip))]
   pub real_size : u64,
-  #[reflect(skip)]
+  #[cfg_attr(feature = "plugin", reflect(with = "file_attributes_to_value"))]
   pub flags : FileAttributes, 
-  #[reflect(skip)]
+  #[cfg_attr(feature = "plugin", reflect(skip))]
   pub reparse_value : u32,
-  #[reflect(skip)]
+  #[cfg_attr(feature = "plugin", reflect(skip))]
   pub name_length : u8,
-  #[reflect(skip)]
+  #[cfg_attr(feature = "plugin", reflect(skip))]
   pub name_space : NameSpace,
+  /// id of the $FILE_NAME attribute in its record, 0 for the names read from an index
+  #[serde(default)]
+  #[cfg_attr(feature = "plugin", reflect(skip))]
+  pub attribute_id : u16,
 }
 
 impl FileName 
 {
-  pub fn new(content : Arc<dyn VFileBuilder>) -> Result<Self>
+  pub fn new(content : &[u8]) -> Result<Self>
   {
-    //let _size = content.size(); check size ?
-    let mut file = content.open()?;
-
-    let mut data = [0;66]; 
-    file.read_exact(&mut data)?;
+    let data = record_slice(content, 0, 66)?;
 
     let parent_mft_entry_id = pad_u64(&data[0..6]);
     let parent_sequence = LittleEndian::read_u16(&data[6..8]);
@@ -76,14 +127,15 @@ impl FileName
 
     let name_space = NameSpace::from_u8(data[65]).ok_or(NtfsError::MftAttributeUnknownNameSpace(data[65]))?;
 
-    if (name_length as u64) * 2 > content.size() - 66//check if > size - offset ?
+    let size = content.len() as u64;
+    if (name_length as u64) * 2 > size.saturating_sub(66)
     {
-      return Err(NtfsError::MftAttributeNameSpaceInvalidSize.into())
+      return Err(NtfsError::MftAttributeNameSpaceInvalidSize{ name_length, size }.into())
     }
 
     //we prefer to return error if we have an invalid filename 
     //and consider the full structure as invalid
-    let file_name = read_utf16_exact(&mut file, (name_length as usize) * 2)?; 
+    let file_name = record_utf16(content, 66, (name_length as usize) * 2)?; 
 
     Ok(FileName{
       file_name,
@@ -99,6 +151,17 @@ impl FileName
       reparse_value,
       name_length,
       name_space,
+      attribute_id : 0,
     })
   }
 }
+
+impl fmt::Display for FileName
+{
+  fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result
+  {
+    write!(f, "{} ({}) parent {}-{} size {} created {} modified {}", self.file_name, self.name_space.as_str(),
+           self.parent_mft_entry_id, self.parent_sequence, self.real_size,
+           self.creation_time.format("%Y-%m-%d %H:%M:%S"), self.modification_time.format("%Y-%m-%d %H:%M:%S"))
+  }
+}
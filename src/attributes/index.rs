@@ -0,0 +1,182 @@
+use std::cmp::min;
+use std::sync::Arc;
+
+use tap::vfile::VFileBuilder;
+
+use anyhow::Result;
+use serde::{Serialize, Deserialize};
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::attributecontent::{pad_u64, record_slice};
+use crate::attributes::FileReference;
+use crate::attributes::filename::FileName;
+use crate::mftentry::MftEntry;
+
+pub const INDEX_SIGNATURE : u32 = 0x58444E49; //INDX
+/// the entry is the last of the node and has no key
+const INDEX_ENTRY_LAST : u32 = 0x2;
+/// offset of the node header in an INDX record
+const INDEX_RECORD_HEADER_SIZE : usize = 0x18;
+
+/**
+ *  Entry of a file name ($I30) index, 
+ *  the key is a copy of the $FILE_NAME attribute of the referenced entry
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexEntry
+{
+  pub file_reference : FileReference,
+  pub file_name : FileName,
+}
+
+/**
+ *  $INDEX_ROOT, resident root node of an index
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexRoot
+{
+  pub attribute_type : u32,
+  pub collation_rule : u32,
+  /// size of the INDX records of the $INDEX_ALLOCATION attribute
+  pub index_record_size : u32,
+  pub entries : Vec<IndexEntry>,
+}
+
+impl IndexRoot
+{
+  pub fn new(content : &[u8]) -> Result<Self>
+  {
+    let data = record_slice(content, 0, 16)?;
+
+    let attribute_type = LittleEndian::read_u32(&data[0..4]);
+    let collation_rule = LittleEndian::read_u32(&data[4..8]);
+    let index_record_size = LittleEndian::read_u32(&data[8..12]);
+    let entries = node_entries(content, 16)?;
+
+    Ok(IndexRoot{ attribute_type, collation_rule, index_record_size, entries })
+  }
+}
+
+/**
+ *  $INDEX_ALLOCATION, non-resident INDX records containing the other nodes of an index
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexAllocation
+{
+  pub entries : Vec<IndexEntry>,
+}
+
+impl IndexAllocation
+{
+  /// read every INDX record, records with an invalid signature (unused) are skipped
+  pub fn new(builder : Arc<dyn VFileBuilder>, index_record_size : u32, sector_size : u16) -> Result<Self>
+  {
+    let mut entries = Vec::new();
+    for_each_index_record(builder, index_record_size, sector_size, |record|
+    {
+      //a corrupted record doesn't prevent reading the others
+      if let Ok(node) = node_entries(record, INDEX_RECORD_HEADER_SIZE)
+      {
+        entries.extend(node);
+      }
+    })?;
+
+    Ok(IndexAllocation{ entries })
+  }
+}
+
+/// call `f` with each INDX record of an $INDEX_ALLOCATION content once its fixups are applied,
+/// records with an invalid signature (unused) or fixups are skipped
+pub(crate) fn for_each_index_record<F>(builder : Arc<dyn VFileBuilder>, index_record_size : u32, sector_size : u16, mut f : F) -> Result<()>
+  where F : FnMut(&[u8])
+{
+  let record_size = index_record_size as usize;
+  if record_size < INDEX_RECORD_HEADER_SIZE
+  {
+    return Ok(())
+  }
+
+  let mut file = builder.open()?;
+  let mut record = vec![0u8; record_size];
+  let mut offset = 0;
+  while offset + index_record_size as u64 <= builder.size()
+  {
+    file.read_exact(&mut record)?;
+    offset += index_record_size as u64;
+
+    if LittleEndian::read_u32(&record[0..4]) != INDEX_SIGNATURE
+    {
+      continue
+    }
+    let fixup_array_offset = LittleEndian::read_u16(&record[4..6]);
+    let fixup_array_entry_count = LittleEndian::read_u16(&record[6..8]).saturating_sub(1);
+    let stride = MftEntry::stride(record_size as u64, fixup_array_entry_count as u64, sector_size);
+    match MftEntry::apply_fixup(&mut record, fixup_array_offset, fixup_array_entry_count, stride)
+    {
+      Some(fixup) if fixup.mismatch_count == 0 => f(&record),
+      _ => continue,
+    }
+  }
+  Ok(())
+}
+
+/// data of the entries of a view index ($SII, $SDH, $O, $Q) node stored in an INDX record
+pub(crate) fn view_entries_data(record : &[u8]) -> Result<Vec<&[u8]>>
+{
+  let offset = INDEX_RECORD_HEADER_SIZE;
+  let header = record_slice(record, offset, 16)?;
+  let entries_offset = LittleEndian::read_u32(&header[0..4]) as usize;
+  let index_size = LittleEndian::read_u32(&header[4..8]) as usize;
+  let end = min(offset.saturating_add(index_size), record.len());
+
+  let mut datas = Vec::new();
+  let mut current = offset.saturating_add(entries_offset);
+  while current + 16 <= end
+  {
+    let entry = &record[current..current + 16];
+    let data_offset = LittleEndian::read_u16(&entry[0..2]) as usize;
+    let data_length = LittleEndian::read_u16(&entry[2..4]) as usize;
+    let length = LittleEndian::read_u16(&entry[8..10]) as usize;
+    let flags = LittleEndian::read_u32(&entry[12..16]);
+    if flags & INDEX_ENTRY_LAST != 0 || length < 16
+    {
+      break
+    }
+    datas.push(record_slice(record, current + data_offset, data_length)?);
+    current += length;
+  }
+
+  Ok(datas)
+}
+
+/// parse the entries of the node whose header is at `offset`
+fn node_entries(data : &[u8], offset : usize) -> Result<Vec<IndexEntry>>
+{
+  let header = record_slice(data, offset, 16)?;
+  let entries_offset = LittleEndian::read_u32(&header[0..4]) as usize;
+  let index_size = LittleEndian::read_u32(&header[4..8]) as usize;
+  let end = min(offset.saturating_add(index_size), data.len());
+
+  let mut entries = Vec::new();
+  let mut current = offset.saturating_add(entries_offset);
+  while current + 16 <= end
+  {
+    let entry = &data[current..current + 16];
+    let length = LittleEndian::read_u16(&entry[8..10]) as usize;
+    let key_length = LittleEndian::read_u16(&entry[10..12]) as usize;
+    let flags = LittleEndian::read_u32(&entry[12..16]);
+    if flags & INDEX_ENTRY_LAST != 0 || length < 16
+    {
+      break
+    }
+
+    let file_reference = FileReference{ entry : pad_u64(&entry[0..6]), sequence : LittleEndian::read_u16(&entry[6..8]) };
+    if let Ok(file_name) = FileName::new(record_slice(data, current + 16, key_length)?)
+    {
+      entries.push(IndexEntry{ file_reference, file_name });
+    }
+    current += length;
+  }
+
+  Ok(entries)
+}
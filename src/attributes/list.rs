@@ -1,19 +1,13 @@
-use std::sync::Arc;
-use std::io::SeekFrom;
-use std::io::Seek;
-
-use tap::vfile::{VFile, VFileBuilder, read_utf16_exact};
-
 use anyhow::Result;
+use serde::{Serialize, Deserialize};
 use byteorder::{ByteOrder, LittleEndian};
 use num_traits::FromPrimitive;
-use seek_bufread::BufReader;
 
 use crate::error::NtfsError;
-use crate::attributecontent::pad_u64;
+use crate::attributecontent::{pad_u64, record_slice, record_utf16};
 use crate::ntfsattributes::NtfsAttributeType;
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct AttributeListItem
 {
   pub name         : Option<String>,
@@ -29,10 +23,10 @@ pub struct AttributeListItem
 
 impl AttributeListItem 
 {
-  pub fn new<T : VFile>(mut file : &mut T) -> Result<Self>
+  /// parse the item at `offset` of the attribute list content
+  pub fn new(content : &[u8], offset : usize) -> Result<Self>
   {
-    let mut data = [0;26]; 
-    file.read_exact(&mut data)?;
+    let data = record_slice(content, offset, 26)?;
 
     let type_id = LittleEndian::read_u32(&data[0..4]);
     if type_id == 0xffffffff
@@ -43,7 +37,7 @@ impl AttributeListItem
     let type_id = match NtfsAttributeType::from_u32(type_id) 
     {
       Some(attribute_type) => attribute_type,
-      None => return Err(NtfsError::MftAttributeUnknownType(type_id).into())
+      None => return Err(NtfsError::MftAttributeListUnknownType{ type_id, offset : offset as u64 }.into())
     };
 
 
@@ -58,8 +52,7 @@ impl AttributeListItem
     let name = match name_size 
     {
       0 => None, 
-      size => { file.seek(SeekFrom::Start(name_offset as u64))?;
-                Some(read_utf16_exact(&mut file, size as usize)?) }
+      size => Some(record_utf16(content, offset + name_offset as usize, size as usize * 2)?),
     };
 
     Ok(AttributeListItem{
@@ -83,21 +76,22 @@ pub struct AttributeList
 
 impl AttributeList
 {
-  pub fn new(content : Arc<dyn VFileBuilder>)-> Result<Vec<AttributeListItem>>
+  pub fn new(content : &[u8])-> Result<Vec<AttributeListItem>>
   {
-    let file = content.open()?;
-    let mut file = BufReader::new(file);
-
     let mut attributes = Vec::new();
-    let mut previous_offset;
+    let mut offset = 0;
 
-    while file.tell()? < content.size()// && previous_offset < file.tell()?
+    while offset < content.len()
     {
-      previous_offset = file.tell()?; 
-      match AttributeListItem::new(&mut file)
+      match AttributeListItem::new(content, offset)
       {
         Ok(attribute) => {
-                            file.seek(SeekFrom::Start(previous_offset + attribute.size as u64))?;
+                            //a zero sized item would loop forever
+                            if attribute.size == 0
+                            {
+                              break
+                            }
+                            offset += attribute.size as usize;
                             attributes.push(attribute); 
                          }
         Err(_err) => break,
@@ -3,9 +3,23 @@ pub mod filename;
 pub mod volume;
 pub mod list;
 pub mod bitmap;
+pub mod index;
+pub mod upcase;
+
+#[cfg(feature = "plugin")]
+use std::sync::Arc;
+
+#[cfg(feature = "plugin")]
+use tap::value::Value;
+#[cfg(feature = "plugin")]
+use tap::reflect::ReflectStruct;
+#[cfg(feature = "plugin")]
+use tap_derive::Reflect;
+use serde::{Serialize, Deserialize};
 
 bitflags! 
 {
+  #[derive(Serialize, Deserialize)]
   pub struct FileAttributes : u32 
   {
     const READONLY             = 0x0000_0001;
@@ -24,3 +38,66 @@ bitflags!
     const ENCRYPTED            = 0x0000_4000;
   }
 }
+
+const FILE_ATTRIBUTES_NAMES : [(FileAttributes, &str); 14] = [
+  (FileAttributes::READONLY, "READONLY"),
+  (FileAttributes::HIDDEN, "HIDDEN"),
+  (FileAttributes::SYSTEM, "SYSTEM"),
+  (FileAttributes::DIRECTORY, "DIRECTORY"),
+  (FileAttributes::ARCHIVE, "ARCHIVE"),
+  (FileAttributes::DEVICE, "DEVICE"),
+  (FileAttributes::NORMAL, "NORMAL"),
+  (FileAttributes::TEMPORARY, "TEMPORARY"),
+  (FileAttributes::SPARSE, "SPARSE"),
+  (FileAttributes::REPARSE, "REPARSE"),
+  (FileAttributes::COMPRESSED, "COMPRESSED"),
+  (FileAttributes::OFFLINE, "OFFLINE"),
+  (FileAttributes::NOT_INDEXED, "NOT_INDEXED"),
+  (FileAttributes::ENCRYPTED, "ENCRYPTED"),
+];
+
+impl FileAttributes
+{
+  /// name of each set flag
+  pub fn names(&self) -> Vec<&'static str>
+  {
+    FILE_ATTRIBUTES_NAMES.iter().filter(|(flag, _)| self.contains(*flag)).map(|(_, name)| *name).collect()
+  }
+}
+
+/// reference to a MFT entry, the sequence allow to check that the entry wasn't reused
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "plugin", derive(Reflect))]
+pub struct FileReference
+{
+  pub entry : u64,
+  pub sequence : u16,
+}
+
+/// readable representation of FileAttributes
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "plugin", derive(Reflect))]
+pub struct FileAttributesFlags
+{
+  pub names : String,
+  pub value : u32,
+}
+
+#[cfg(feature = "plugin")]
+pub fn option_copy_to_value<T>(value : &Option<T>) -> Option<Value>
+  where T : Into<Value> + Copy
+{
+  value.map(|value| value.into())
+}
+
+#[cfg(feature = "plugin")]
+pub fn file_attributes_to_value(flags : &FileAttributes) -> Option<Value>
+{
+  Some(Value::ReflectStruct(Arc::new(FileAttributesFlags{ names : flags.names().join(", "), value : flags.bits() })))
+}
+
+#[cfg(feature = "plugin")]
+pub fn option_file_attributes_to_value(flags : &Option<FileAttributes>) -> Option<Value>
+{
+  flags.as_ref().and_then(file_attributes_to_value)
+}
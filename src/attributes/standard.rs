@@ -1,57 +1,61 @@
-use std::sync::Arc;
-use std::io::Read;
+use std::fmt;
 
-use tap::vfile::VFileBuilder;
+#[cfg(feature = "plugin")]
 use tap::reflect::{ReflectStruct};
+#[cfg(feature = "plugin")]
 use tap::value::Value;
 use tap::datetime::WindowsTimestamp;
+#[cfg(feature = "plugin")]
 use tap_derive::Reflect;
 
 use anyhow::Result;
 use byteorder::{ByteOrder, LittleEndian};
 use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
 
 use crate::error::NtfsError;
 use crate::attributes::FileAttributes;
+#[cfg(feature = "plugin")]
+use crate::attributes::{file_attributes_to_value, option_copy_to_value};
 
-#[derive(Debug, Reflect, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "plugin", derive(Reflect))]
 pub struct StandardInformation
 {
   pub creation_time : DateTime<Utc>,
   pub altered_time : DateTime<Utc>,
   pub mft_altered_time : DateTime<Utc>,
   pub accessed_time : DateTime<Utc>,
-  #[reflect(skip)]
+  #[cfg_attr(feature = "plugin", reflect(with = "file_attributes_to_value"))]
   pub flags : FileAttributes,
   pub version_maximum_number : u32,
   pub version_number : u32,
   pub class_id : u32,
 
-  #[reflect(skip)]
+  //only present since NTFS 3.0
+  #[cfg_attr(feature = "plugin", reflect(with = "option_copy_to_value"))]
   pub owner_id : Option<u32>,
-  #[reflect(skip)]
+  #[cfg_attr(feature = "plugin", reflect(with = "option_copy_to_value"))]
   pub security_id : Option<u32>,
-  #[reflect(skip)]
+  #[cfg_attr(feature = "plugin", reflect(with = "option_copy_to_value"))]
   pub quota_charged : Option<u64>,
-  #[reflect(skip)]
+  #[cfg_attr(feature = "plugin", reflect(with = "option_copy_to_value"))]
   pub usn : Option<u64>,
 }
 
 impl StandardInformation
 {
-  pub fn new(content : Arc<dyn VFileBuilder>) -> Result<Self>
+  pub fn new(content : &[u8]) -> Result<Self>
   {
-    let size = content.size();
+    let size = content.len() as u64;
     
-    if size < 48 && size != 72
+    //NTFS 1.x only store the first 48 bytes
+    if size < 48
     {
-      return Err(NtfsError::MftAttributeStandardInvalidSize.into())
+      return Err(NtfsError::MftAttributeStandardInvalidSize{ size }.into())
     };
    
-    let mut file = content.open()?;
-
-    let mut data = [0;48]; 
-    file.read_exact(&mut data)?;
+    let data = &content[0..48];
 
     let creation_time = WindowsTimestamp(LittleEndian::read_u64(&data[0..8])).to_datetime()?;
     let altered_time  = WindowsTimestamp(LittleEndian::read_u64(&data[8..16])).to_datetime()?;
@@ -62,10 +66,9 @@ impl StandardInformation
     let version_number = LittleEndian::read_u32(&data[40..44]);
     let class_id = LittleEndian::read_u32(&data[44..48]);
 
-    if size == 72
+    if size >= 72
     {
-      let mut data = [0; 24];
-      file.read_exact(&mut data)?;
+      let data = &content[48..72];
 
       let owner_id = Some(LittleEndian::read_u32(&data[0..4]));
       let security_id = Some(LittleEndian::read_u32(&data[4..8]));
@@ -106,3 +109,13 @@ impl StandardInformation
     }
   }
 }
+
+impl fmt::Display for StandardInformation
+{
+  fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result
+  {
+    write!(f, "created {} modified {} mft modified {} accessed {} flags [{}]",
+           self.creation_time.format("%Y-%m-%d %H:%M:%S"), self.altered_time.format("%Y-%m-%d %H:%M:%S"),
+           self.mft_altered_time.format("%Y-%m-%d %H:%M:%S"), self.accessed_time.format("%Y-%m-%d %H:%M:%S"), self.flags.names().join(", "))
+  }
+}
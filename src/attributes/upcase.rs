@@ -0,0 +1,44 @@
+use anyhow::Result;
+use byteorder::{ByteOrder, LittleEndian};
+
+/**
+ *  $UpCase table (entry 10), upper case value of each UTF-16 code unit 
+ *  used by NTFS to compare names case-insensitively
+ */
+#[derive(Debug, Clone, Default)]
+pub struct UpCase
+{
+  table : Vec<u16>,
+}
+
+impl UpCase
+{
+  pub fn new(content : &[u8]) -> Result<Self>
+  {
+    let table = content.chunks_exact(2).map(LittleEndian::read_u16).collect();
+    Ok(UpCase{ table })
+  }
+
+  /// upper case a code unit, ASCII is used if the table is empty or too small
+  pub fn to_upper(&self, unit : u16) -> u16
+  {
+    match self.table.get(unit as usize)
+    {
+      Some(upper) => *upper,
+      None if (b'a' as u16..=b'z' as u16).contains(&unit) => unit - 0x20,
+      None => unit,
+    }
+  }
+
+  /// upper case a name as NTFS does, code unit by code unit
+  pub fn upcase(&self, name : &str) -> Vec<u16>
+  {
+    name.encode_utf16().map(|unit| self.to_upper(unit)).collect()
+  }
+
+  /// compare two names case-insensitively 
+  pub fn equals(&self, left : &str, right : &str) -> bool
+  {
+    left.encode_utf16().map(|unit| self.to_upper(unit)).eq(right.encode_utf16().map(|unit| self.to_upper(unit)))
+  }
+}
@@ -1,31 +1,24 @@
 #![allow(dead_code)]
-use std::sync::Arc;
-use std::io::SeekFrom;
-use std::io::Seek;
-
-use tap::vfile::{VFileBuilder, read_utf16_exact};
-
 use anyhow::Result;
+use serde::{Serialize, Deserialize};
 
-#[derive(Debug)]
+use crate::attributecontent::{record_slice, record_utf16};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VolumeInformation
 {
-  version : String,
-  major   : u8,
-  minor   : u8,
+  pub version : String,
+  pub major   : u8,
+  pub minor   : u8,
   //flags :
 }
 
 //XXX add as node attribute 
 impl VolumeInformation 
 {
-  pub fn new(content : Arc<dyn VFileBuilder>) -> Result<Self>
+  pub fn new(content : &[u8]) -> Result<Self>
   {
-    let mut file = content.open()?;
-
-    file.seek(SeekFrom::Start(8))?;
-    let mut data = [0;4]; 
-    file.read_exact(&mut data)?;
+    let data = record_slice(content, 8, 4)?;
 
     let major = data[0];
     let minor = data[1];
@@ -54,23 +47,26 @@ impl VolumeInformation
       minor,
     })
   }
+
+  /// NT4 era volume, without $Extend and with 48 bytes $STANDARD_INFORMATION
+  pub fn is_legacy(&self) -> bool
+  {
+    self.major < 3
+  }
 }
 
 //XXX add as node attribute 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VolumeName
 {
-  name : String,
+  pub name : String,
 }
 
 impl VolumeName
 {
-  pub fn new(content : Arc<dyn VFileBuilder>) -> Result<Self>
+  pub fn new(content : &[u8]) -> Result<Self>
   {
-    let mut file = content.open()?;
-
-    //check size XXX (at least mft entry size)
-    let name = read_utf16_exact(&mut file, content.size() as usize)?;
+    let name = record_utf16(content, 0, content.len())?;
 
     Ok(VolumeName{ name })
   }
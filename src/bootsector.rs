@@ -1,20 +1,47 @@
+use std::fmt;
 use std::sync::Arc;
 use std::io::SeekFrom;
 
 use tap::vfile::{VFile, VFileBuilder};
+#[cfg(feature = "plugin")]
 use tap::reflect::{ReflectStruct};
+#[cfg(feature = "plugin")]
 use tap::value::Value;
 use tap::mappedvfile::{MappedVFileBuilder,FileRanges};
+#[cfg(feature = "plugin")]
 use tap::node::Node;
+#[cfg(feature = "plugin")]
 use tap_derive::Reflect;
+#[cfg(feature = "plugin")]
 use tap::attribute::Attributes;
 
 use anyhow::Result;
+use serde::{Serialize, Deserialize};
 use byteorder::{ByteOrder, LittleEndian};
 
 use crate::error::NtfsError;
 
-#[derive(Debug, Reflect)]
+/// largest cluster size supported by Windows (2MB since Windows 10 1709)
+pub const MAX_CLUSTER_SIZE : u64 = 2 * 1024 * 1024;
+/// index record size used by every Windows version, used when the boot sector value is damaged
+const DEFAULT_INDEX_RECORD_SIZE : u32 = 4096;
+/// OEM id written by Windows when formatting a volume
+const NTFS_OEM_ID : &[u8; 8] = b"NTFS    ";
+
+/// serial number as displayed by Windows by dir or vol, from its low 32 bits (XXXX-XXXX)
+pub fn serial_string(serial : u64) -> String
+{
+  format!("{:04X}-{:04X}", (serial >> 16) & 0xffff, serial & 0xffff)
+}
+
+/// full 64 bits serial number as displayed by fsutil, event logs and LNK parsers (XXXX-XXXX-XXXX-XXXX)
+pub fn serial_hex_string(serial : u64) -> String
+{
+  format!("{:04X}-{:04X}-{:04X}-{:04X}", serial >> 48, (serial >> 32) & 0xffff, (serial >> 16) & 0xffff, serial & 0xffff)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "plugin", derive(Reflect))]
 pub struct BPB
 {
   pub bytes_per_sector                  : u16,
@@ -29,13 +56,39 @@ pub struct BPB
   pub checksum                          : u32, 
 }
 
-#[derive(Debug, Reflect)]
+/**
+ *  Geometry known by the analyst, replace the boot sector values
+ *  so a volume with a damaged boot sector can still be parsed
+ */
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Geometry
+{
+  pub sector_size : Option<u16>,
+  pub cluster_size : Option<u64>,
+  pub mft_record_size : Option<u32>,
+}
+
+impl Geometry
+{
+  fn is_empty(&self) -> bool
+  {
+    self.sector_size.is_none() && self.cluster_size.is_none() && self.mft_record_size.is_none()
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "plugin", derive(Reflect))]
 pub struct BootSector
 {
-  pub oem_id : u64,
+  /// OEM id as ASCII, other bytes are escaped as \xNN
+  pub oem_id : String,
+  /// the OEM id is "NTFS    ", imaging or tampering tools can write another one
+  pub is_standard_oem_id : bool,
   pub bpb : Arc<BPB>,
+  /// 64 bits serial number of the BPB formatted as XXXX-XXXX-XXXX-XXXX
+  pub volume_serial : String,
   pub end_of_sector : u16,
-  pub cluster_size : u32,
+  pub cluster_size : u64,
   pub mft_record_size : u32,
   pub index_record_size : u32
   //end_of_sector : u16,
@@ -44,27 +97,41 @@ pub struct BootSector
 impl BootSector
 {
   pub fn from_file<T : VFile>(file : &mut T) -> Result<BootSector>
+  {
+    BootSector::from_file_with(file, &Geometry::default())
+  }
+
+  /// parse the boot sector using the values of `geometry` instead of the ones read when they are set,
+  /// the end of sector marker is not checked if a value is overridden
+  pub fn from_file_with<T : VFile>(file : &mut T, geometry : &Geometry) -> Result<BootSector>
   {
     file.seek(SeekFrom::Start(0))?;
 
     let mut data = [0;512]; 
     file.read_exact(&mut data)?;
+    BootSector::from_bytes(&data, geometry)
+  }
+
+  /// parse a boot sector read in memory, see `from_file_with`
+  pub fn from_bytes(data : &[u8; 512], geometry : &Geometry) -> Result<BootSector>
+  {
     //first 3 u8 contain the jmp code
-    let oem_id = LittleEndian::read_u64(&data[3..3+8]); //we read the OEMID
+    let oem_id = BootSector::oem_id_string(&data[3..3+8]); //we read the OEMID
+    let is_standard_oem_id = &data[3..3+8] == NTFS_OEM_ID;
     let end_of_sector = LittleEndian::read_u16(&data[510..512]);
-    if end_of_sector != 0xAA55
+    if end_of_sector != 0xAA55 && geometry.is_empty()
     {
       return Err(NtfsError::BootSectorInvalid("end of sector").into())
     };
 
-    let bytes_per_sector = LittleEndian::read_u16(&data[0xb..0xd]);
+    let bytes_per_sector = geometry.sector_size.unwrap_or_else(|| LittleEndian::read_u16(&data[0xb..0xd]));
     if bytes_per_sector == 0 || bytes_per_sector % 512 != 0
     {
       return Err(NtfsError::BootSectorInvalid("bytes per sector").into())
     }
 
     let sector_per_cluster = data[0xd];
-    if sector_per_cluster == 0
+    if sector_per_cluster == 0 && geometry.cluster_size.is_none()
     {
       return Err(NtfsError::BootSectorInvalid("sector per cluster").into())
     }
@@ -82,36 +149,40 @@ impl BootSector
       return Err(NtfsError::BootSectorInvalid("MFT logical cluster number").into())
     }
     let clusters_per_mft_record = data[0x40] as i8;
-    if clusters_per_mft_record == 0
+    if clusters_per_mft_record == 0 && geometry.mft_record_size.is_none()
     {
       return Err(NtfsError::BootSectorInvalid("invalid cluster per MFT record").into())
     }
     let clusters_per_index_record = data[0x44] as i8;
-    if clusters_per_index_record == 0
+    if clusters_per_index_record == 0 && geometry.is_empty()
     {
       return Err(NtfsError::BootSectorInvalid("invalid cluster per index buffer").into())
     }
     let volume_serial_number = LittleEndian::read_u64(&data[0x48..0x48+8]);
     let checksum = LittleEndian::read_u32(&data[0x50..0x54]);
 
-    let cluster_size : u32 = sector_per_cluster as u32 * bytes_per_sector as u32;
-  
-    let mft_record_size : u32 = if clusters_per_mft_record > 0 
+    let cluster_size : u64 = match geometry.cluster_size
     {
-      clusters_per_mft_record as u32 * cluster_size
-    }
-    else
-    {
-       1 << (-clusters_per_mft_record) as i32
+      Some(cluster_size) if cluster_size.is_power_of_two() && cluster_size % bytes_per_sector as u64 == 0 => cluster_size,
+      Some(_) => return Err(NtfsError::BootSectorInvalid("cluster size").into()),
+      None => BootSector::sectors_per_cluster(sector_per_cluster).and_then(|sectors| sectors.checked_mul(bytes_per_sector as u64))
+                                                                  .ok_or(NtfsError::BootSectorInvalid("sector per cluster"))?,
     };
-
-    let index_record_size : u32 = if clusters_per_index_record > 0
+    if cluster_size > MAX_CLUSTER_SIZE
     {
-      clusters_per_index_record as u32 * cluster_size
+      return Err(NtfsError::BootSectorInvalid("cluster size").into())
     }
-    else
+  
+    let mft_record_size = match geometry.mft_record_size
     {
-      1 << (-clusters_per_index_record) as i32
+      Some(mft_record_size) if mft_record_size.is_power_of_two() && mft_record_size >= bytes_per_sector as u32 => mft_record_size,
+      Some(_) => return Err(NtfsError::BootSectorInvalid("MFT record size").into()),
+      None => BootSector::record_size(clusters_per_mft_record, cluster_size).ok_or(NtfsError::BootSectorInvalid("MFT record size"))?,
+    };
+    let index_record_size = match clusters_per_index_record
+    {
+      0 => DEFAULT_INDEX_RECORD_SIZE,
+      _ => BootSector::record_size(clusters_per_index_record, cluster_size).ok_or(NtfsError::BootSectorInvalid("index record size"))?,
     };
  
 
@@ -130,6 +201,8 @@ impl BootSector
 
     Ok(BootSector{ 
       oem_id,
+      is_standard_oem_id,
+      volume_serial : serial_hex_string(bpb.volume_serial_number),
       bpb : Arc::new(bpb),
       end_of_sector,
       cluster_size,
@@ -138,11 +211,94 @@ impl BootSector
     })
   }
 
+  /// values above 0x80 are the negative power of two of sector per cluster (64K+ clusters),
+  /// None if the shift doesn't fit in 64 bits, the cluster size is checked afterward
+  fn sectors_per_cluster(sector_per_cluster : u8) -> Option<u64>
+  {
+    match sector_per_cluster > 0x80
+    {
+      true => 1u64.checked_shl(256 - sector_per_cluster as u32),
+      false => Some(sector_per_cluster as u64),
+    }
+  }
+
+  /// printable ASCII characters of the OEM id, other bytes are escaped so they stay visible
+  fn oem_id_string(oem_id : &[u8]) -> String
+  {
+    oem_id.iter().map(|byte| match byte
+    {
+      0x20..=0x7e => (*byte as char).to_string(),
+      _ => format!("\\x{:02x}", byte),
+    }).collect()
+  }
+
+  /// size of a record described as a number of clusters if positive or as a power of two if negative
+  fn record_size(clusters_per_record : i8, cluster_size : u64) -> Option<u32>
+  {
+    if clusters_per_record > 0
+    {
+      (clusters_per_record as u64).checked_mul(cluster_size).and_then(|size| u32::try_from(size).ok())
+    }
+    else
+    {
+      1u32.checked_shl(clusters_per_record.unsigned_abs() as u32)
+    }
+  }
+
+  /// serial number as displayed by Windows (low 32 bits, XXXX-XXXX)
+  pub fn volume_serial_string(&self) -> String
+  {
+    serial_string(self.bpb.volume_serial_number)
+  }
+
+  /// full 64 bits serial number (XXXX-XXXX-XXXX-XXXX)
+  pub fn volume_serial_hex(&self) -> String
+  {
+    serial_hex_string(self.bpb.volume_serial_number)
+  }
+
   pub fn size(&self) -> u64
   {
     self.bpb.bytes_per_sector as u64 * 16
   }
 
+  /// number of clusters of the volume, the backup boot sector is after the last cluster
+  pub fn cluster_count(&self) -> u64
+  {
+    self.bpb.total_sectors.saturating_mul(self.bpb.bytes_per_sector as u64) / self.cluster_size
+  }
+
+  /// offset in the partition of a logical cluster number, None on overflow
+  pub fn lcn_to_offset(&self, lcn : u64) -> Option<u64>
+  {
+    lcn.checked_mul(self.cluster_size)
+  }
+
+  /// logical cluster number containing a partition offset
+  pub fn offset_to_lcn(&self, offset : u64) -> u64
+  {
+    offset / self.cluster_size
+  }
+
+  /// offset in the partition of the first record of $MFT
+  pub fn mft_offset(&self) -> Option<u64>
+  {
+    self.lcn_to_offset(self.bpb.mft_logical_cluster_number)
+  }
+
+  /// offset in the partition of the first record of $MFTMirr
+  pub fn mft_mirror_offset(&self) -> Option<u64>
+  {
+    self.lcn_to_offset(self.bpb.mft_mirror_logical_cluster_number)
+  }
+
+  /// offset in the partition of the record `n` if the MFT was contiguous,
+  /// only reliable for the system records in the first run of the MFT, use MftEntries otherwise
+  pub fn mft_record_offset(&self, n : u64) -> Option<u64>
+  {
+    n.checked_mul(self.mft_record_size as u64)?.checked_add(self.mft_offset()?)
+  }
+
   pub fn to_builder(&self, builder : Arc<dyn VFileBuilder>) -> Arc<dyn VFileBuilder>
   {
     let mut file_ranges = FileRanges::new();
@@ -154,6 +310,7 @@ impl BootSector
     Arc::new(MappedVFileBuilder::new(file_ranges))
   }
 
+  #[cfg(feature = "plugin")]
   pub fn add_attribute(self, node : &Node, parent_builder : Arc<dyn VFileBuilder>)
   {
     let boot_sector_data = self.to_builder(parent_builder);
@@ -166,6 +323,7 @@ impl BootSector
     node.value().add_attribute("datatype", "ntfs/bootsector", None);
   }
 
+  #[cfg(feature = "plugin")]
   pub fn to_node(self, parent_builder : Arc<dyn VFileBuilder>) -> Node
   {
     let boot_sector_data = self.to_builder(parent_builder);
@@ -180,3 +338,56 @@ impl BootSector
     boot_sector_node
   }
 }
+
+impl fmt::Display for BootSector
+{
+  fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result
+  {
+    write!(f, "NTFS volume {} {} bytes : sectors {} bytes, clusters {} bytes, records {} bytes, $MFT at cluster {}, $MFTMirr at cluster {}",
+           self.volume_serial_string(), self.bpb.total_sectors.saturating_mul(self.bpb.bytes_per_sector as u64),
+           self.bpb.bytes_per_sector, self.cluster_size, self.mft_record_size,
+           self.bpb.mft_logical_cluster_number, self.bpb.mft_mirror_logical_cluster_number)?;
+    if !self.is_standard_oem_id
+    {
+      write!(f, ", OEM id \"{}\"", self.oem_id)?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests
+{
+  use super::*;
+
+  /// boot sector of a 1GB volume with 4K clusters
+  fn boot_sector(sector_per_cluster : u8) -> [u8; 512]
+  {
+    let mut data = [0u8; 512];
+    data[3..11].copy_from_slice(NTFS_OEM_ID);
+    LittleEndian::write_u16(&mut data[0xb..0xd], 512);
+    data[0xd] = sector_per_cluster;
+    LittleEndian::write_u64(&mut data[0x28..0x30], 2 * 1024 * 1024);
+    LittleEndian::write_u64(&mut data[0x30..0x38], 4);
+    LittleEndian::write_u64(&mut data[0x38..0x40], 2);
+    data[0x40] = 0xf6;
+    data[0x44] = 1;
+    LittleEndian::write_u16(&mut data[510..512], 0xAA55);
+    data
+  }
+
+  #[test]
+  fn valid_sector_per_cluster()
+  {
+    let boot_sector = BootSector::from_bytes(&boot_sector(8), &Geometry::default()).unwrap();
+    assert_eq!(boot_sector.cluster_size, 4096);
+  }
+
+  #[test]
+  fn invalid_sector_per_cluster()
+  {
+    //shift of 127 and 64 bits
+    assert!(BootSector::from_bytes(&boot_sector(0x81), &Geometry::default()).is_err());
+    assert!(BootSector::from_bytes(&boot_sector(0xC0), &Geometry::default()).is_err());
+  }
+}
@@ -0,0 +1,39 @@
+use std::io::Cursor;
+use std::sync::Arc;
+
+use tap::vfile::{VFile, VFileBuilder};
+
+use anyhow::Result;
+use serde::{Serialize, Deserialize};
+
+/**
+ *  Builder reading data generated in memory,
+ *  used to add the exports of a volume to the tree
+ */
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BufferVFileBuilder
+{
+  data : Arc<[u8]>,
+}
+
+impl BufferVFileBuilder
+{
+  pub fn new(data : Vec<u8>) -> Self
+  {
+    BufferVFileBuilder{ data : data.into() }
+  }
+}
+
+#[typetag::serde]
+impl VFileBuilder for BufferVFileBuilder
+{
+  fn open(&self) -> Result<Box<dyn VFile>>
+  {
+    Ok(Box::new(Cursor::new(self.data.clone())))
+  }
+
+  fn size(&self) -> u64
+  {
+    self.data.len() as u64
+  }
+}
@@ -0,0 +1,142 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::Result;
+use serde::{Serialize, Deserialize};
+
+use crate::error::NtfsError;
+use crate::mft::MftEntries;
+use crate::mapping::MappedRange;
+use crate::ntfs::NtfsNodeAttribute;
+
+/// version of the cache format, cache of an other version are ignored
+const CACHE_VERSION : u32 = 1;
+
+/// initial value of an FNV-1a hash
+pub const FNV_OFFSET : u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME : u64 = 0x0000_0100_0000_01b3;
+
+/// FNV-1a hash, stable between builds so it can be used as a cache key
+pub fn fnv1a(hash : u64, data : &[u8]) -> u64
+{
+  data.iter().fold(hash, |hash, byte| (hash ^ *byte as u64).wrapping_mul(FNV_PRIME))
+}
+
+/// builder a cached range is read from
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CachedSource
+{
+  Partition,
+  Mft,
+  Zero,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedRange
+{
+  pub range : Range<u64>,
+  pub offset : u64,
+  pub source : CachedSource,
+}
+
+impl CachedRange
+{
+  /// describe a mapping relatively to the builders of the MFT, None if it use an other builder
+  pub fn from_mapping(mapping : &[MappedRange], mft_entries : &MftEntries) -> Option<Vec<CachedRange>>
+  {
+    mapping.iter().map(|mapped|
+    {
+      let source = if Arc::ptr_eq(&mapped.builder, mft_entries.master_mft_builder())
+      {
+        CachedSource::Mft
+      }
+      else if mft_entries.partition_builder().is_some_and(|builder| Arc::ptr_eq(&mapped.builder, builder))
+      {
+        CachedSource::Partition
+      }
+      else if mft_entries.zero_builder().is_some_and(|builder| Arc::ptr_eq(&mapped.builder, builder))
+      {
+        CachedSource::Zero
+      }
+      else
+      {
+        return None
+      };
+      Some(CachedRange{ range : mapped.range.clone(), offset : mapped.offset, source })
+    }).collect()
+  }
+
+  /// recreate a mapping with the builders of the MFT
+  pub fn to_mapping(ranges : &[CachedRange], mft_entries : &MftEntries) -> Option<Vec<MappedRange>>
+  {
+    ranges.iter().map(|cached|
+    {
+      let builder = match cached.source
+      {
+        CachedSource::Mft => mft_entries.master_mft_builder().clone(),
+        CachedSource::Partition => mft_entries.partition_builder()?.clone(),
+        CachedSource::Zero => mft_entries.zero_builder()?.clone(),
+      };
+      Some(MappedRange::new(cached.range.clone(), cached.offset, builder))
+    }).collect()
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedNode
+{
+  pub name : String,
+  pub attributes : NtfsNodeAttribute,
+  pub data : Option<Vec<CachedRange>>,
+}
+
+/// result of the parsing of a record
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum CachedEntry
+{
+  Nodes(Vec<CachedNode>),
+  Reserved(String),
+  Unused,
+  Error(String),
+  Skipped,
+}
+
+/**
+ *  MetadataCache
+ *  Parsed records saved on disk so the MFT walk can be skipped when the same volume is parsed again
+ */
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MetadataCache
+{
+  version : u32,
+  pub entries : Vec<(u64, CachedEntry)>,
+}
+
+impl MetadataCache
+{
+  pub fn new(entries : Vec<(u64, CachedEntry)>) -> Self
+  {
+    MetadataCache{ version : CACHE_VERSION, entries }
+  }
+
+  pub fn load(path : &Path) -> Result<Self>
+  {
+    let file = BufReader::new(File::open(path)?);
+    let cache : MetadataCache = bincode::deserialize_from(file)?;
+    if cache.version != CACHE_VERSION
+    {
+      return Err(NtfsError::MetadataCacheVersion(cache.version).into())
+    }
+    Ok(cache)
+  }
+
+  pub fn save(&self, path : &Path) -> Result<()>
+  {
+    let file = BufWriter::new(File::create(path)?);
+    bincode::serialize_into(file, self)?;
+    Ok(())
+  }
+}
@@ -0,0 +1,97 @@
+use std::ops::Range;
+use std::sync::Arc;
+
+use serde::{Serialize, Deserialize};
+
+use crate::ntfsattributes::NtfsAttributeType;
+
+/**
+ *  Attribute owning a run of clusters
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterOwner
+{
+  /// base entry of the attribute, extension records are resolved to their base record
+  pub entry_id : u64,
+  pub type_id : NtfsAttributeType,
+  /// name of the attribute, None for the unnamed stream
+  pub name : Option<String>,
+  /// the entry is not in use anymore, its clusters may have been reallocated
+  pub is_deleted : bool,
+}
+
+/// clusters of the volume used by an attribute starting at `vcn` in the attribute
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterRun
+{
+  pub lcn : Range<u64>,
+  pub vcn : u64,
+  pub owner : Arc<ClusterOwner>,
+}
+
+/**
+ *  ClusterMap
+ *  Reverse map of the runs of every non-resident attribute, used to find which file owned a cluster.
+ *  Runs of deleted entries can overlap the runs of allocated ones so a cluster can have multiple owners
+ */
+#[derive(Debug, Default)]
+pub struct ClusterMap
+{
+  runs : Vec<ClusterRun>, //sorted by first lcn
+  max_end : Vec<u64>, //highest lcn end of the runs up to each index, to stop searching overlapping runs
+}
+
+impl ClusterMap
+{
+  pub fn new(mut runs : Vec<ClusterRun>) -> Self
+  {
+    runs.sort_by_key(|run| run.lcn.start);
+    let max_end = runs.iter().scan(0, |max_end, run| { *max_end = std::cmp::max(*max_end, run.lcn.end); Some(*max_end) }).collect();
+    ClusterMap{ runs, max_end }
+  }
+
+  /// runs containing the cluster `lcn`
+  pub fn find(&self, lcn : u64) -> Vec<&ClusterRun>
+  {
+    let end = self.runs.partition_point(|run| run.lcn.start <= lcn);
+    (0..end).rev()
+            .take_while(|index| self.max_end[*index] > lcn)
+            .map(|index| &self.runs[index])
+            .filter(|run| run.lcn.contains(&lcn))
+            .collect()
+  }
+
+  pub fn runs(&self) -> &[ClusterRun]
+  {
+    &self.runs
+  }
+
+  pub fn len(&self) -> usize
+  {
+    self.runs.len()
+  }
+
+  pub fn is_empty(&self) -> bool
+  {
+    self.runs.is_empty()
+  }
+}
+
+/**
+ *  Stream containing a byte of the partition
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OffsetOwner
+{
+  /// path of the entry from the root of the volume
+  pub path : String,
+  pub entry_id : u64,
+  pub type_id : NtfsAttributeType,
+  /// name of the attribute, None for the unnamed stream
+  pub name : Option<String>,
+  /// virtual cluster of the attribute containing the offset
+  pub vcn : u64,
+  /// offset of the byte inside the attribute content
+  pub stream_offset : u64,
+  pub is_deleted : bool,
+}
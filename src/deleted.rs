@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+
+#[cfg(feature = "plugin")]
+use tap::value::Value;
+#[cfg(feature = "plugin")]
+use tap::reflect::ReflectStruct;
+#[cfg(feature = "plugin")]
+use tap_derive::Reflect;
+use serde::{Serialize, Deserialize};
+
+#[cfg(feature = "plugin")]
+use crate::attributes::option_copy_to_value;
+
+/**
+ *  DeletionHints
+ *  NTFS doesn't store deletion time, journals parser can register here the 
+ *  events that let us estimate it (last FILE_DELETE USN record)
+ */
+#[derive(Debug, Clone, Default)]
+pub struct DeletionHints
+{
+  hints : HashMap<(u64, u16), DeletionHint>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DeletionHint
+{
+  pub usn_time : Option<DateTime<Utc>>,
+}
+
+impl DeletionHints
+{
+  pub fn new() -> Self
+  {
+    DeletionHints::default()
+  }
+
+  /// register a $UsnJrnl FILE_DELETE record, only the most recent is kept
+  pub fn add_usn_delete(&mut self, entry : u64, sequence : u16, time : DateTime<Utc>)
+  {
+    let hint = self.hints.entry((entry, sequence)).or_default();
+    if hint.usn_time.map(|current| current < time).unwrap_or(true)
+    {
+      hint.usn_time = Some(time);
+    }
+  }
+
+  /// sequence is incremented when a record is freed, journals reference the previous one
+  pub fn find(&self, entry : u64, sequence : u16) -> Option<&DeletionHint>
+  {
+    self.hints.get(&(entry, sequence.wrapping_sub(1))).or_else(|| self.hints.get(&(entry, sequence)))
+  }
+
+  pub fn is_empty(&self) -> bool
+  {
+    self.hints.is_empty()
+  }
+}
+
+/**
+ *  Best effort deletion time of a deleted node
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "plugin", derive(Reflect))]
+pub struct DeletedTime
+{
+  /// time of the last FILE_DELETE USN record
+  #[cfg_attr(feature = "plugin", reflect(with = "option_copy_to_value"))]
+  pub estimated_time : Option<DateTime<Utc>>,
+}
+
+impl DeletedTime
+{
+  pub fn from_hint(hint : &DeletionHint) -> Self
+  {
+    DeletedTime{ estimated_time : hint.usn_time }
+  }
+}
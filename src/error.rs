@@ -1,59 +1,97 @@
 use thiserror::Error;
 
+use crate::ntfsattributes::NtfsAttributeType;
+
 #[derive(Error, Debug, Clone)]
 pub enum NtfsError
 {
   #[error("MFT record size is 0")]
   MftRecordSize, 
 
+  #[error("MFT record size {0} is smaller than the record header")]
+  MftRecordSizeTooSmall(u32),
+
+  #[error("MFT record size {0} is larger than 4096 bytes")]
+  MftRecordSizeTooLarge(u64),
+
   #[error("No partition provided to read non-resident attribute data")]
   NonResidentData,
   
   #[error("Boot sector as an invalid {0} value")]
   BootSectorInvalid(&'static str),
 
-  #[error("MFT entry is unused")]
-  MftUnusedEntry,
+  #[error("Metadata cache version {0} is not supported")]
+  MetadataCacheVersion(u32),
+
+  #[error("MFT entry {entry} is unused")]
+  MftUnusedEntry{ entry : u64 },
 
-  #[error("MFT signature is invalid")]
-  MftInvalidSignature,
+  #[error("MFT entry {entry} fixup array at offset {offset} with {count} entries is larger than the record")]
+  MftFixupArrayTooLarge{ entry : u64, offset : u16, count : u16 },
 
-  #[error("MFT Attribute {0} not found")]
-  MftAttributeNotFound(&'static str),
+  #[error("MFT record read of {size} bytes at offset {offset} is past the end of the record")]
+  MftRecordOutOfBounds{ offset : u64, size : u64 },
 
-  #[error("MFT attribute unknown type {0}")]
-  MftAttributeUnknownType(u32),
+  #[error("MFT entry {entry} signature {signature:#x} is invalid")]
+  MftInvalidSignature{ entry : u64, signature : u32 },
+
+  #[error("MFT entry {entry} attribute {attribute} not found")]
+  MftAttributeNotFound{ entry : u64, attribute : &'static str },
+
+  #[error("MFT entry {entry} attribute at offset {offset} has unknown type {type_id:#x}")]
+  MftAttributeUnknownType{ type_id : u32, entry : u64, offset : u32 },
 
   #[error("MFT attributes end")]
   MftAttributesEnd,
 
-  #[error("MFT attribute unknown data type")]
-  MftAttributeDataType,
+  #[error("MFT entry {entry} attribute {type_id:?} at offset {offset} has unknown data type {flag}")]
+  MftAttributeDataType{ type_id : NtfsAttributeType, entry : u64, offset : u32, flag : u8 },
 
   #[error("MFT Attribute FileName unknown name space {0}")]
   MftAttributeUnknownNameSpace(u8),
 
-  #[error("MFT Attribute FileName name space size is invalid")]
-  MftAttributeNameSpaceInvalidSize,
+  #[error("MFT Attribute FileName name length {name_length} is larger than attribute size {size}")]
+  MftAttributeNameSpaceInvalidSize{ name_length : u8, size : u64 },
 
-  #[error("MFT Attribute Standard Information size is invalid")]
-  MftAttributeStandardInvalidSize,
+  #[error("MFT Attribute Standard Information size {size} is invalid")]
+  MftAttributeStandardInvalidSize{ size : u64 },
 
   #[error("MFT Attribute List end")]
   MftAttributeListEnd,
 
-  #[error("Resident attribute offset is large than MFT")]
-  ResidentAttributeOffsetTooLarge,
+  #[error("MFT Attribute List item at offset {offset} has unknown type {type_id:#x}")]
+  MftAttributeListUnknownType{ type_id : u32, offset : u64 },
+
+  #[error("MFT entry {entry} resident attribute {type_id:?} offset {offset} is larger than MFT")]
+  ResidentAttributeOffsetTooLarge{ type_id : NtfsAttributeType, entry : u64, offset : u64 },
 
-  #[error("Resident attribute content size is larger than MFT")]
-  ResidentAttributeContentTooLarge,
+  #[error("MFT entry {entry} resident attribute {type_id:?} content size {size} at offset {offset} is larger than MFT")]
+  ResidentAttributeContentTooLarge{ type_id : NtfsAttributeType, entry : u64, offset : u64, size : u64 },
 
-  #[error("Non resident attribute offset is larger than partition")]
-  NonResidentAttributeOffsetTooLarge,
+  #[error("MFT entry {entry} non resident attribute {type_id:?} run offset {offset} is larger than partition")]
+  NonResidentAttributeOffsetTooLarge{ type_id : NtfsAttributeType, entry : u64, offset : u64 },
 
   #[error("Non resident attribute require a zero builder to read sparse attribute")]
   NonResidentAttributeZeroBuilder,
 
   #[error("Non resident attribute require cluster size to be read")]
   NonResidentAttributeClusterSize,
+
+  #[error("Content of {size} bytes is larger than the {max} bytes read in memory")]
+  ContentTooLarge{ size : u64, max : u64 },
+
+  #[error("Arithmetic overflow while computing {0}")]
+  Overflow(&'static str),
+
+  #[error("Path {0} not found")]
+  PathNotFound(String),
+
+  #[error("Volume offset {offset} is past the end of the data of size {size}")]
+  VolumeOffset{ offset : u64, size : u64 },
+
+  #[error("USN record at offset {offset} has unsupported version {version}")]
+  UsnRecordVersion{ offset : u64, version : u16 },
+
+  #[error("$LogFile has an invalid {0}")]
+  LogFileInvalid(&'static str),
 }
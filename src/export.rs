@@ -0,0 +1,101 @@
+//! exports of the metadata of a volume to the formats read by other tools, without creating the tree
+
+use std::io::Write;
+#[cfg(feature = "sqlite")]
+use std::fs::{self, File};
+#[cfg(feature = "sqlite")]
+use std::io;
+
+use anyhow::Result;
+use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
+
+use crate::ntfs::Ntfs;
+use crate::ntfs::walk::WalkFilter;
+use crate::export::timeline::TimelineOptions;
+
+pub mod bodyfile;
+pub mod listing;
+pub mod timeline;
+pub mod l2tcsv;
+pub mod dfxml;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+/// format written by `export`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
+pub enum ExportFormat
+{
+  /// TSK bodyfile read by mactime, a line for $STANDARD_INFORMATION and one for $FILE_NAME
+  #[default]
+  Bodyfile,
+  /// listing with a row by entry and by alternate data stream with the eight timestamps
+  Csv,
+  /// same rows as Csv with a JSON object by line
+  Jsonl,
+  /// log2timeline CSV with the events of the timestamps and of the journals in chronological order
+  L2tCsv,
+  /// events of the timestamps and of the journals in chronological order with a JSON object by line
+  Timeline,
+  /// chronological events of each entry with a JSON object by entry
+  EntryTimeline,
+  /// client records of $LogFile ordered by LSN with a JSON object by line, they have no timestamp so they are not in the timelines
+  LogRecords,
+  /// DFXML with the volume and a fileobject by entry and by alternate data stream with their byte runs
+  Dfxml,
+  /// SQLite database with the entries, names, attributes and runs
+  #[cfg(feature = "sqlite")]
+  Sqlite,
+}
+
+impl ExportFormat
+{
+  /// extension of a file written in this format
+  pub fn extension(&self) -> &'static str
+  {
+    match self
+    {
+      ExportFormat::Bodyfile => "body",
+      ExportFormat::Csv => "csv",
+      ExportFormat::Jsonl => "jsonl",
+      ExportFormat::L2tCsv => "csv",
+      ExportFormat::Timeline => "jsonl",
+      ExportFormat::EntryTimeline => "jsonl",
+      ExportFormat::LogRecords => "jsonl",
+      ExportFormat::Dfxml => "xml",
+      #[cfg(feature = "sqlite")]
+      ExportFormat::Sqlite => "sqlite",
+    }
+  }
+}
+
+/// write the entries matching `filter` in `format`, return the number of records written,
+/// `timeline_options` selects the journals merged in the timeline formats
+pub fn export<W : Write>(ntfs : &Ntfs, filter : &WalkFilter, format : ExportFormat, timeline_options : &TimelineOptions, output : &mut W) -> Result<u64>
+{
+  match format
+  {
+    ExportFormat::Bodyfile => bodyfile::write_bodyfile(ntfs.walk(filter), output),
+    ExportFormat::Csv => listing::write_csv(ntfs.walk(filter), output),
+    ExportFormat::Jsonl => listing::write_jsonl(ntfs.walk(filter), output),
+    ExportFormat::L2tCsv => l2tcsv::write_l2tcsv(timeline::timeline(ntfs, filter, timeline_options).into_iter(), output),
+    ExportFormat::Timeline => timeline::write_timeline(timeline::timeline(ntfs, filter, timeline_options).into_iter(), output),
+    ExportFormat::EntryTimeline => timeline::write_entry_timelines(&timeline::entry_timelines(timeline::timeline(ntfs, filter, timeline_options)), output),
+    ExportFormat::LogRecords => timeline::write_log_records(timeline::log_records(ntfs)?.into_iter(), output),
+    ExportFormat::Dfxml => dfxml::write_dfxml(ntfs, ntfs.walk(filter), output),
+    #[cfg(feature = "sqlite")]
+    ExportFormat::Sqlite =>
+    {
+      //SQLite needs a file, the database is created in the temporary directory then copied to the output
+      let path = std::env::temp_dir().join(format!("tap-plugin-ntfs-{}-{:p}.sqlite", std::process::id(), ntfs));
+      let result = sqlite::write_sqlite(ntfs, ntfs.walk(filter), &path).and_then(|count|
+      {
+        io::copy(&mut File::open(&path)?, output)?;
+        output.flush()?;
+        Ok(count)
+      });
+      let _ = fs::remove_file(&path);
+      result
+    },
+  }
+}
@@ -0,0 +1,95 @@
+//! TSK 3 bodyfile, `MD5|name|inode|mode|UID|GID|size|atime|mtime|ctime|crtime` with times in seconds since the epoch,
+//! inodes use the TSK NTFS `entry-type-id` format of the attribute holding the content or the name
+
+use std::io::Write;
+
+use chrono::{DateTime, Utc};
+use anyhow::Result;
+
+use crate::ntfs::walk::WalkEntry;
+
+/// type of the attribute in the inode of a file, $DATA
+const DATA_TYPE : u32 = 128;
+/// type of the attribute in the inode of a directory, $INDEX_ROOT
+const INDEX_ROOT_TYPE : u32 = 144;
+/// type of the attribute in the inode of the $FILE_NAME lines
+const FILE_NAME_TYPE : u32 = 48;
+
+/// seconds since the epoch, 0 is used by mactime for unknown times
+fn epoch(time : Option<DateTime<Utc>>) -> i64
+{
+  time.map(|time| time.timestamp()).unwrap_or(0)
+}
+
+/// `|` separates the fields and a new line the entries, they are replaced with `\` and the control characters
+/// by their \xNN escape so mactime reads the right fields
+fn escape_name(name : &str) -> String
+{
+  let mut escaped = String::with_capacity(name.len());
+  for c in name.chars()
+  {
+    match c
+    {
+      c if c == '|' || c == '\\' || c.is_control() => escaped.push_str(&format!("\\x{:02x}", c as u32)),
+      c => escaped.push(c),
+    }
+  }
+  escaped
+}
+
+/// TSK inode of the content or of the $FILE_NAME of an entry, the entry id alone if it has no such attribute
+fn inode(entry : &WalkEntry, file_name : bool) -> String
+{
+  let attribute = match (file_name, entry.is_directory)
+  {
+    (true, _) => Some((FILE_NAME_TYPE, entry.file_name_attribute_id)),
+    (false, true) => entry.attribute_id.map(|id| (INDEX_ROOT_TYPE, id)),
+    (false, false) => entry.attribute_id.map(|id| (DATA_TYPE, id)),
+  };
+  match attribute
+  {
+    Some((type_id, id)) => format!("{}-{}-{}", entry.entry_id, type_id, id),
+    None => entry.entry_id.to_string(),
+  }
+}
+
+/// line of an entry, `file_name` select the $FILE_NAME timestamps instead of the $STANDARD_INFORMATION ones
+pub fn bodyfile_line(entry : &WalkEntry, file_name : bool) -> String
+{
+  let mut name = escape_name(&entry.path);
+  if file_name
+  {
+    name.push_str(" ($FILE_NAME)");
+  }
+  if entry.is_deleted
+  {
+    name.push_str(" (deleted)");
+  }
+  let mode = match entry.is_directory
+  {
+    true => "d/drwxrwxrwx",
+    false => "r/rrwxrwxrwx",
+  };
+  let (accessed, modified, changed, created) = match file_name
+  {
+    true => (Some(entry.file_name_accessed_time), Some(entry.file_name_modification_time), Some(entry.file_name_mft_modification_time), Some(entry.file_name_creation_time)),
+    false => (entry.accessed_time, entry.modification_time, entry.mft_modification_time, entry.creation_time),
+  };
+
+  format!("0|{}|{}|{}|0|0|{}|{}|{}|{}|{}", name, inode(entry, file_name), mode, entry.size,
+          epoch(accessed), epoch(modified), epoch(changed), epoch(created))
+}
+
+/// write a $STANDARD_INFORMATION and a $FILE_NAME line for each entry, return the number of lines written
+pub fn write_bodyfile<W : Write>(entries : impl Iterator<Item = WalkEntry>, output : &mut W) -> Result<u64>
+{
+  let mut count = 0;
+  for entry in entries
+  {
+    writeln!(output, "{}", bodyfile_line(&entry, false))?;
+    writeln!(output, "{}", bodyfile_line(&entry, true))?;
+    count += 2;
+  }
+  output.flush()?;
+  Ok(count)
+}
@@ -0,0 +1,167 @@
+//! DFXML description of the volume and of its files with the runs of their data streams, without hashes
+
+use std::io::Write;
+use std::sync::Arc;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use anyhow::Result;
+
+use crate::ntfs::Ntfs;
+use crate::ntfs::walk::WalkEntry;
+use crate::ntfsattributes::NtfsAttributeType;
+
+const DFXML_NAMESPACE : &str = "http://www.forensicswiki.org/wiki/Category:Digital_Forensics_XML";
+const DFXML_VERSION : &str = "1.2.0";
+
+/// run of a data stream, resident and sparse data have no run
+struct ByteRun
+{
+  file_offset : u64,
+  fs_offset : u64,
+  len : u64,
+}
+
+/// escape the characters that can't be used in XML text and attributes
+fn xml_escape(text : &str) -> String
+{
+  let mut escaped = String::with_capacity(text.len());
+  for c in text.chars()
+  {
+    match c
+    {
+      '&' => escaped.push_str("&amp;"),
+      '<' => escaped.push_str("&lt;"),
+      '>' => escaped.push_str("&gt;"),
+      '"' => escaped.push_str("&quot;"),
+      '\'' => escaped.push_str("&apos;"),
+      //control characters are not allowed in XML 1.0
+      c if (c as u32) < 0x20 && !matches!(c, '\t' | '\n' | '\r') => escaped.push_str(&format!("\\x{:02x}", c as u32)),
+      c => escaped.push(c),
+    }
+  }
+  escaped
+}
+
+fn dfxml_time(time : DateTime<Utc>) -> String
+{
+  time.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+/// runs of a data stream in the partition, empty if the stream is resident or can't be read
+fn byte_runs(ntfs : &Ntfs, entry_id : u64, stream : Option<&str>) -> Vec<ByteRun>
+{
+  let partition_builder = match ntfs.mft_entries().partition_builder()
+  {
+    Some(partition_builder) => partition_builder,
+    None => return Vec::new(),
+  };
+  let entry = match ntfs.mft_entries().entry(entry_id)
+  {
+    Ok(entry) => entry,
+    Err(_) => return Vec::new(),
+  };
+  let (contents, _) = entry.all_contents(Some(ntfs.mft_entries()));
+  let mapping = contents.into_iter()
+                        .find(|content| content.mft_attribute.type_id == NtfsAttributeType::Data && content.mft_attribute.name.as_deref() == stream)
+                        .and_then(|content| content.mapping().ok())
+                        .unwrap_or_default();
+
+  //ranges mapped to the zero builder are sparse, and resident data is mapped to the MFT
+  mapping.iter().filter(|mapped| Arc::ptr_eq(&mapped.builder, partition_builder)).map(|mapped|
+    ByteRun{ file_offset : mapped.range.start, fs_offset : mapped.offset, len : mapped.range.end - mapped.range.start }).collect()
+}
+
+fn write_fileobject<W : Write>(ntfs : &Ntfs, entry : &WalkEntry, stream : Option<(&str, u64)>, output : &mut W) -> Result<()>
+{
+  let (filename, size) = match stream
+  {
+    Some((name, size)) => (format!("{}:{}", entry.path, name), size),
+    None => (entry.path.clone(), entry.size),
+  };
+  let (name_type, meta_type) = match entry.is_directory
+  {
+    true => ("d", 2),
+    false => ("r", 1),
+  };
+
+  writeln!(output, "    <fileobject>")?;
+  writeln!(output, "      <filename>{}</filename>", xml_escape(&filename))?;
+  writeln!(output, "      <name_type>{}</name_type>", name_type)?;
+  writeln!(output, "      <filesize>{}</filesize>", size)?;
+  match entry.is_deleted
+  {
+    true => writeln!(output, "      <unalloc>1</unalloc>")?,
+    false => writeln!(output, "      <alloc>1</alloc>")?,
+  }
+  writeln!(output, "      <inode>{}</inode>", entry.entry_id)?;
+  writeln!(output, "      <meta_type>{}</meta_type>", meta_type)?;
+  writeln!(output, "      <seq>{}</seq>", entry.sequence)?;
+  let times = [("mtime", entry.modification_time), ("ctime", entry.mft_modification_time), ("atime", entry.accessed_time), ("crtime", entry.creation_time)];
+  for (tag, time) in times.iter().filter_map(|(tag, time)| Some((tag, (*time)?)))
+  {
+    writeln!(output, "      <{}>{}</{}>", tag, dfxml_time(time), tag)?;
+  }
+
+  let runs = byte_runs(ntfs, entry.entry_id, stream.map(|(name, _)| name));
+  if !runs.is_empty()
+  {
+    writeln!(output, "      <byte_runs>")?;
+    for run in runs
+    {
+      writeln!(output, "        <byte_run file_offset='{}' fs_offset='{}' len='{}'/>", run.file_offset, run.fs_offset, run.len)?;
+    }
+    writeln!(output, "      </byte_runs>")?;
+  }
+  writeln!(output, "    </fileobject>")?;
+  Ok(())
+}
+
+/// write the volume and a fileobject for each entry and each of its alternate data streams, return the number of fileobjects written
+pub fn write_dfxml<W : Write>(ntfs : &Ntfs, entries : impl Iterator<Item = WalkEntry>, output : &mut W) -> Result<u64>
+{
+  let mft_entries = ntfs.mft_entries();
+  let cluster_size = mft_entries.cluster_size().unwrap_or(0);
+  let block_count = match (mft_entries.partition_builder(), cluster_size)
+  {
+    (Some(partition_builder), cluster_size) if cluster_size != 0 => partition_builder.size() / cluster_size,
+    _ => 0,
+  };
+
+  writeln!(output, "<?xml version='1.0' encoding='UTF-8'?>")?;
+  writeln!(output, "<dfxml xmlns='{}' version='{}'>", DFXML_NAMESPACE, DFXML_VERSION)?;
+  writeln!(output, "  <creator>")?;
+  writeln!(output, "    <program>{}</program>", env!("CARGO_PKG_NAME"))?;
+  writeln!(output, "    <version>{}</version>", env!("CARGO_PKG_VERSION"))?;
+  writeln!(output, "  </creator>")?;
+  writeln!(output, "  <volume offset='0'>")?;
+  writeln!(output, "    <partition_offset>0</partition_offset>")?;
+  writeln!(output, "    <sector_size>{}</sector_size>", mft_entries.sector_size())?;
+  writeln!(output, "    <block_size>{}</block_size>", cluster_size)?;
+  writeln!(output, "    <ftype_str>ntfs</ftype_str>")?;
+  writeln!(output, "    <block_count>{}</block_count>", block_count)?;
+  if let Some(volume_serial) = ntfs.volume_serial()
+  {
+    writeln!(output, "    <volume_serial>{:016x}</volume_serial>", volume_serial)?;
+  }
+  if let Some(volume_name) = ntfs.volume_name()
+  {
+    writeln!(output, "    <volume_label>{}</volume_label>", xml_escape(volume_name))?;
+  }
+
+  let mut count = 0;
+  for entry in entries
+  {
+    write_fileobject(ntfs, &entry, None, output)?;
+    count += 1;
+    for (name, size) in entry.ads.iter()
+    {
+      write_fileobject(ntfs, &entry, Some((name, *size)), output)?;
+      count += 1;
+    }
+  }
+
+  writeln!(output, "  </volume>")?;
+  writeln!(output, "</dfxml>")?;
+  output.flush()?;
+  Ok(count)
+}
@@ -0,0 +1,47 @@
+//! log2timeline CSV format read by the super timeline tools, a line by event
+
+use std::io::Write;
+
+use anyhow::Result;
+
+use crate::export::listing::csv_field;
+use crate::export::timeline::{EventSource, TimelineEvent};
+
+const L2TCSV_HEADER : &str = "date,time,timezone,MACB,source,sourcetype,type,user,host,short,desc,version,filename,inode,notes,format,extra";
+
+/// line of an event, times are in UTC
+pub fn l2tcsv_line(event : &TimelineEvent) -> String
+{
+  let source_type = format!("NTFS {}", event.source.as_str());
+  let short = match event.source
+  {
+    EventSource::UsnJrnl => format!("{} {}", event.description, event.path),
+    _ => event.path.clone(),
+  };
+  let deleted = match event.is_deleted
+  {
+    true => " (deleted)",
+    false => "",
+  };
+  let desc = format!("{}{} Entry: {} Sequence: {}", event.path, deleted, event.entry_id, event.sequence);
+
+  let fields = [event.time.format("%m/%d/%Y").to_string(), event.time.format("%H:%M:%S").to_string(), "UTC".to_string(),
+                event.macb.clone(), "FILE".to_string(), source_type, csv_field(&event.description), "-".to_string(), "-".to_string(),
+                csv_field(&short), csv_field(&desc), "2".to_string(), csv_field(&event.path), event.entry_id.to_string(),
+                "-".to_string(), "tap-plugin-ntfs".to_string(), "-".to_string()];
+  fields.join(",")
+}
+
+/// write a header and a line for each event, return the number of events written
+pub fn write_l2tcsv<W : Write>(events : impl Iterator<Item = TimelineEvent>, output : &mut W) -> Result<u64>
+{
+  let mut count = 0;
+  writeln!(output, "{}", L2TCSV_HEADER)?;
+  for event in events
+  {
+    writeln!(output, "{}", l2tcsv_line(&event))?;
+    count += 1;
+  }
+  output.flush()?;
+  Ok(count)
+}
@@ -0,0 +1,130 @@
+//! listing of the MFT with a row by entry and by alternate data stream, as CSV or as a JSON object by line
+
+use std::io::Write;
+
+use chrono::{DateTime, Utc};
+use anyhow::Result;
+use serde::{Serialize, Deserialize};
+
+use crate::ntfs::walk::WalkEntry;
+
+const CSV_HEADER : &str = "path,entry_id,sequence,is_deleted,is_directory,stream,size,flags,\
+si_creation_time,si_modification_time,si_mft_modification_time,si_accessed_time,\
+fn_creation_time,fn_modification_time,fn_mft_modification_time,fn_accessed_time";
+
+/**
+ *  ListingRow
+ *  Row of the listing, `stream` is None for the row of the entry and its unnamed data stream
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListingRow
+{
+  pub path : String,
+  pub entry_id : u64,
+  pub sequence : u16,
+  pub is_deleted : bool,
+  pub is_directory : bool,
+  pub stream : Option<String>,
+  pub size : u64,
+  /// names of the $STANDARD_INFORMATION flags separated by |
+  pub flags : String,
+  pub si_creation_time : Option<DateTime<Utc>>,
+  pub si_modification_time : Option<DateTime<Utc>>,
+  pub si_mft_modification_time : Option<DateTime<Utc>>,
+  pub si_accessed_time : Option<DateTime<Utc>>,
+  pub fn_creation_time : DateTime<Utc>,
+  pub fn_modification_time : DateTime<Utc>,
+  pub fn_mft_modification_time : DateTime<Utc>,
+  pub fn_accessed_time : DateTime<Utc>,
+}
+
+impl ListingRow
+{
+  /// row of the entry followed by a row for each of its alternate data streams
+  pub fn from_entry(entry : &WalkEntry) -> Vec<ListingRow>
+  {
+    let row = ListingRow{
+      path : entry.path.clone(),
+      entry_id : entry.entry_id,
+      sequence : entry.sequence,
+      is_deleted : entry.is_deleted,
+      is_directory : entry.is_directory,
+      stream : None,
+      size : entry.size,
+      flags : entry.flags.map(|flags| flags.names().join("|")).unwrap_or_default(),
+      si_creation_time : entry.creation_time,
+      si_modification_time : entry.modification_time,
+      si_mft_modification_time : entry.mft_modification_time,
+      si_accessed_time : entry.accessed_time,
+      fn_creation_time : entry.file_name_creation_time,
+      fn_modification_time : entry.file_name_modification_time,
+      fn_mft_modification_time : entry.file_name_mft_modification_time,
+      fn_accessed_time : entry.file_name_accessed_time,
+    };
+
+    let ads_rows : Vec<ListingRow> = entry.ads.iter().map(|(name, size)| ListingRow{ stream : Some(name.clone()), size : *size, ..row.clone() }).collect();
+    let mut rows = vec![row];
+    rows.extend(ads_rows);
+    rows
+  }
+
+  fn to_csv(&self) -> String
+  {
+    let fields = [csv_field(&self.path), self.entry_id.to_string(), self.sequence.to_string(), self.is_deleted.to_string(),
+                  self.is_directory.to_string(), self.stream.as_deref().map(csv_field).unwrap_or_default(), self.size.to_string(),
+                  csv_field(&self.flags), csv_time(self.si_creation_time), csv_time(self.si_modification_time),
+                  csv_time(self.si_mft_modification_time), csv_time(self.si_accessed_time), csv_time(Some(self.fn_creation_time)),
+                  csv_time(Some(self.fn_modification_time)), csv_time(Some(self.fn_mft_modification_time)), csv_time(Some(self.fn_accessed_time))];
+    fields.join(",")
+  }
+}
+
+/// quote a field containing a separator, a quote or a new line
+pub(crate) fn csv_field(field : &str) -> String
+{
+  match field.contains([',', '"', '\n', '\r'])
+  {
+    true => format!("\"{}\"", field.replace('"', "\"\"")),
+    false => field.to_string(),
+  }
+}
+
+/// time with the 100ns precision of NTFS, empty if unknown
+pub(crate) fn csv_time(time : Option<DateTime<Utc>>) -> String
+{
+  time.map(|time| time.format("%Y-%m-%dT%H:%M:%S%.7fZ").to_string()).unwrap_or_default()
+}
+
+/// write a header and the rows of each entry, return the number of rows written
+pub fn write_csv<W : Write>(entries : impl Iterator<Item = WalkEntry>, output : &mut W) -> Result<u64>
+{
+  let mut count = 0;
+  writeln!(output, "{}", CSV_HEADER)?;
+  for entry in entries
+  {
+    for row in ListingRow::from_entry(&entry)
+    {
+      writeln!(output, "{}", row.to_csv())?;
+      count += 1;
+    }
+  }
+  output.flush()?;
+  Ok(count)
+}
+
+/// write each row as a JSON object on its own line, return the number of rows written
+pub fn write_jsonl<W : Write>(entries : impl Iterator<Item = WalkEntry>, output : &mut W) -> Result<u64>
+{
+  let mut count = 0;
+  for entry in entries
+  {
+    for row in ListingRow::from_entry(&entry)
+    {
+      serde_json::to_writer(&mut *output, &row)?;
+      writeln!(output)?;
+      count += 1;
+    }
+  }
+  output.flush()?;
+  Ok(count)
+}
@@ -0,0 +1,106 @@
+//! SQLite database of the entries, names, attributes and runs of a volume, for triage with SQL queries
+
+use std::path::Path;
+use std::sync::Arc;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use anyhow::Result;
+use rusqlite::{params, Connection, Transaction};
+
+use crate::ntfs::Ntfs;
+use crate::ntfs::walk::WalkEntry;
+use crate::attributecontent::ResidentType;
+
+const SCHEMA : &str = "
+CREATE TABLE entries(entry_id INTEGER PRIMARY KEY, sequence INTEGER, path TEXT, is_deleted INTEGER, is_directory INTEGER,
+                     size INTEGER, ads_size INTEGER, flags INTEGER, si_creation_time TEXT, si_modification_time TEXT,
+                     si_mft_modification_time TEXT, si_accessed_time TEXT);
+CREATE TABLE names(entry_id INTEGER, parent_entry_id INTEGER, parent_sequence INTEGER, name TEXT, name_space TEXT,
+                   creation_time TEXT, modification_time TEXT, mft_modification_time TEXT, accessed_time TEXT,
+                   allocated_size INTEGER, real_size INTEGER);
+CREATE TABLE attributes(entry_id INTEGER, type_id INTEGER, type TEXT, name TEXT, resident INTEGER, size INTEGER);
+CREATE TABLE runs(entry_id INTEGER, type_id INTEGER, name TEXT, vcn INTEGER, lcn INTEGER, length INTEGER);
+";
+
+/// indexes are created after the inserts as it's faster than updating them on each row
+const INDEXES : &str = "
+CREATE INDEX names_name ON names(name);
+CREATE INDEX names_parent ON names(parent_entry_id);
+CREATE INDEX attributes_entry ON attributes(entry_id);
+CREATE INDEX runs_lcn ON runs(lcn);
+CREATE INDEX runs_entry ON runs(entry_id);
+";
+
+/// ISO 8601 time that can be compared and used by the SQLite date functions
+fn sql_time(time : Option<DateTime<Utc>>) -> Option<String>
+{
+  time.map(|time| time.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+}
+
+/// insert the names, attributes and runs of an entry, read again from the MFT as the walk only keep one name
+fn insert_entry(ntfs : &Ntfs, transaction : &Transaction, entry : &WalkEntry) -> Result<()>
+{
+  transaction.prepare_cached("INSERT INTO entries VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)")?
+             .execute(params![entry.entry_id as i64, entry.sequence, entry.path, entry.is_deleted, entry.is_directory,
+                              entry.size as i64, entry.ads_size as i64, entry.flags.map(|flags| flags.bits()),
+                              sql_time(entry.creation_time), sql_time(entry.modification_time),
+                              sql_time(entry.mft_modification_time), sql_time(entry.accessed_time)])?;
+
+  let mft_entry = ntfs.mft_entries().entry(entry.entry_id)?;
+  let attributes = mft_entry.attributes_iter(Some(ntfs.mft_entries())).into_attributes();
+  let mut insert_name = transaction.prepare_cached("INSERT INTO names VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)")?;
+  for file_name in attributes.find_filenames()
+  {
+    insert_name.execute(params![entry.entry_id as i64, file_name.parent_mft_entry_id as i64, file_name.parent_sequence, file_name.file_name,
+                                file_name.name_space.as_str(), sql_time(Some(file_name.creation_time)), sql_time(Some(file_name.modification_time)),
+                                sql_time(Some(file_name.mft_modification_time)), sql_time(Some(file_name.accessed_time)),
+                                file_name.allocated_size as i64, file_name.real_size as i64])?;
+  }
+
+  let cluster_size = ntfs.mft_entries().cluster_size().filter(|cluster_size| *cluster_size != 0);
+  let partition_builder = ntfs.mft_entries().partition_builder();
+  let mut insert_attribute = transaction.prepare_cached("INSERT INTO attributes VALUES(?1, ?2, ?3, ?4, ?5, ?6)")?;
+  let mut insert_run = transaction.prepare_cached("INSERT INTO runs VALUES(?1, ?2, ?3, ?4, ?5, ?6)")?;
+  let (contents, _) = mft_entry.all_contents(Some(ntfs.mft_entries()));
+  for content in contents
+  {
+    let attribute = &content.mft_attribute;
+    let type_id = attribute.type_id.clone() as u32;
+    let resident = matches!(attribute.data, ResidentType::Resident(_));
+    let size = content.builder().map(|builder| builder.size() as i64).ok();
+    insert_attribute.execute(params![entry.entry_id as i64, type_id, format!("{:?}", attribute.type_id), attribute.name, resident, size])?;
+
+    let (cluster_size, partition_builder) = match (cluster_size, partition_builder)
+    {
+      (Some(cluster_size), Some(partition_builder)) if !resident => (cluster_size, partition_builder),
+      _ => continue,
+    };
+    //sparse ranges are mapped to the zero builder and have no clusters
+    for mapped in content.mapping().unwrap_or_default().iter().filter(|mapped| Arc::ptr_eq(&mapped.builder, partition_builder))
+    {
+      let length = (mapped.range.end - mapped.range.start).div_ceil(cluster_size);
+      insert_run.execute(params![entry.entry_id as i64, type_id, attribute.name, (mapped.range.start / cluster_size) as i64,
+                                 (mapped.offset / cluster_size) as i64, length as i64])?;
+    }
+  }
+  Ok(())
+}
+
+/// create the database at `path` with the entries, return the number of entries inserted
+pub fn write_sqlite(ntfs : &Ntfs, entries : impl Iterator<Item = WalkEntry>, path : &Path) -> Result<u64>
+{
+  let mut connection = Connection::open(path)?;
+  connection.execute_batch(SCHEMA)?;
+
+  let transaction = connection.transaction()?;
+  let mut count = 0;
+  for entry in entries
+  {
+    insert_entry(ntfs, &transaction, &entry)?;
+    count += 1;
+  }
+  transaction.commit()?;
+
+  connection.execute_batch(INDEXES)?;
+  Ok(count)
+}
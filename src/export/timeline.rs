@@ -0,0 +1,224 @@
+//! events of the timeline built from the timestamps of the entries and from the journals of the volume
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+
+use chrono::{DateTime, Utc};
+use anyhow::Result;
+use log::warn;
+use serde::{Serialize, Deserialize};
+
+use crate::ntfs::{Ntfs, ROOT_ENTRY, USNJRNL_PATH, USNJRNL_STREAM};
+use crate::ntfs::walk::{WalkEntry, WalkFilter};
+use crate::ntfsattributes::NtfsAttributeType;
+use crate::usnjrnl::{UsnRecord, UsnRecords};
+use crate::logfile::{LogFile, LogRecord};
+
+const LOGFILE_PATH : &str = "/$LogFile";
+
+/// attribute or journal an event was read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EventSource
+{
+  StandardInformation,
+  FileName,
+  UsnJrnl,
+}
+
+impl EventSource
+{
+  pub fn as_str(&self) -> &'static str
+  {
+    match self
+    {
+      EventSource::StandardInformation => "$STANDARD_INFORMATION",
+      EventSource::FileName => "$FILE_NAME",
+      EventSource::UsnJrnl => "$UsnJrnl",
+    }
+  }
+}
+
+/**
+ *  TimelineEvent
+ *  Something that happened to an entry at a time, timestamps of an attribute sharing the same time are a single event
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineEvent
+{
+  pub time : DateTime<Utc>,
+  /// letters of the timestamps at this time in MACB order, . for the others
+  pub macb : String,
+  pub source : EventSource,
+  /// type of the timestamps or reasons of the journal record
+  pub description : String,
+  pub path : String,
+  pub entry_id : u64,
+  pub sequence : u16,
+  pub is_deleted : bool,
+}
+
+/// journals whose events are merged with the timestamps of the entries
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TimelineOptions
+{
+  pub usnjrnl : bool,
+}
+
+impl Default for TimelineOptions
+{
+  fn default() -> Self
+  {
+    TimelineOptions{ usnjrnl : true }
+  }
+}
+
+/// (letter, description) of the timestamps in MACB order
+const MACB : [(char, &str); 4] = [('M', "Content Modification Time"), ('A', "Last Access Time"), ('C', "Metadata Modification Time"), ('B', "Creation Time")];
+
+/// events of the $STANDARD_INFORMATION and $FILE_NAME timestamps of an entry
+pub fn entry_events(entry : &WalkEntry) -> Vec<TimelineEvent>
+{
+  let standard_information = [entry.modification_time, entry.accessed_time, entry.mft_modification_time, entry.creation_time];
+  let file_name = [entry.file_name_modification_time, entry.file_name_accessed_time, entry.file_name_mft_modification_time, entry.file_name_creation_time].map(Some);
+
+  let mut events = Vec::new();
+  for (source, times) in [(EventSource::StandardInformation, standard_information), (EventSource::FileName, file_name)]
+  {
+    let mut distinct : Vec<DateTime<Utc>> = times.iter().flatten().copied().collect();
+    distinct.sort();
+    distinct.dedup();
+    for time in distinct
+    {
+      let macb : String = MACB.iter().zip(times.iter()).map(|((letter, _), other)| if *other == Some(time) { *letter } else { '.' }).collect();
+      let description : Vec<&str> = MACB.iter().zip(times.iter()).filter(|(_, other)| **other == Some(time)).map(|((_, description), _)| *description).collect();
+      events.push(TimelineEvent{ time, macb, source, description : description.join("; "), path : entry.path.clone(),
+                                 entry_id : entry.entry_id, sequence : entry.sequence, is_deleted : entry.is_deleted });
+    }
+  }
+  events
+}
+
+/// event of a $UsnJrnl record, `path` is the path of its parent followed by its name
+pub fn usn_event(record : &UsnRecord, path : String) -> TimelineEvent
+{
+  TimelineEvent{
+    time : record.timestamp,
+    macb : "....".to_string(),
+    source : EventSource::UsnJrnl,
+    description : record.reason.names().join(" | "),
+    path,
+    entry_id : record.entry,
+    sequence : record.sequence,
+    is_deleted : false,
+  }
+}
+
+/// events of the records of the $UsnJrnl:$J stream of the volume, an empty list if the volume has no journal
+pub fn usn_events(ntfs : &Ntfs) -> Result<Vec<TimelineEvent>>
+{
+  let (entry_id, _) = match ntfs.open_path(USNJRNL_PATH)
+  {
+    Ok(usnjrnl) => usnjrnl,
+    Err(_) => return Ok(Vec::new()),
+  };
+  let builder = ntfs.attribute_builder(entry_id, NtfsAttributeType::Data, Some(USNJRNL_STREAM))?;
+
+  let mut parent_paths : HashMap<u64, String> = HashMap::new();
+  let mut events = Vec::new();
+  //invalid records are skipped by the iterator, an error is a read error so we stop there
+  for record in UsnRecords::new(builder)?.map_while(Result::ok)
+  {
+    let parent_path = parent_paths.entry(record.parent_entry).or_insert_with(|| match record.parent_entry
+    {
+      ROOT_ENTRY => String::new(),
+      parent_entry => ntfs.entry_path(parent_entry).unwrap_or_else(|_| format!("/Unknown_{}", parent_entry)),
+    });
+    let path = format!("{}/{}", parent_path, record.file_name);
+    events.push(usn_event(&record, path));
+  }
+  Ok(events)
+}
+
+/// client records of the $LogFile sorted by LSN, an empty list if the volume has no $LogFile.
+/// Records have no timestamp so they are exported apart from the timelines
+pub fn log_records(ntfs : &Ntfs) -> Result<Vec<LogRecord>>
+{
+  let (entry_id, _) = match ntfs.open_path(LOGFILE_PATH)
+  {
+    Ok(logfile) => logfile,
+    Err(_) => return Ok(Vec::new()),
+  };
+  let builder = ntfs.attribute_builder(entry_id, NtfsAttributeType::Data, None)?;
+
+  //the log is circular, records are read in page order
+  let mut records : Vec<LogRecord> = LogFile::new(builder)?.records()?.map_while(Result::ok).filter(|record| record.redo_length != 0 || record.undo_length != 0).collect();
+  records.sort_by_key(|record| record.lsn);
+  Ok(records)
+}
+
+/// write a $LogFile record by line as a JSON object, return the number of records written
+pub fn write_log_records<W : Write>(records : impl Iterator<Item = LogRecord>, output : &mut W) -> Result<u64>
+{
+  let mut count = 0;
+  for record in records
+  {
+    serde_json::to_writer(&mut *output, &record)?;
+    writeln!(output)?;
+    count += 1;
+  }
+  output.flush()?;
+  Ok(count)
+}
+
+/// events of the entries matching `filter` and of the enabled journals in chronological order,
+/// a journal that can't be read is skipped so the events of the entries are still returned
+pub fn timeline(ntfs : &Ntfs, filter : &WalkFilter, options : &TimelineOptions) -> Vec<TimelineEvent>
+{
+  let mut events : Vec<TimelineEvent> = ntfs.walk(filter).flat_map(|entry| entry_events(&entry)).collect();
+  if options.usnjrnl
+  {
+    events.extend(usn_events(ntfs).unwrap_or_else(|err| { warn!("Can't read $UsnJrnl : {}", err); Vec::new() }));
+  }
+  //stable sort so the journal records keep their order when they share a time
+  events.sort_by_key(|event| event.time);
+  events
+}
+
+/// chronological events of each entry, journal events are grouped with the entry they are about
+pub fn entry_timelines(events : Vec<TimelineEvent>) -> BTreeMap<u64, Vec<TimelineEvent>>
+{
+  let mut timelines : BTreeMap<u64, Vec<TimelineEvent>> = BTreeMap::new();
+  for event in events
+  {
+    timelines.entry(event.entry_id).or_default().push(event);
+  }
+  timelines
+}
+
+/// write an event by line as a JSON object, return the number of events written
+pub fn write_timeline<W : Write>(events : impl Iterator<Item = TimelineEvent>, output : &mut W) -> Result<u64>
+{
+  let mut count = 0;
+  for event in events
+  {
+    serde_json::to_writer(&mut *output, &event)?;
+    writeln!(output)?;
+    count += 1;
+  }
+  output.flush()?;
+  Ok(count)
+}
+
+/// write an entry by line as a JSON object with its id and its events, return the number of events written
+pub fn write_entry_timelines<W : Write>(timelines : &BTreeMap<u64, Vec<TimelineEvent>>, output : &mut W) -> Result<u64>
+{
+  let mut count = 0;
+  for (entry_id, events) in timelines
+  {
+    serde_json::to_writer(&mut *output, &serde_json::json!({ "entry_id" : entry_id, "events" : events }))?;
+    writeln!(output)?;
+    count += events.len() as u64;
+  }
+  output.flush()?;
+  Ok(count)
+}
@@ -0,0 +1,99 @@
+//! tap plugin exporting the metadata of the partition found in the data of a node to a timeline format,
+//! to a file or to a new node without creating the whole tree
+
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::BufWriter;
+use std::sync::Arc;
+
+use tap::plugin;
+use tap::config_schema;
+use tap::node::Node;
+use tap::vfile::VFileBuilder;
+use tap::error::RustructError;
+use tap::tree::{TreeNodeId, TreeNodeIdSchema};
+use tap::plugin::{PluginInfo, PluginInstance, PluginConfig, PluginArgument, PluginResult, PluginEnvironment};
+
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use schemars::JsonSchema;
+
+use crate::bootsector::BootSector;
+use crate::buffervfile::BufferVFileBuilder;
+use crate::export::{export, ExportFormat};
+use crate::export::timeline::TimelineOptions;
+use crate::mapping::offset_builder;
+use crate::ntfs::Ntfs;
+use crate::ntfs::walk::WalkFilter;
+use crate::options::NtfsOptions;
+
+plugin!("ntfs_export", "File system", "Export the metadata of a NTFS filesystem to a timeline format", NtfsExportPlugin, Arguments);
+
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Arguments
+{
+  #[schemars(with = "TreeNodeIdSchema")]
+  file : TreeNodeId,
+  ///byte offset of the volume in the data of the node (0 by default)
+  offset : Option<u64>,
+  ///format of the export (Bodyfile by default)
+  format : Option<ExportFormat>,
+  ///export deleted entries (true by default)
+  include_deleted : Option<bool>,
+  ///merge the $UsnJrnl records in the timeline formats (true by default)
+  usnjrnl : Option<bool>,
+  ///file where the export is written, if not set it's added as the data of an "export" node
+  output : Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize,Default)]
+pub struct Results
+{
+  ///node containing the export if no output file was set
+  export_node : Option<TreeNodeId>,
+  ///number of records written
+  records : u64,
+}
+
+#[derive(Default)]
+pub struct NtfsExportPlugin
+{
+}
+
+impl NtfsExportPlugin
+{
+  fn run(&mut self, args : Arguments, env : PluginEnvironment) -> Result<Results>
+  {
+    let file_node = env.tree.get_node_from_id(args.file).ok_or(RustructError::ArgumentNotFound("file"))?;
+    let value = file_node.value().get_value("data").ok_or(RustructError::ValueNotFound("data"))?;
+    let partition_builder = value.try_as_vfile_builder().ok_or(RustructError::ValueTypeMismatch)?;
+    let partition_builder = match args.offset
+    {
+      Some(offset) if offset != 0 => offset_builder(partition_builder, offset)?,
+      _ => partition_builder,
+    };
+
+    let boot_sector = BootSector::from_file(&mut partition_builder.open()?)?;
+    let ntfs = Ntfs::from_partition(partition_builder, &boot_sector, NtfsOptions::default())?;
+    let filter = WalkFilter::new().deleted(args.include_deleted.unwrap_or(true));
+    let format = args.format.unwrap_or_default();
+    let timeline_options = TimelineOptions{ usnjrnl : args.usnjrnl.unwrap_or(TimelineOptions::default().usnjrnl) };
+
+    if let Some(output) = args.output
+    {
+      let mut output = BufWriter::new(File::create(output)?);
+      let records = export(&ntfs, &filter, format, &timeline_options, &mut output)?;
+      return Ok(Results{ export_node : None, records })
+    }
+
+    let mut data = Vec::new();
+    let records = export(&ntfs, &filter, format, &timeline_options, &mut data)?;
+    let export_node = Node::new(format!("export.{}", format.extension()));
+    let builder : Arc<dyn VFileBuilder> = Arc::new(BufferVFileBuilder::new(data));
+    export_node.value().add_attribute("data", builder, None);
+    let export_node_id = env.tree.add_child(args.file, export_node)?;
+
+    Ok(Results{ export_node : Some(export_node_id), records })
+  }
+}
@@ -0,0 +1,116 @@
+//! tap plugin exporting the data streams of selected files of the partition found in the data of a node,
+//! to a directory or as new nodes without creating the whole tree
+
+use std::fmt::Debug;
+use std::path::Path;
+
+use tap::plugin;
+use tap::config_schema;
+use tap::node::Node;
+use tap::error::RustructError;
+use tap::tree::{TreeNodeId, TreeNodeIdSchema};
+use tap::plugin::{PluginInfo, PluginInstance, PluginConfig, PluginArgument, PluginResult, PluginEnvironment};
+
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use schemars::JsonSchema;
+
+use crate::bootsector::BootSector;
+use crate::mapping::offset_builder;
+use crate::ntfs::Ntfs;
+use crate::ntfs::walk::WalkFilter;
+use crate::ntfs::extract::ExtractFilter;
+use crate::options::NtfsOptions;
+
+plugin!("ntfs_extract", "File system", "Extract files of a NTFS filesystem", NtfsExtractPlugin, Arguments);
+
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Arguments
+{
+  #[schemars(with = "TreeNodeIdSchema")]
+  file : TreeNodeId,
+  ///byte offset of the volume in the data of the node (0 by default)
+  offset : Option<u64>,
+  ///only extract the files under these paths (e.g. /Users), compared case-insensitively
+  paths : Option<Vec<String>>,
+  ///only extract the files whose name match one of these globs using * and ?
+  names : Option<Vec<String>>,
+  ///only extract the files with one of these extensions
+  extensions : Option<Vec<String>>,
+  ///first record to extract
+  first_entry : Option<u64>,
+  ///record after the last record to extract
+  end_entry : Option<u64>,
+  ///extract deleted files under $Deleted (true by default)
+  include_deleted : Option<bool>,
+  ///extract alternate data streams as name:stream (true by default)
+  ads : Option<bool>,
+  ///directory where files are written, if not set they are added as nodes under an "extract" node
+  output : Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize,Default)]
+pub struct Results
+{
+  ///node containing the extracted streams if no output directory was set
+  extract_node : Option<TreeNodeId>,
+  ///number of data streams extracted
+  streams : u64,
+  bytes : u64,
+  errors : Vec<String>,
+}
+
+#[derive(Default)]
+pub struct NtfsExtractPlugin
+{
+}
+
+impl NtfsExtractPlugin
+{
+  fn run(&mut self, args : Arguments, env : PluginEnvironment) -> Result<Results>
+  {
+    let file_node = env.tree.get_node_from_id(args.file).ok_or(RustructError::ArgumentNotFound("file"))?;
+    let value = file_node.value().get_value("data").ok_or(RustructError::ValueNotFound("data"))?;
+    let partition_builder = value.try_as_vfile_builder().ok_or(RustructError::ValueTypeMismatch)?;
+    let partition_builder = match args.offset
+    {
+      Some(offset) if offset != 0 => offset_builder(partition_builder, offset)?,
+      _ => partition_builder,
+    };
+
+    let boot_sector = BootSector::from_file(&mut partition_builder.open()?)?;
+    let ntfs = Ntfs::from_partition(partition_builder, &boot_sector, NtfsOptions::default())?;
+
+    let mut walk = WalkFilter::new().deleted(args.include_deleted.unwrap_or(true));
+    walk.names = args.names.unwrap_or_default();
+    walk.extensions = args.extensions.unwrap_or_default().iter().map(|extension| extension.trim_start_matches('.').to_string()).collect();
+    let mut filter = ExtractFilter::new().walk(walk).ads(args.ads.unwrap_or(true));
+    filter.paths = args.paths.unwrap_or_default();
+    if args.first_entry.is_some() || args.end_entry.is_some()
+    {
+      filter = filter.entry_range(args.first_entry.unwrap_or(0)..args.end_entry.unwrap_or(u64::MAX));
+    }
+
+    if let Some(output) = args.output
+    {
+      let report = ntfs.extract(&filter, Path::new(&output));
+      return Ok(Results{ extract_node : None, streams : report.streams, bytes : report.bytes, errors : report.errors })
+    }
+
+    let extract_node_id = env.tree.add_child(args.file, Node::new("extract"))?;
+    let mut results = Results{ extract_node : Some(extract_node_id), ..Results::default() };
+    for stream in ntfs.extract_streams(&filter)
+    {
+      //the relative path keep deleted files apart and prefix them with their entry id
+      let node = Node::new(stream.relative_path().to_string_lossy().replace('\\', "/"));
+      node.value().add_attribute("path", stream.path.clone(), None);
+      node.value().add_attribute("data", stream.builder.clone(), None);
+      results.streams += 1;
+      results.bytes += stream.builder.size();
+      env.tree.add_child(extract_node_id, node)?;
+    }
+
+    Ok(results)
+  }
+}
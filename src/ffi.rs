@@ -0,0 +1,199 @@
+//! C ABI to open a volume, list directories and read streams from non-Rust tools
+//!
+//! Functions return NULL or a negative value on error, the message of the last error
+//! of the calling thread is returned by `ntfs_last_error`. A panic while parsing a corrupted
+//! volume is reported as an error instead of unwinding into the caller.
+//! Strings returned by the library must be released with `ntfs_free_string`.
+//!
+//! The crate is not built as a cdylib by default so crates depending on it don't build an unused shared library,
+//! the shared library exporting these functions is built with
+//! `cargo rustc --release --lib --no-default-features --features ffi --crate-type cdylib`
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::io::{Read, Seek, SeekFrom};
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::ptr;
+
+use anyhow::{anyhow, Result};
+
+use crate::ntfs::Ntfs;
+use crate::options::NtfsOptions;
+use crate::ntfsattributes::NtfsAttributeType;
+
+thread_local!
+{
+  static LAST_ERROR : RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(err : anyhow::Error)
+{
+  let message = CString::new(err.to_string().replace('\0', "")).unwrap_or_default();
+  LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
+
+/// run `f` and store its error or panic so it can be retrieved with `ntfs_last_error`,
+/// unwinding through an extern "C" function would abort the host process
+fn catch<T>(f : impl FnOnce() -> Result<T>) -> Option<T>
+{
+  match panic::catch_unwind(AssertUnwindSafe(f))
+  {
+    Ok(Ok(value)) => Some(value),
+    Ok(Err(err)) => { set_last_error(err); None },
+    Err(payload) =>
+    {
+      let message = payload.downcast_ref::<&str>().map(|message| message.to_string())
+                           .or_else(|| payload.downcast_ref::<String>().cloned())
+                           .unwrap_or_default();
+      set_last_error(anyhow!("panic : {}", message));
+      None
+    },
+  }
+}
+
+unsafe fn to_str<'a>(string : *const c_char) -> Result<Option<&'a str>>
+{
+  if string.is_null()
+  {
+    return Ok(None)
+  }
+  Ok(Some(CStr::from_ptr(string).to_str()?))
+}
+
+/// opaque handle of an opened volume
+pub struct NtfsVolume
+{
+  ntfs : Ntfs,
+}
+
+/// open the NTFS volume or partition image at `path`, NULL on error
+///
+/// # Safety
+/// `path` must be a valid NUL terminated string
+#[no_mangle]
+pub unsafe extern "C" fn ntfs_open_volume(path : *const c_char) -> *mut NtfsVolume
+{
+  let volume = catch(||
+  {
+    let path = to_str(path)?.ok_or_else(|| anyhow!("path is NULL"))?;
+    let ntfs = Ntfs::from_path(Path::new(path), NtfsOptions::new())?;
+    Ok(NtfsVolume{ ntfs })
+  });
+
+  match volume
+  {
+    Some(volume) => Box::into_raw(Box::new(volume)),
+    None => ptr::null_mut(),
+  }
+}
+
+/// release a volume returned by `ntfs_open_volume`
+///
+/// # Safety
+/// `volume` must be NULL or a handle returned by `ntfs_open_volume` not already closed
+#[no_mangle]
+pub unsafe extern "C" fn ntfs_close_volume(volume : *mut NtfsVolume)
+{
+  if !volume.is_null()
+  {
+    drop(Box::from_raw(volume));
+  }
+}
+
+/// number of records in the MFT
+///
+/// # Safety
+/// `volume` must be a handle returned by `ntfs_open_volume`
+#[no_mangle]
+pub unsafe extern "C" fn ntfs_entry_count(volume : *const NtfsVolume) -> u64
+{
+  match volume.as_ref()
+  {
+    Some(volume) => volume.ntfs.mft_entries().count(),
+    None => 0,
+  }
+}
+
+/// content of the directory `entry_id` as a JSON array of index entries, NULL on error
+///
+/// # Safety
+/// `volume` must be a handle returned by `ntfs_open_volume`
+#[no_mangle]
+pub unsafe extern "C" fn ntfs_list_entry(volume : *const NtfsVolume, entry_id : u64) -> *mut c_char
+{
+  let json = catch(||
+  {
+    let volume = volume.as_ref().ok_or_else(|| anyhow!("volume is NULL"))?;
+    let entries = volume.ntfs.directory_entries(entry_id)?;
+    Ok(CString::new(serde_json::to_string(&entries)?)?)
+  });
+
+  match json
+  {
+    Some(json) => json.into_raw(),
+    None => ptr::null_mut(),
+  }
+}
+
+/// read up to `size` bytes at `offset` of the data stream `name` of `entry_id` (NULL for the unnamed stream),
+/// return the number of bytes read or -1 on error
+///
+/// # Safety
+/// `volume` must be a handle returned by `ntfs_open_volume`, `name` NULL or a valid NUL terminated string
+/// and `buffer` must be writable for `size` bytes
+#[no_mangle]
+pub unsafe extern "C" fn ntfs_read_stream(volume : *const NtfsVolume, entry_id : u64, name : *const c_char, offset : u64, buffer : *mut u8, size : usize) -> i64
+{
+  let read = catch(||
+  {
+    let volume = volume.as_ref().ok_or_else(|| anyhow!("volume is NULL"))?;
+    if buffer.is_null()
+    {
+      return Err(anyhow!("buffer is NULL"))
+    }
+    let builder = volume.ntfs.attribute_builder(entry_id, NtfsAttributeType::Data, to_str(name)?)?;
+    if offset >= builder.size()
+    {
+      return Ok(0)
+    }
+
+    let buffer = std::slice::from_raw_parts_mut(buffer, size);
+    let mut file = builder.open()?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut total = 0;
+    while total < buffer.len()
+    {
+      match file.read(&mut buffer[total..])?
+      {
+        0 => break,
+        read => total += read,
+      }
+    }
+    Ok(total as i64)
+  });
+
+  read.unwrap_or(-1)
+}
+
+/// message of the last error that occured in the calling thread, NULL if none,
+/// the string is owned by the library and valid until the next call of this thread
+#[no_mangle]
+pub extern "C" fn ntfs_last_error() -> *const c_char
+{
+  LAST_ERROR.with(|last_error| last_error.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
+
+/// release a string returned by the library
+///
+/// # Safety
+/// `string` must be NULL or a string returned by `ntfs_list_entry`
+#[no_mangle]
+pub unsafe extern "C" fn ntfs_free_string(string : *mut c_char)
+{
+  if !string.is_null()
+  {
+    drop(CString::from_raw(string));
+  }
+}
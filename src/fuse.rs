@@ -0,0 +1,283 @@
+//! read-only FUSE mount of a parsed volume
+//!
+//! Live files are mounted under their path, alternate data streams are exposed next to their file
+//! as `name:stream`, deleted entries under `/$Deleted` and entries without a valid parent under `/$Orphan`
+//! both named `{entry id}_{name}`.
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tap::vfile::VFile;
+
+use anyhow::Result;
+use fuser::{Filesystem, FileAttr, FileType, MountOption, Request, ReplyAttr, ReplyData, ReplyDirectory, ReplyEmpty, ReplyEntry, ReplyOpen};
+use libc::{ENOENT, EIO, EISDIR};
+use log::warn;
+
+use crate::ntfs::{Ntfs, ROOT_ENTRY};
+use crate::ntfsattributes::NtfsAttributeType;
+
+/// the content is never modified so the kernel can cache it for long
+const TTL : Duration = Duration::from_secs(3600);
+const ROOT_INODE : u64 = 1;
+const DELETED_INODE : u64 = 2;
+const ORPHAN_INODE : u64 = 3;
+const BLOCK_SIZE : u32 = 512;
+
+/// attributes needed to create the nodes, attribute list can reference names or data in other records
+const MOUNT_ATTRIBUTES : [NtfsAttributeType; 4] = [NtfsAttributeType::StandardInformation, NtfsAttributeType::FileName,
+                                                   NtfsAttributeType::AttributeList, NtfsAttributeType::Data];
+
+/// file or directory of the mount, its inode is its index in `NtfsFilesystem::nodes` + 1
+struct FsNode
+{
+  name : String,
+  parent : u64,
+  children : Vec<u64>,
+  kind : FileType,
+  /// entry and data stream name read by the node, None for synthetic directories
+  stream : Option<(u64, Option<String>)>,
+  size : u64,
+  created : SystemTime,
+  modified : SystemTime,
+  changed : SystemTime,
+  accessed : SystemTime,
+}
+
+impl FsNode
+{
+  fn directory(name : &str, parent : u64) -> Self
+  {
+    FsNode{ name : name.to_string(), parent, children : Vec::new(), kind : FileType::Directory, stream : None, size : 0,
+            created : UNIX_EPOCH, modified : UNIX_EPOCH, changed : UNIX_EPOCH, accessed : UNIX_EPOCH }
+  }
+}
+
+/**
+ *  NtfsFilesystem
+ *  Nodes are created from the MFT when the filesystem is created, data is read on demand from the streams builders
+ */
+pub struct NtfsFilesystem
+{
+  ntfs : Ntfs,
+  nodes : Vec<FsNode>,
+  open_files : HashMap<u64, Box<dyn VFile>>,
+  next_handle : u64,
+}
+
+impl NtfsFilesystem
+{
+  pub fn new(ntfs : Ntfs) -> Self
+  {
+    let mut nodes = vec![FsNode::directory("", ROOT_INODE), FsNode::directory("$Deleted", ROOT_INODE), FsNode::directory("$Orphan", ROOT_INODE)];
+    nodes[0].children = vec![DELETED_INODE, ORPHAN_INODE];
+    let mut entries_inode : HashMap<u64, u64> = HashMap::from([(ROOT_ENTRY, ROOT_INODE)]);
+    let mut parents : Vec<(u64, u64, bool)> = Vec::new(); //inode, parent entry, is deleted
+
+    for (entry_id, entry) in ntfs.mft_entries().iter()
+    {
+      let entry = match entry
+      {
+        Ok(entry) if entry_id != ROOT_ENTRY && entry.is_base_record() => entry,
+        _ => continue,
+      };
+      let attributes = entry.attributes_iter(Some(ntfs.mft_entries())).only(&MOUNT_ATTRIBUTES).into_attributes();
+      let file_name = match attributes.find_filename_with(ntfs.options().name_space)
+      {
+        Some(file_name) => file_name,
+        None => continue,
+      };
+      let is_deleted = !entry.is_used();
+      let name = match is_deleted
+      {
+        true => format!("{}_{}", entry_id, file_name.file_name),
+        false => file_name.file_name.clone(),
+      };
+
+      let times = attributes.find_standard_info().into_iter().next()
+                            .map(|info| (info.creation_time, info.altered_time, info.mft_altered_time, info.accessed_time))
+                            .unwrap_or((file_name.creation_time, file_name.modification_time, file_name.mft_modification_time, file_name.accessed_time));
+      let node = |name : String, kind : FileType, stream : Option<(u64, Option<String>)>, size : u64| FsNode{
+        name, parent : ROOT_INODE, children : Vec::new(), kind, stream, size,
+        created : times.0.into(), modified : times.1.into(), changed : times.2.into(), accessed : times.3.into(),
+      };
+
+      let inode = nodes.len() as u64 + 1;
+      let datas = attributes.find_datas();
+      match entry.is_directory()
+      {
+        true => nodes.push(node(name.clone(), FileType::Directory, None, 0)),
+        false =>
+        {
+          //the actual size, the slack of the last cluster is not part of the file
+          let size = datas.iter().find(|data| data.mft_attribute.name.is_none()).map(|data| data.size()).unwrap_or(0);
+          nodes.push(node(name.clone(), FileType::RegularFile, Some((entry_id, None)), size));
+        },
+      }
+      entries_inode.insert(entry_id, inode);
+      parents.push((inode, file_name.parent_mft_entry_id, is_deleted));
+
+      for data in datas
+      {
+        if let (Some(stream), Ok(_)) = (&data.mft_attribute.name, data.builder())
+        {
+          //alternate data streams are linked to the parent of their file
+          parents.push((nodes.len() as u64 + 1, file_name.parent_mft_entry_id, is_deleted));
+          nodes.push(node(format!("{}:{}", name, stream), FileType::RegularFile, Some((entry_id, Some(stream.clone()))), data.size()));
+        }
+      }
+    }
+
+    for (inode, parent_entry, is_deleted) in parents
+    {
+      let parent = match (is_deleted, entries_inode.get(&parent_entry))
+      {
+        (true, _) => DELETED_INODE,
+        (false, Some(parent)) if nodes[*parent as usize - 1].kind == FileType::Directory => *parent,
+        (false, _) => ORPHAN_INODE,
+      };
+      nodes[inode as usize - 1].parent = parent;
+      nodes[parent as usize - 1].children.push(inode);
+    }
+
+    NtfsFilesystem{ ntfs, nodes, open_files : HashMap::new(), next_handle : 1 }
+  }
+
+  fn node(&self, inode : u64) -> Option<&FsNode>
+  {
+    inode.checked_sub(1).and_then(|index| self.nodes.get(index as usize))
+  }
+
+  fn attr(&self, inode : u64, node : &FsNode) -> FileAttr
+  {
+    let (perm, nlink) = match node.kind
+    {
+      FileType::Directory => (0o555, 2),
+      _ => (0o444, 1),
+    };
+    FileAttr{
+      ino : inode,
+      size : node.size,
+      blocks : node.size.div_ceil(BLOCK_SIZE as u64),
+      atime : node.accessed,
+      mtime : node.modified,
+      ctime : node.changed,
+      crtime : node.created,
+      kind : node.kind,
+      perm,
+      nlink,
+      uid : 0,
+      gid : 0,
+      rdev : 0,
+      blksize : BLOCK_SIZE,
+      flags : 0,
+    }
+  }
+}
+
+impl Filesystem for NtfsFilesystem
+{
+  fn lookup(&mut self, _req : &Request, parent : u64, name : &OsStr, reply : ReplyEntry)
+  {
+    let found = self.node(parent).and_then(|parent| parent.children.iter()
+                    .find(|child| self.node(**child).is_some_and(|node| OsStr::new(&node.name) == name)));
+    match found.and_then(|inode| self.node(*inode).map(|node| self.attr(*inode, node)))
+    {
+      Some(attr) => reply.entry(&TTL, &attr, 0),
+      None => reply.error(ENOENT),
+    }
+  }
+
+  fn getattr(&mut self, _req : &Request, ino : u64, reply : ReplyAttr)
+  {
+    match self.node(ino)
+    {
+      Some(node) => reply.attr(&TTL, &self.attr(ino, node)),
+      None => reply.error(ENOENT),
+    }
+  }
+
+  fn readdir(&mut self, _req : &Request, ino : u64, _fh : u64, offset : i64, mut reply : ReplyDirectory)
+  {
+    let node = match self.node(ino)
+    {
+      Some(node) if node.kind == FileType::Directory => node,
+      Some(_) => return reply.error(libc::ENOTDIR),
+      None => return reply.error(ENOENT),
+    };
+
+    let entries = [(ino, ".".to_string()), (node.parent, "..".to_string())].into_iter()
+                  .chain(node.children.iter().filter_map(|child| self.node(*child).map(|child_node| (*child, child_node.name.clone()))));
+    for (index, (inode, name)) in entries.enumerate().skip(offset as usize)
+    {
+      let kind = self.node(inode).map(|node| node.kind).unwrap_or(FileType::Directory);
+      if reply.add(inode, (index + 1) as i64, kind, name)
+      {
+        break
+      }
+    }
+    reply.ok();
+  }
+
+  fn open(&mut self, _req : &Request, ino : u64, _flags : i32, reply : ReplyOpen)
+  {
+    let (entry_id, stream) = match self.node(ino).map(|node| &node.stream)
+    {
+      Some(Some(stream)) => stream.clone(),
+      Some(None) => return reply.error(EISDIR),
+      None => return reply.error(ENOENT),
+    };
+    match self.ntfs.attribute_builder(entry_id, NtfsAttributeType::Data, stream.as_deref()).and_then(|builder| builder.open())
+    {
+      Ok(file) =>
+      {
+        let handle = self.next_handle;
+        self.next_handle += 1;
+        self.open_files.insert(handle, file);
+        reply.opened(handle, 0);
+      },
+      Err(err) =>
+      {
+        warn!("can't open entry {} : {}", entry_id, err);
+        reply.error(EIO);
+      },
+    }
+  }
+
+  fn read(&mut self, _req : &Request, ino : u64, fh : u64, offset : i64, size : u32, _flags : i32, _lock_owner : Option<u64>, reply : ReplyData)
+  {
+    let file_size = self.node(ino).map(|node| node.size).unwrap_or(0);
+    let file = match self.open_files.get_mut(&fh)
+    {
+      Some(file) => file,
+      None => return reply.error(ENOENT),
+    };
+    let offset = offset.max(0) as u64;
+    let size = (size as u64).min(file_size.saturating_sub(offset)) as usize;
+
+    let mut buffer = vec![0u8; size];
+    let read = file.seek(SeekFrom::Start(offset)).and_then(|_| file.read_exact(&mut buffer));
+    match read
+    {
+      Ok(()) => reply.data(&buffer),
+      Err(_) => reply.error(EIO),
+    }
+  }
+
+  fn release(&mut self, _req : &Request, _ino : u64, fh : u64, _flags : i32, _lock_owner : Option<u64>, _flush : bool, reply : ReplyEmpty)
+  {
+    self.open_files.remove(&fh);
+    reply.ok();
+  }
+}
+
+/// mount the volume read-only on `mountpoint`, block until it is unmounted
+pub fn mount(ntfs : Ntfs, mountpoint : &Path) -> Result<()>
+{
+  let options = [MountOption::RO, MountOption::FSName("ntfs".to_string()), MountOption::DefaultPermissions];
+  fuser::mount2(NtfsFilesystem::new(ntfs), mountpoint, &options)?;
+  Ok(())
+}
@@ -0,0 +1,396 @@
+//! $LogFile parser, restart pages (RSTR) describe the log and record pages (RCRD) contain the transactions records.
+//! It only needs the content of the file so it works on the $LogFile of a volume or on an extracted copy
+
+use std::collections::VecDeque;
+use std::io::SeekFrom;
+use std::sync::Arc;
+
+use tap::vfile::{VFile, VFileBuilder};
+#[cfg(feature = "plugin")]
+use tap::reflect::ReflectStruct;
+#[cfg(feature = "plugin")]
+use tap::value::Value;
+#[cfg(feature = "plugin")]
+use tap_derive::Reflect;
+
+use anyhow::Result;
+use byteorder::{ByteOrder, LittleEndian};
+use serde::{Serialize, Deserialize};
+
+use crate::error::NtfsError;
+use crate::mftentry::MftEntry;
+use crate::attributecontent::record_slice;
+
+const RESTART_SIGNATURE : u32 = 0x52545352; //RSTR
+const RECORD_SIGNATURE : u32 = 0x44524352; //RCRD
+/// sector size protected by the fixups of the log pages
+const LOG_SECTOR_SIZE : u16 = 512;
+/// restart and log pages are 4KB on current Windows, bigger sizes come from a damaged restart page
+const MAX_PAGE_SIZE : u32 = 64 * 1024;
+const LOG_RECORD_HEADER_SIZE : usize = 0x30;
+/// size of the client data header before the list of LCN
+const CLIENT_DATA_HEADER_SIZE : usize = 0x20;
+const RESTART_AREA_CLEAN : u16 = 0x2;
+const LOG_RECORD_CLIENT : u32 = 1;
+
+const OPERATION_NAMES : [&str; 35] = [
+  "Noop", "CompensationLogRecord", "InitializeFileRecordSegment", "DeallocateFileRecordSegment",
+  "WriteEndOfFileRecordSegment", "CreateAttribute", "DeleteAttribute", "UpdateResidentValue",
+  "UpdateNonresidentValue", "UpdateMappingPairs", "DeleteDirtyClusters", "SetNewAttributeSizes",
+  "AddIndexEntryRoot", "DeleteIndexEntryRoot", "AddIndexEntryAllocation", "DeleteIndexEntryAllocation",
+  "WriteEndOfIndexBuffer", "SetIndexEntryVcnRoot", "SetIndexEntryVcnAllocation", "UpdateFileNameRoot",
+  "UpdateFileNameAllocation", "SetBitsInNonresidentBitMap", "ClearBitsInNonresidentBitMap", "HotFix",
+  "EndTopLevelAction", "PrepareTransaction", "CommitTransaction", "ForgetTransaction",
+  "OpenNonresidentAttribute", "OpenAttributeTableDump", "AttributeNamesDump", "DirtyPageTableDump",
+  "TransactionTableDump", "UpdateRecordDataRoot", "UpdateRecordDataAllocation",
+];
+
+/// name of a redo or undo operation code
+pub fn operation_name(operation : u16) -> String
+{
+  match OPERATION_NAMES.get(operation as usize)
+  {
+    Some(name) => name.to_string(),
+    None => format!("Unknown({:#x})", operation),
+  }
+}
+
+/**
+ *  LogFileRestart
+ *  Restart page and restart area, the most recent of the two copies is used
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "plugin", derive(Reflect))]
+pub struct LogFileRestart
+{
+  pub chkdsk_lsn : u64,
+  pub system_page_size : u32,
+  pub log_page_size : u32,
+  pub major_version : i16,
+  pub minor_version : i16,
+  /// LSN of the last checkpoint
+  pub current_lsn : u64,
+  pub log_clients : u16,
+  /// the volume was cleanly unmounted
+  pub clean : bool,
+  pub sequence_number_bits : u32,
+  pub file_size : u64,
+  /// offset of the first record in the record pages
+  pub log_page_data_offset : u16,
+}
+
+impl LogFileRestart
+{
+  /// parse a restart page, fixups must already be applied
+  pub fn new(page : &[u8]) -> Result<Self>
+  {
+    let header = record_slice(page, 0, 0x1e)?;
+    if LittleEndian::read_u32(&header[0..4]) != RESTART_SIGNATURE
+    {
+      return Err(NtfsError::LogFileInvalid("restart page signature").into())
+    }
+    let chkdsk_lsn = LittleEndian::read_u64(&header[8..16]);
+    let system_page_size = LittleEndian::read_u32(&header[16..20]);
+    let log_page_size = LittleEndian::read_u32(&header[20..24]);
+    let restart_area_offset = LittleEndian::read_u16(&header[24..26]) as usize;
+    let minor_version = LittleEndian::read_i16(&header[26..28]);
+    let major_version = LittleEndian::read_i16(&header[28..30]);
+    if !log_page_size.is_power_of_two() || (log_page_size as usize) < LOG_SECTOR_SIZE as usize || log_page_size > MAX_PAGE_SIZE
+    {
+      return Err(NtfsError::LogFileInvalid("log page size").into())
+    }
+
+    let area = record_slice(page, restart_area_offset, 0x2c)?;
+    let current_lsn = LittleEndian::read_u64(&area[0..8]);
+    let log_clients = LittleEndian::read_u16(&area[8..10]);
+    let flags = LittleEndian::read_u16(&area[14..16]);
+    let sequence_number_bits = LittleEndian::read_u32(&area[16..20]);
+    let file_size = LittleEndian::read_u64(&area[24..32]);
+    let log_page_data_offset = LittleEndian::read_u16(&area[38..40]);
+    if log_page_data_offset as u32 >= log_page_size
+    {
+      return Err(NtfsError::LogFileInvalid("log page data offset").into())
+    }
+
+    Ok(LogFileRestart{
+      chkdsk_lsn,
+      system_page_size,
+      log_page_size,
+      major_version,
+      minor_version,
+      current_lsn,
+      log_clients,
+      clean : flags & RESTART_AREA_CLEAN != 0,
+      sequence_number_bits,
+      file_size,
+      log_page_data_offset,
+    })
+  }
+}
+
+/**
+ *  LogRecord
+ *  Record of a log page, the client data header is decoded for client records
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "plugin", derive(Reflect))]
+pub struct LogRecord
+{
+  /// offset in $LogFile of the page containing the start of the record
+  pub page_offset : u64,
+  pub lsn : u64,
+  pub previous_lsn : u64,
+  pub undo_next_lsn : u64,
+  pub client_data_length : u32,
+  /// 1 for client records, 2 for client restart records
+  pub record_type : u32,
+  pub transaction_id : u32,
+  pub redo_operation : String,
+  pub undo_operation : String,
+  pub redo_length : u16,
+  pub undo_length : u16,
+  /// index of the attribute in the open attribute table
+  pub target_attribute : u16,
+  pub record_offset : u16,
+  pub attribute_offset : u16,
+  pub cluster_block_offset : u16,
+  pub target_vcn : u64,
+  /// clusters modified by the operation
+  #[cfg_attr(feature = "plugin", reflect(skip))]
+  pub target_lcns : Vec<u64>,
+}
+
+impl LogRecord
+{
+  /// parse a record header followed by its client data
+  pub fn new(data : &[u8], page_offset : u64) -> Result<Self>
+  {
+    let header = record_slice(data, 0, LOG_RECORD_HEADER_SIZE)?;
+    let lsn = LittleEndian::read_u64(&header[0..8]);
+    let previous_lsn = LittleEndian::read_u64(&header[8..16]);
+    let undo_next_lsn = LittleEndian::read_u64(&header[16..24]);
+    let client_data_length = LittleEndian::read_u32(&header[24..28]);
+    let record_type = LittleEndian::read_u32(&header[32..36]);
+    let transaction_id = LittleEndian::read_u32(&header[36..40]);
+
+    let mut record = LogRecord{
+      page_offset, lsn, previous_lsn, undo_next_lsn, client_data_length, record_type, transaction_id,
+      redo_operation : operation_name(0), undo_operation : operation_name(0), redo_length : 0, undo_length : 0,
+      target_attribute : 0, record_offset : 0, attribute_offset : 0, cluster_block_offset : 0, target_vcn : 0, target_lcns : Vec::new(),
+    };
+    if record_type != LOG_RECORD_CLIENT || (client_data_length as usize) < CLIENT_DATA_HEADER_SIZE
+    {
+      return Ok(record)
+    }
+
+    let client = record_slice(data, LOG_RECORD_HEADER_SIZE, CLIENT_DATA_HEADER_SIZE)?;
+    record.redo_operation = operation_name(LittleEndian::read_u16(&client[0..2]));
+    record.undo_operation = operation_name(LittleEndian::read_u16(&client[2..4]));
+    record.redo_length = LittleEndian::read_u16(&client[6..8]);
+    record.undo_length = LittleEndian::read_u16(&client[10..12]);
+    record.target_attribute = LittleEndian::read_u16(&client[12..14]);
+    let lcns_to_follow = LittleEndian::read_u16(&client[14..16]) as usize;
+    record.record_offset = LittleEndian::read_u16(&client[16..18]);
+    record.attribute_offset = LittleEndian::read_u16(&client[18..20]);
+    record.cluster_block_offset = LittleEndian::read_u16(&client[20..22]);
+    record.target_vcn = LittleEndian::read_u64(&client[24..32]);
+    if let Ok(lcns) = record_slice(data, LOG_RECORD_HEADER_SIZE + CLIENT_DATA_HEADER_SIZE, lcns_to_follow * 8)
+    {
+      record.target_lcns = lcns.chunks_exact(8).map(LittleEndian::read_u64).collect();
+    }
+
+    Ok(record)
+  }
+}
+
+/**
+ *  LogFile
+ *  Parsed restart area, the records are read on demand with `records`
+ */
+pub struct LogFile
+{
+  builder : Arc<dyn VFileBuilder>,
+  pub restart : LogFileRestart,
+}
+
+impl LogFile
+{
+  pub fn new(builder : Arc<dyn VFileBuilder>) -> Result<Self>
+  {
+    let mut file = builder.open()?;
+    let first = LogFile::read_restart(file.as_mut(), 0);
+    //the second copy is at the system page size
+    let second = first.as_ref().map(|restart| restart.system_page_size as u64).unwrap_or(4096);
+    let second = LogFile::read_restart(file.as_mut(), second);
+
+    let restart = match (first, second)
+    {
+      (Ok(first), Ok(second)) if second.current_lsn > first.current_lsn => second,
+      (Ok(first), _) => first,
+      (Err(_), Ok(second)) => second,
+      (Err(err), Err(_)) => return Err(err),
+    };
+    Ok(LogFile{ builder, restart })
+  }
+
+  fn read_restart(file : &mut dyn VFile, offset : u64) -> Result<LogFileRestart>
+  {
+    let mut header = [0u8; 0x18];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut header)?;
+    let page_size = LittleEndian::read_u32(&header[16..20]);
+    if !page_size.is_power_of_two() || page_size < LOG_SECTOR_SIZE as u32 || page_size > MAX_PAGE_SIZE
+    {
+      return Err(NtfsError::LogFileInvalid("system page size").into())
+    }
+    let page = read_page(file, offset, page_size as usize)?;
+    LogFileRestart::new(&page)
+  }
+
+  /// iterator on the records of the record pages in file order, the log is circular so records are not sorted by LSN
+  pub fn records(&self) -> Result<LogRecords>
+  {
+    //NTFS 1.1 has two restart pages followed by two tail copies of the last pages
+    let first_page = match self.restart.major_version
+    {
+      1 => 4,
+      _ => 2,
+    };
+    Ok(LogRecords{
+      file : self.builder.open()?,
+      size : self.builder.size(),
+      page_size : self.restart.log_page_size as u64,
+      data_offset : self.restart.log_page_data_offset as usize,
+      next_page : first_page * self.restart.log_page_size as u64,
+      pending : None,
+      records : VecDeque::new(),
+      done : false,
+    })
+  }
+}
+
+/// read a multi sector page and apply its fixups
+fn read_page(file : &mut dyn VFile, offset : u64, size : usize) -> Result<Vec<u8>>
+{
+  let mut page = vec![0u8; size];
+  file.seek(SeekFrom::Start(offset))?;
+  file.read_exact(&mut page)?;
+  let fixup_offset = LittleEndian::read_u16(&page[4..6]);
+  let fixup_count = LittleEndian::read_u16(&page[6..8]).saturating_sub(1);
+  let stride = MftEntry::stride(size as u64, fixup_count as u64, LOG_SECTOR_SIZE);
+  match MftEntry::apply_fixup(&mut page, fixup_offset, fixup_count, stride)
+  {
+    Some(fixup) if fixup.mismatch_count == 0 => Ok(page),
+    _ => Err(NtfsError::LogFileInvalid("page fixup").into()),
+  }
+}
+
+/// record spanning multiple pages
+struct PendingRecord
+{
+  page_offset : u64,
+  data : Vec<u8>,
+  size : usize,
+}
+
+/**
+ *  LogRecords
+ *  Iterator on the records of the record pages, pages that can't be read or have invalid fixups are skipped
+ */
+pub struct LogRecords
+{
+  file : Box<dyn VFile>,
+  size : u64,
+  page_size : u64,
+  data_offset : usize,
+  next_page : u64,
+  pending : Option<PendingRecord>,
+  records : VecDeque<LogRecord>,
+  done : bool,
+}
+
+impl LogRecords
+{
+  /// parse the records of the next page, false at the end of the file
+  fn read_next_page(&mut self) -> Result<bool>
+  {
+    if self.next_page + self.page_size > self.size
+    {
+      return Ok(false)
+    }
+    let page_offset = self.next_page;
+    self.next_page += self.page_size;
+
+    let page = match read_page(self.file.as_mut(), page_offset, self.page_size as usize)
+    {
+      Ok(page) if LittleEndian::read_u32(&page[0..4]) == RECORD_SIGNATURE => page,
+      Ok(_) | Err(_) => { self.pending = None; return Ok(true) },
+    };
+    let last_end_lsn = LittleEndian::read_u64(&page[32..40]);
+    let mut position = self.data_offset;
+
+    if let Some(mut pending) = self.pending.take()
+    {
+      let size = (pending.size - pending.data.len()).min(page.len() - position);
+      pending.data.extend_from_slice(&page[position..position + size]);
+      position += align8(size);
+      match pending.data.len() == pending.size
+      {
+        true => self.records.extend(LogRecord::new(&pending.data, pending.page_offset).ok()),
+        false => { self.pending = Some(pending); return Ok(true) },
+      }
+    }
+
+    while position + LOG_RECORD_HEADER_SIZE <= page.len()
+    {
+      let lsn = LittleEndian::read_u64(&page[position..position + 8]);
+      //records after the last record that ends in the page are leftovers of a previous pass
+      if lsn == 0 || (last_end_lsn != 0 && lsn > last_end_lsn)
+      {
+        break
+      }
+      let client_data_length = LittleEndian::read_u32(&page[position + 24..position + 28]) as usize;
+      if client_data_length as u64 > self.size
+      {
+        break
+      }
+      let size = LOG_RECORD_HEADER_SIZE + client_data_length;
+      if position + size > page.len()
+      {
+        self.pending = Some(PendingRecord{ page_offset, data : page[position..].to_vec(), size });
+        break
+      }
+      self.records.extend(LogRecord::new(&page[position..position + size], page_offset).ok());
+      position += align8(size);
+    }
+    Ok(true)
+  }
+}
+
+fn align8(size : usize) -> usize
+{
+  (size + 7) & !7
+}
+
+impl Iterator for LogRecords
+{
+  type Item = Result<LogRecord>;
+
+  fn next(&mut self) -> Option<Self::Item>
+  {
+    while !self.done
+    {
+      if let Some(record) = self.records.pop_front()
+      {
+        return Some(Ok(record))
+      }
+      match self.read_next_page()
+      {
+        Ok(true) => (),
+        Ok(false) => self.done = true,
+        Err(err) => { self.done = true; return Some(Err(err)) },
+      }
+    }
+    None
+  }
+}
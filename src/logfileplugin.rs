@@ -0,0 +1,98 @@
+//! tap plugin parsing the $LogFile found in the data of a node, e.g. a $LogFile extracted from a volume,
+//! the restart area is added as an attribute and each record as a child node
+
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use tap::plugin;
+use tap::config_schema;
+use tap::node::Node;
+use tap::error::RustructError;
+use tap::tree::{TreeNodeId, TreeNodeIdSchema};
+use tap::plugin::{PluginInfo, PluginInstance, PluginConfig, PluginArgument, PluginResult, PluginEnvironment};
+
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use schemars::JsonSchema;
+use log::warn;
+
+use crate::logfile::LogFile;
+
+plugin!("logfile", "File system", "Parse an extracted $LogFile", LogFilePlugin, Arguments);
+
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Arguments
+{
+  #[schemars(with = "TreeNodeIdSchema")]
+  file : TreeNodeId,
+}
+
+#[derive(Debug, Serialize, Deserialize,Default)]
+pub struct Results
+{
+  ///node containing the records
+  logfile_node : Option<TreeNodeId>,
+  ///version of the log from the restart page
+  version : String,
+  log_page_size : u32,
+  ///LSN of the last checkpoint
+  current_lsn : u64,
+  ///the volume was cleanly unmounted
+  clean : bool,
+  ///number of records parsed
+  records : u64,
+  lowest_lsn : Option<u64>,
+  highest_lsn : Option<u64>,
+}
+
+#[derive(Default)]
+pub struct LogFilePlugin
+{
+}
+
+impl LogFilePlugin
+{
+  fn run(&mut self, args : Arguments, env : PluginEnvironment) -> Result<Results>
+  {
+    let file_node = env.tree.get_node_from_id(args.file).ok_or(RustructError::ArgumentNotFound("file"))?;
+    file_node.value().add_attribute(self.name(), None, None);
+    let value = file_node.value().get_value("data").ok_or(RustructError::ValueNotFound("data"))?;
+    let logfile_builder = value.try_as_vfile_builder().ok_or(RustructError::ValueTypeMismatch)?;
+
+    let logfile = LogFile::new(logfile_builder)?;
+    let restart = &logfile.restart;
+    let mut results = Results{
+      version : format!("{}.{}", restart.major_version, restart.minor_version),
+      log_page_size : restart.log_page_size,
+      current_lsn : restart.current_lsn,
+      clean : restart.clean,
+      ..Results::default()
+    };
+
+    let logfile_node = Node::new("logfile");
+    logfile_node.value().add_attribute("restart", Arc::new(restart.clone()), None);
+    let logfile_node_id = env.tree.add_child(args.file, logfile_node)?;
+    results.logfile_node = Some(logfile_node_id);
+
+    for record in logfile.records()?
+    {
+      let record = match record
+      {
+        Ok(record) => record,
+        //records read before the error are kept
+        Err(err) => { warn!("Can't read $LogFile : {}", err); break },
+      };
+
+      results.records += 1;
+      results.lowest_lsn = Some(results.lowest_lsn.map_or(record.lsn, |lsn| lsn.min(record.lsn)));
+      results.highest_lsn = Some(results.highest_lsn.map_or(record.lsn, |lsn| lsn.max(record.lsn)));
+
+      let node = Node::new(format!("{}_{}", record.lsn, record.redo_operation));
+      node.value().add_attribute("logfile", Arc::new(record), None);
+      env.tree.add_child(logfile_node_id, node)?;
+    }
+
+    Ok(results)
+  }
+}
@@ -0,0 +1,259 @@
+use std::cmp::{min, max};
+use std::ops::Range;
+use std::sync::{Arc, OnceLock};
+
+use tap::vfile::{VFile, VFileBuilder};
+use tap::mappedvfile::{MappedVFileBuilder, FileRanges};
+
+use anyhow::Result;
+use serde::{Serialize, Deserialize};
+
+use crate::error::NtfsError;
+use crate::ntfsattributes::NtfsAttributeType;
+
+/**
+ *  Range of a virtual file mapped to an offset of a builder.
+ *  Mappings are kept as a list so they can be composed at construction time
+ *  instead of stacking MappedVFileBuilder that are traversed on each read
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MappedRange
+{
+  pub range : Range<u64>,
+  pub offset : u64,
+  pub builder : Arc<dyn VFileBuilder>,
+}
+
+impl MappedRange
+{
+  pub fn new(range : Range<u64>, offset : u64, builder : Arc<dyn VFileBuilder>) -> Self
+  {
+    MappedRange{ range, offset, builder }
+  }
+}
+
+/// append a range to a mapping, extending the last range if it continue it
+/// in the virtual file and in the builder
+pub fn push_range(mapping : &mut Vec<MappedRange>, mapped : MappedRange)
+{
+  if let Some(last) = mapping.last_mut()
+  {
+    if last.range.end == mapped.range.start && Arc::ptr_eq(&last.builder, &mapped.builder) 
+       && last.offset.checked_add(last.range.end - last.range.start) == Some(mapped.offset)
+    {
+      last.range.end = mapped.range.end;
+      return
+    }
+  }
+  mapping.push(mapped);
+}
+
+/// append a range of zeros to a mapping, extending the last range if it's also sparse 
+/// as the offset in the zero builder doesn't matter
+pub fn push_sparse_range(mapping : &mut Vec<MappedRange>, range : Range<u64>, zero_builder : Arc<dyn VFileBuilder>)
+{
+  if let Some(last) = mapping.last_mut()
+  {
+    if last.range.end == range.start && Arc::ptr_eq(&last.builder, &zero_builder)
+    {
+      last.range.end = range.end;
+      return
+    }
+  }
+  mapping.push(MappedRange::new(range, 0, zero_builder));
+}
+
+/// map `size` bytes at `offset` of the virtual file described by `mapping` to the position `start`
+/// of a new virtual file, the result point directly to the builders of `mapping`
+pub fn resolve(mapping : &[MappedRange], start : u64, offset : u64, size : u64) -> Vec<MappedRange>
+{
+  let end = offset.saturating_add(size);
+
+  mapping.iter()
+    .filter(|mapped| mapped.range.start < end && mapped.range.end > offset)
+    .map(|mapped|
+    {
+      let from = max(offset, mapped.range.start);
+      let to = min(end, mapped.range.end);
+      MappedRange::new(start + (from - offset)..start + (to - offset), mapped.offset + (from - mapped.range.start), mapped.builder.clone())
+    })
+    .collect()
+}
+
+/// range containing the byte at `offset` of the virtual file described by `mapping`, 
+/// with the offset of this byte in the range builder
+pub fn map_offset(mapping : &[MappedRange], offset : u64) -> Option<(&MappedRange, u64)>
+{
+  mapping.iter()
+         .find(|mapped| mapped.range.contains(&offset))
+         .map(|mapped| (mapped, mapped.offset + (offset - mapped.range.start)))
+}
+
+/// offset in the virtual file described by `mapping` of the byte at `offset` of `builder`
+pub fn unmap_offset(mapping : &[MappedRange], builder : &Arc<dyn VFileBuilder>, offset : u64) -> Option<u64>
+{
+  mapping.iter()
+         .filter(|mapped| Arc::ptr_eq(&mapped.builder, builder))
+         .find(|mapped| offset >= mapped.offset && offset - mapped.offset < mapped.range.end - mapped.range.start)
+         .map(|mapped| mapped.range.start + (offset - mapped.offset))
+}
+
+/// size of the virtual file described by `mapping`
+pub fn mapping_size(mapping : &[MappedRange]) -> u64
+{
+  mapping.iter().map(|mapped| mapped.range.end).max().unwrap_or(0)
+}
+
+/// builder reading `builder` from `offset` to its end, used to read a volume inside a disk image
+pub fn offset_builder(builder : Arc<dyn VFileBuilder>, offset : u64) -> Result<Arc<dyn VFileBuilder>>
+{
+  let size = builder.size();
+  if offset >= size
+  {
+    return Err(NtfsError::VolumeOffset{ offset, size }.into())
+  }
+  Ok(to_builder(&[MappedRange::new(0..size - offset, offset, builder)]))
+}
+
+/// create a single level builder from a mapping
+pub fn to_builder(mapping : &[MappedRange]) -> Arc<dyn VFileBuilder>
+{
+  let mut file_ranges = FileRanges::new();
+
+  for mapped in mapping
+  {
+    file_ranges.push(mapped.range.clone(), mapped.offset, mapped.builder.clone());
+  }
+  Arc::new(MappedVFileBuilder::new(file_ranges))
+}
+
+/**
+ *  StreamRuns
+ *  Runs of a non-resident attribute with the builders they are read from,
+ *  they are only resolved to a mapping when the stream is read
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamRuns
+{
+  /// entry and type of the attribute, used in errors
+  pub entry : u64,
+  pub type_id : NtfsAttributeType,
+  pub vcn_start : u64,
+  /// absolute cluster offset of each run, 0 for sparse runs, and its length in clusters
+  pub runs : Vec<(i64, u64)>,
+  pub cluster_size : u64,
+  pub partition_builder : Arc<dyn VFileBuilder>,
+  pub zero_builder : Arc<dyn VFileBuilder>,
+}
+
+impl StreamRuns
+{
+  /// end of the last run in the stream, computed without mapping the runs
+  pub fn size(&self) -> Result<u64>
+  {
+    self.runs.iter().try_fold(self.vcn_start, |clusters, (_, length)| clusters.checked_add(*length))
+             .and_then(|clusters| clusters.checked_mul(self.cluster_size))
+             .ok_or_else(|| NtfsError::Overflow("non resident run range").into())
+  }
+
+  /// ranges of the stream in the partition, sparse runs are read from the zero builder
+  pub fn mapping(&self) -> Result<Vec<MappedRange>>
+  {
+    let mut mapping = Vec::new();
+    let mut total_size : u64 = self.vcn_start.checked_mul(self.cluster_size).ok_or(NtfsError::Overflow("non resident vcn start"))?;
+    for (offset, length) in self.runs.iter()
+    {
+      let run_size = length.checked_mul(self.cluster_size).ok_or(NtfsError::Overflow("non resident run length"))?;
+      let run_end = total_size.checked_add(run_size).ok_or(NtfsError::Overflow("non resident run range"))?;
+      let range = total_size..run_end;
+
+      if *offset == 0 //sparse
+      {
+        push_sparse_range(&mut mapping, range, self.zero_builder.clone());
+      }
+      else
+      {
+        //a negative absolute offset is invalid and would wrap
+        let run_offset = u64::try_from(*offset).map_err(|_| NtfsError::Overflow("non resident run offset"))?;
+        let run_offset = run_offset.checked_mul(self.cluster_size).ok_or(NtfsError::Overflow("non resident run offset"))?;
+
+        if run_offset > self.partition_builder.size()
+        {
+          return Err(NtfsError::NonResidentAttributeOffsetTooLarge{ type_id : self.type_id.clone(), entry : self.entry, offset : run_offset }.into()) 
+        }
+        //check if range is valid before pushing !
+        //physically contiguous runs are merged to keep the range table small
+        push_range(&mut mapping, MappedRange::new(range, run_offset, self.partition_builder.clone()));
+      }
+      total_size = run_end;
+    }
+    Ok(mapping)
+  }
+}
+
+/// content of a `LazyVFileBuilder`
+#[derive(Debug, Serialize, Deserialize)]
+enum LazyContent
+{
+  /// mapping restored from the metadata cache
+  Mapping(Vec<MappedRange>),
+  Runs(StreamRuns),
+}
+
+/**
+ *  Builder keeping only the runs or the mapping of a stream,
+ *  the runs are resolved and the MappedVFileBuilder is created on first open so streams that are never read cost nothing
+ */
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LazyVFileBuilder
+{
+  content : LazyContent,
+  size : u64,
+  #[serde(skip)]
+  builder : OnceLock<Arc<dyn VFileBuilder>>,
+}
+
+impl LazyVFileBuilder
+{
+  pub fn new(mapping : Vec<MappedRange>) -> Self
+  {
+    let size = mapping_size(&mapping);
+    LazyVFileBuilder{ content : LazyContent::Mapping(mapping), size, builder : OnceLock::new() }
+  }
+
+  /// stream of `size` bytes read from `runs`, the size of the runs if None,
+  /// the end of the last cluster past the size is not part of the stream
+  pub fn from_runs(runs : StreamRuns, size : Option<u64>) -> Result<Self>
+  {
+    let runs_size = runs.size()?;
+    let size = size.map_or(runs_size, |size| min(size, runs_size));
+    Ok(LazyVFileBuilder{ content : LazyContent::Runs(runs), size, builder : OnceLock::new() })
+  }
+}
+
+#[typetag::serde]
+impl VFileBuilder for LazyVFileBuilder
+{
+  fn open(&self) -> Result<Box<dyn VFile>>
+  {
+    let builder = match self.builder.get()
+    {
+      Some(builder) => builder,
+      None =>
+      {
+        let builder = match &self.content
+        {
+          LazyContent::Mapping(mapping) => to_builder(mapping),
+          LazyContent::Runs(runs) => to_builder(&resolve(&runs.mapping()?, 0, 0, self.size)),
+        };
+        self.builder.get_or_init(|| builder)
+      },
+    };
+    builder.open()
+  }
+
+  fn size(&self) -> u64
+  {
+    self.size
+  }
+}
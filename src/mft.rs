@@ -1,14 +1,80 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::io::{Seek, SeekFrom};
+use std::ops::Range;
 
 use tap::vfile::VFileBuilder;
 use tap::zerovfile::ZeroVFileBuilder;
 use tap::memoryvfile::MemoryVFileBuilder;
+use tap::mappedvfile::{MappedVFileBuilder, FileRanges};
 
-use crate::mftentry::MftEntry;
+use crate::mftentry::{MftEntry, MFT_HEADER_SIZE, MFT_SIGNATURE_FILE};
 use crate::error::NtfsError;
 use crate::ntfs::NtfsNode;
+use crate::ntfsattributes::NtfsAttributeType;
+use crate::attributecontent::ResidentType;
+use crate::attributes::bitmap::Bitmap;
+use crate::attributes::filename::NameSpacePreference;
+use crate::cache::{fnv1a, FNV_OFFSET};
 
 use anyhow::Result;
+use byteorder::{ByteOrder, LittleEndian};
+use lru::LruCache;
+use rayon::prelude::*;
+
+/// records copied in $MFTMirr, $MFT to $Volume
+const MFT_MIRROR_RECORDS : u64 = 4;
+
+/// number of records read to guess the geometry of an extracted MFT
+const PROBE_RECORDS : u64 = 16;
+
+/// MFT bigger than this are read through the prefetch window instead of being loaded in memory
+const STREAM_MFT_THRESHOLD : u64 = 1024 * 1024 * 1024;
+
+/// size of the sequential reads used to read ahead the records being parsed
+const PREFETCH_SIZE : u64 = 4 * 1024 * 1024;
+
+/// default memory used to keep parsed records referenced by attribute lists
+pub const DEFAULT_ENTRY_CACHE_SIZE : u64 = 16 * 1024 * 1024;
+
+/**
+ *  MFT data read ahead of the records being parsed,
+ *  so records are read with large sequential reads
+ */
+#[derive(Debug, Default)]
+struct PrefetchWindow
+{
+  start : u64,
+  data : Vec<u8>,
+}
+
+impl PrefetchWindow
+{
+  fn get(&self, offset : u64, size : u64) -> Option<&[u8]>
+  {
+    let start = offset.checked_sub(self.start)?;
+    self.data.get(start as usize..(start + size) as usize)
+  }
+
+  fn fill(&mut self, builder : &Arc<dyn VFileBuilder>, offset : u64, size : u64) -> Result<()>
+  {
+    self.start = offset;
+    self.data.clear();
+
+    let size = std::cmp::min(size, builder.size().saturating_sub(offset));
+    let mut file = builder.open()?;
+    file.seek(SeekFrom::Start(offset))?;
+    self.data.resize(size as usize, 0);
+    if let Err(err) = file.read_exact(&mut self.data)
+    {
+      self.data.clear();
+      return Err(err.into())
+    }
+    Ok(())
+  }
+}
 
 /**
  *  MftEntries
@@ -22,73 +88,295 @@ pub struct MftEntries
   zero_builder : Option<Arc<dyn VFileBuilder>>, //use for sparse non-resident 
   mft_record_size : u32,
   sector_size : u16,
-  cluster_size : Option<u32>, //use for non-resident fixup size
+  cluster_size : Option<u64>, //use for non-resident fixup size
   master_mft_builder : Arc<dyn VFileBuilder>,
   number_of_entry : u64,
   master_mft_entry : Option<MftEntry>,
+  mft_bitmap : Option<Vec<u8>>, //$MFT:$BITMAP record allocation state
+  entry_cache : Option<Mutex<LruCache<u64, Arc<MftEntry>>>>, //recently parsed records, None if disabled
+  pinned_entries : Mutex<Option<HashMap<u64, Arc<MftEntry>>>>, //records kept until unpinned, None if not pinning
+  cache_hits : AtomicU64,
+  cache_misses : AtomicU64,
+  prefetch : Mutex<PrefetchWindow>,
 }
 
 impl MftEntries 
 {
-  pub fn from_partition(partition_builder : Arc<dyn VFileBuilder>,  mft_logical_cluster_number : u64, cluster_size : u32, sector_size : u16, mft_record_size : u32) -> Result<MftEntries>
+  /// `stream_mft` choose between loading the MFT in memory or reading it by window, 
+  /// if None the MFT is streamed when it's bigger than STREAM_MFT_THRESHOLD
+  pub fn from_partition(partition_builder : Arc<dyn VFileBuilder>,  mft_logical_cluster_number : u64, cluster_size : u64, sector_size : u16, mft_record_size : u32, stream_mft : Option<bool>) -> Result<MftEntries>
+  {
+    MftEntries::check_record_size(mft_record_size)?;
+    let master_mft_offset = mft_logical_cluster_number.checked_mul(cluster_size).ok_or(NtfsError::Overflow("MFT offset"))?;
+    let master_mft_entry = MftEntries::read_master_entry(&partition_builder, master_mft_offset, cluster_size, sector_size, mft_record_size)?;
+    let master_mft_builder = master_mft_entry.data_attribute()?;
+
+    MftEntries::with_partition(partition_builder, master_mft_entry, master_mft_builder, cluster_size, sector_size, mft_record_size, stream_mft)
+  }
+
+  /// read the $MFT record from $MFTMirr when the first cluster of $MFT is unreadable,
+  /// the records copied in $MFTMirr are read from it and the others from the MFT runs
+  pub fn from_mirror(partition_builder : Arc<dyn VFileBuilder>, mft_mirror_logical_cluster_number : u64, cluster_size : u64, sector_size : u16, mft_record_size : u32, stream_mft : Option<bool>) -> Result<MftEntries>
+  {
+    MftEntries::check_record_size(mft_record_size)?;
+    let mirror_offset = mft_mirror_logical_cluster_number.checked_mul(cluster_size).ok_or(NtfsError::Overflow("MFT mirror offset"))?;
+    let master_mft_entry = MftEntries::read_master_entry(&partition_builder, mirror_offset, cluster_size, sector_size, mft_record_size)?;
+    let mft_builder = master_mft_entry.data_attribute()?;
+
+    let mirror_size = (MFT_MIRROR_RECORDS * mft_record_size as u64).min(mft_builder.size());
+    let mut file_ranges = FileRanges::new();
+    file_ranges.push(0..mirror_size, mirror_offset, partition_builder.clone());
+    if mirror_size < mft_builder.size()
+    {
+      file_ranges.push(mirror_size..mft_builder.size(), mirror_size, mft_builder);
+    }
+    let master_mft_builder : Arc<dyn VFileBuilder> = Arc::new(MappedVFileBuilder::new(file_ranges));
+
+    MftEntries::with_partition(partition_builder, master_mft_entry, master_mft_builder, cluster_size, sector_size, mft_record_size, stream_mft)
+  }
+
+  /// read the records from an extracted MFT and the non-resident data from the partition,
+  /// when the MFT runs in the partition can't be read
+  pub fn from_master_mft_with_partition(master_mft_builder : Arc<dyn VFileBuilder>, partition_builder : Arc<dyn VFileBuilder>, cluster_size : u64, sector_size : u16, mft_record_size : u32, stream_mft : Option<bool>) -> Result<MftEntries>
+  {
+    MftEntries::check_record_size(mft_record_size)?;
+    let zero_builder = Arc::new(ZeroVFileBuilder{});
+    let master_mft_entry = MftEntry::from_offset(0, Some(partition_builder.clone()), master_mft_builder.clone(), Some(zero_builder), mft_record_size, sector_size, Some(cluster_size))?;
+
+    MftEntries::with_partition(partition_builder, master_mft_entry, master_mft_builder, cluster_size, sector_size, mft_record_size, stream_mft)
+  }
+
+  fn check_record_size(mft_record_size : u32) -> Result<()>
   {
-    //check value bound
     if mft_record_size == 0
     {
       return Err(NtfsError::MftRecordSize{}.into())
     }
+    if mft_record_size < MFT_HEADER_SIZE
+    {
+      return Err(NtfsError::MftRecordSizeTooSmall(mft_record_size).into())
+    }
+    Ok(())
+  }
 
-    
-
-    let master_mft_offset = mft_logical_cluster_number * cluster_size as u64;
+  /// $MFT record read from a partition offset
+  fn read_master_entry(partition_builder : &Arc<dyn VFileBuilder>, offset : u64, cluster_size : u64, sector_size : u16, mft_record_size : u32) -> Result<MftEntry>
+  {
     let zero_builder = Arc::new(ZeroVFileBuilder{});
+    let mut master_mft_entry = MftEntry::from_offset(offset, Some(partition_builder.clone()), partition_builder.clone(), Some(zero_builder), mft_record_size, sector_size, Some(cluster_size))?;
+    master_mft_entry.id = 0; //read from the partition offset
+    Ok(master_mft_entry)
+  }
 
-    let master_mft_entry = MftEntry::from_offset(master_mft_offset, Some(partition_builder.clone()), partition_builder.clone(), Some(zero_builder.clone()), mft_record_size, sector_size, Some(cluster_size))?;
-    let master_mft_builder = master_mft_entry.data_attribute()?;
-    let master_mft_builder = MemoryVFileBuilder::new(master_mft_builder.clone())?;//Use in memory cache of MFT
+  fn with_partition(partition_builder : Arc<dyn VFileBuilder>, master_mft_entry : MftEntry, master_mft_builder : Arc<dyn VFileBuilder>, cluster_size : u64, sector_size : u16, mft_record_size : u32, stream_mft : Option<bool>) -> Result<MftEntries>
+  {
+    let master_mft_builder = MftEntries::mft_storage(master_mft_builder, stream_mft)?;
 
-    let number_of_entry = master_mft_builder.size() / mft_record_size as u64;
+    //only the bits of the records of $MFT:$DATA are read from $BITMAP
+    let record_count = MftEntries::entry_count(&master_mft_entry, master_mft_builder.size(), mft_record_size, &None);
+    let mft_bitmap = MftEntries::read_bitmap(&master_mft_entry, record_count);
+    let number_of_entry = MftEntries::entry_count(&master_mft_entry, master_mft_builder.size(), mft_record_size, &mft_bitmap);
 
     Ok(MftEntries{
       partition_builder : Some(partition_builder),
-      zero_builder : Some(zero_builder), //used only for non-resident
+      zero_builder : Some(Arc::new(ZeroVFileBuilder{})), //used only for non-resident
       mft_record_size,
       cluster_size : Some(cluster_size), //used only for non-resident
       sector_size, 
       master_mft_builder,
       number_of_entry,
       master_mft_entry : Some(master_mft_entry),
+      mft_bitmap,
+      entry_cache : MftEntries::entry_cache(DEFAULT_ENTRY_CACHE_SIZE, mft_record_size),
+      pinned_entries : Mutex::new(None),
+      cache_hits : AtomicU64::new(0),
+      cache_misses : AtomicU64::new(0),
+      prefetch : Mutex::new(PrefetchWindow::default()),
     })
   }
 
-  pub fn from_master_mft(master_mft_builder : Arc<dyn VFileBuilder>, sector_size : Option<u16>, mft_record_size : Option<u32>) -> Result<MftEntries>
+  pub fn from_master_mft(master_mft_builder : Arc<dyn VFileBuilder>, sector_size : Option<u16>, mft_record_size : Option<u32>, stream_mft : Option<bool>) -> Result<MftEntries>
   {
-    let master_mft_builder = MemoryVFileBuilder::new(master_mft_builder.clone())?;//Use in memory cache of MFT
+    let master_mft_builder = MftEntries::mft_storage(master_mft_builder, stream_mft)?;
 
-    let sector_size = sector_size.unwrap_or(512);
+    //try to find geometry by validating fixup of the first records if not provided
+    let probed = match (sector_size, mft_record_size)
+    {
+      (Some(_), Some(_)) => None,
+      _ => MftEntries::probe_geometry(master_mft_builder.clone()),
+    };
+
+    let sector_size = sector_size.or_else(|| probed.map(|(sector_size, _)| sector_size)).unwrap_or(512);
 
-    let mft_record_size = match mft_record_size
+    let mft_record_size = match mft_record_size.or_else(|| probed.map(|(_, record_size)| record_size))
     {
       Some(mft_record_size) => mft_record_size,
       None =>  MftEntry::from_offset(0, None, master_mft_builder.clone(), Some(Arc::new(ZeroVFileBuilder{})), 4096, sector_size, Some(4096))?.allocated_size,
     };
 
+    match mft_record_size
+    {
+      0 => return Err(NtfsError::MftRecordSize.into()),
+      size if size < MFT_HEADER_SIZE => return Err(NtfsError::MftRecordSizeTooSmall(size).into()),
+      _ => (),
+    }
+
+    //$DATA header of entry 0 is resident in the record and give us the initialized size
     let master_mft_builder_size = master_mft_builder.size();
+    let number_of_entry = match MftEntry::from_offset(0, None, master_mft_builder.clone(), None, mft_record_size, sector_size, None)
+    {
+      Ok(master_mft_entry) => MftEntries::entry_count(&master_mft_entry, master_mft_builder_size, mft_record_size, &None),
+      Err(_) => master_mft_builder_size / mft_record_size as u64,
+    };
 
-    match mft_record_size
+    Ok(MftEntries{
+      partition_builder : None,
+      zero_builder : None,
+      mft_record_size,
+      cluster_size : None,
+      sector_size,  
+      master_mft_builder,
+      number_of_entry,
+      master_mft_entry : None,
+      mft_bitmap : None, //$BITMAP is non-resident and can't be read without the partition
+      entry_cache : MftEntries::entry_cache(DEFAULT_ENTRY_CACHE_SIZE, mft_record_size),
+      pinned_entries : Mutex::new(None),
+      cache_hits : AtomicU64::new(0),
+      cache_misses : AtomicU64::new(0),
+      prefetch : Mutex::new(PrefetchWindow::default()),
+    })
+  }
+
+  /// use an in memory cache of the MFT unless it must be streamed
+  fn mft_storage(master_mft_builder : Arc<dyn VFileBuilder>, stream_mft : Option<bool>) -> Result<Arc<dyn VFileBuilder>>
+  {
+    let stream_mft = stream_mft.unwrap_or(master_mft_builder.size() > STREAM_MFT_THRESHOLD);
+    match stream_mft
+    {
+      true => Ok(master_mft_builder),
+      false => Ok(MemoryVFileBuilder::new(master_mft_builder)?),
+    }
+  }
+
+  /// guess (fixup stride, record size) of an extracted MFT by checking that FILE records
+  /// are spaced by the candidate size and that every fixup slot contains the update sequence number
+  pub fn probe_geometry(master_mft_builder : Arc<dyn VFileBuilder>) -> Option<(u16, u32)>
+  {
+    let probe_size = std::cmp::min(master_mft_builder.size(), PROBE_RECORDS * 4096) as usize;
+    let mut data = vec![0u8; probe_size];
+    let mut file = master_mft_builder.open().ok()?;
+    file.read_exact(&mut data).ok()?;
+
+    for record_size in [512, 1024, 2048, 4096]
     {
-      0 => Err(NtfsError::MftRecordSize.into()),
-      _ => Ok(MftEntries{
-        partition_builder : None,
-        zero_builder : None,
-        mft_record_size,
-        cluster_size : None,
-        sector_size,  
-        master_mft_builder,
-        number_of_entry : master_mft_builder_size / mft_record_size as u64,
-        master_mft_entry : None,
-      })
+      let mut stride = None;
+      let mut records = 0;
+      let mut valid = true;
+
+      for record in data.chunks_exact(record_size)
+      {
+        if LittleEndian::read_u32(&record[0..4]) != MFT_SIGNATURE_FILE
+        {
+          continue
+        }
+        records += 1;
+
+        match MftEntries::probe_fixup(record)
+        {
+          Some(record_stride) if stride.is_none() || stride == Some(record_stride) => stride = Some(record_stride),
+          _ => { valid = false; break },
+        }
+      }
+
+      if valid && records > 0
+      {
+        if let Some(stride) = stride
+        {
+          return Some((stride as u16, record_size as u32))
+        }
+      }
     }
+    None
+  }
+
+  /// return the fixup stride if the update sequence array is consistent with the record size
+  fn probe_fixup(record : &[u8]) -> Option<usize>
+  {
+    let fixup_offset = LittleEndian::read_u16(&record[4..6]) as usize;
+    let fixup_count = LittleEndian::read_u16(&record[6..8]) as usize;
+
+    if fixup_count < 2 || record.len() % (fixup_count - 1) != 0 || fixup_offset + 2 > record.len()
+    {
+      return None
+    }
+
+    let stride = record.len() / (fixup_count - 1);
+    if stride < 512 || stride > u16::MAX as usize
+    {
+      return None
+    }
+
+    let usn = &record[fixup_offset..fixup_offset + 2];
+    for sector in 1..fixup_count
+    {
+      let end = sector * stride;
+      if &record[end - 2..end] != usn
+      {
+        return None
+      }
+    }
+    Some(stride)
+  }
+
+  /// number of records to enumerate, bounded by the $MFT initialized size and $BITMAP length
+  /// so allocated but uninitialized tail space isn't read
+  fn entry_count(master_mft_entry : &MftEntry, data_size : u64, mft_record_size : u32, mft_bitmap : &Option<Vec<u8>>) -> u64
+  {
+    let mut size = data_size;
+
+    let initialized_size = master_mft_entry.contents().into_iter()
+      .filter(|content| content.mft_attribute.type_id == NtfsAttributeType::Data && content.mft_attribute.name.is_none())
+      .find_map(|content| match &content.mft_attribute.data
+      {
+        ResidentType::NonResident(non_resident) if non_resident.vnc_start == 0 => Some(non_resident.content_initialized_size),
+        _ => None,
+      });
+
+    if let Some(initialized_size) = initialized_size
+    {
+      if initialized_size != 0
+      {
+        size = std::cmp::min(size, initialized_size);
+      }
+    }
+
+    let mut count = size / mft_record_size as u64;
+    if let Some(bitmap) = mft_bitmap.as_ref().filter(|bitmap| !bitmap.is_empty())
+    {
+      count = std::cmp::min(count, bitmap.len() as u64 * 8);
+    }
+    count
+  }
+
+  fn read_bitmap(master_mft_entry : &MftEntry, record_count : u64) -> Option<Vec<u8>>
+  {
+    master_mft_entry.contents().into_iter()
+      .find(|content| content.mft_attribute.type_id == NtfsAttributeType::Bitmap && content.mft_attribute.name.is_none())
+      .and_then(|content| content.builder().ok())
+      .and_then(|builder| Bitmap::read(builder, record_count).ok())
+  }
+
+  /// create a cache holding as many records as fit in `cache_size` bytes
+  fn entry_cache(cache_size : u64, mft_record_size : u32) -> Option<Mutex<LruCache<u64, Arc<MftEntry>>>>
+  {
+    let capacity = usize::try_from(cache_size / mft_record_size as u64).ok()?;
+    NonZeroUsize::new(capacity).map(|capacity| Mutex::new(LruCache::new(capacity)))
+  }
+
+  /// limit the memory used by the parsed records cache, 0 disable the cache
+  pub fn set_entry_cache_size(&mut self, cache_size : u64)
+  {
+    self.entry_cache = MftEntries::entry_cache(cache_size, self.mft_record_size);
   }
 
   pub fn count(&self) -> u64
@@ -96,11 +384,23 @@ impl MftEntries
     self.number_of_entry
   }
 
+  /// allocation state of a record according to $MFT:$BITMAP, None if the bitmap is unavailable
+  pub fn is_allocated(&self, entry_id : u64) -> Option<bool>
+  {
+    self.mft_bitmap.as_ref().and_then(|bitmap| Bitmap::is_set(bitmap, entry_id))
+  }
+
+  /// number of allocated records according to $MFT:$BITMAP
+  pub fn allocated_count(&self) -> Option<u64>
+  {
+    self.mft_bitmap.as_ref().map(|_| (0..self.number_of_entry).filter(|id| self.is_allocated(*id) == Some(true)).count() as u64)
+  }
+
   pub fn master_mft(&self) -> Option<NtfsNode> 
   {
     let mut node = match &self.master_mft_entry
     {
-      Some(master_mft_entry) => NtfsNode::from_entry(0, master_mft_entry, self),
+      Some(master_mft_entry) => NtfsNode::from_entry(0, master_mft_entry, Some(self), NameSpacePreference::default(), None),
       None => return None,
     };
 
@@ -111,9 +411,225 @@ impl MftEntries
     None
   }
 
-  //create an iterator XXX 
+  /// keep every record read with `cached_entry` until `unpin_entries` is called,
+  /// so extension records shared by many attribute lists are parsed only once
+  pub fn pin_entries(&self)
+  {
+    if let Ok(mut pinned_entries) = self.pinned_entries.lock()
+    {
+      *pinned_entries = Some(HashMap::new());
+    }
+  }
+
+  /// release the records kept since `pin_entries`
+  pub fn unpin_entries(&self)
+  {
+    if let Ok(mut pinned_entries) = self.pinned_entries.lock()
+    {
+      *pinned_entries = None;
+    }
+  }
+
+  /// return a parsed record from the cache or read it and keep it in the cache,
+  /// used for records that are read multiple time like attribute list extensions
+  pub fn cached_entry(&self, entry_id : u64) -> Result<Arc<MftEntry>>
+  {
+    if let Ok(mut pinned_entries) = self.pinned_entries.lock()
+    {
+      if let Some(pinned_entries) = pinned_entries.as_mut()
+      {
+        if let Some(entry) = pinned_entries.get(&entry_id)
+        {
+          self.cache_hits.fetch_add(1, Ordering::Relaxed);
+          return Ok(entry.clone())
+        }
+        self.cache_misses.fetch_add(1, Ordering::Relaxed);
+        let entry = Arc::new(self.entry(entry_id)?);
+        pinned_entries.insert(entry_id, entry.clone());
+        return Ok(entry)
+      }
+    }
+
+    let cache = match &self.entry_cache
+    {
+      Some(cache) => cache,
+      None => return Ok(Arc::new(self.entry(entry_id)?)),
+    };
+
+    if let Some(entry) = cache.lock().ok().and_then(|mut cache| cache.get(&entry_id).cloned())
+    {
+      self.cache_hits.fetch_add(1, Ordering::Relaxed);
+      return Ok(entry)
+    }
+
+    self.cache_misses.fetch_add(1, Ordering::Relaxed);
+    let entry = Arc::new(self.entry(entry_id)?);
+    if let Ok(mut cache) = cache.lock()
+    {
+      cache.put(entry_id, entry.clone());
+    }
+    Ok(entry)
+  }
+
+  pub fn partition_builder(&self) -> Option<&Arc<dyn VFileBuilder>>
+  {
+    self.partition_builder.as_ref()
+  }
+
+  pub fn zero_builder(&self) -> Option<&Arc<dyn VFileBuilder>>
+  {
+    self.zero_builder.as_ref()
+  }
+
+  pub fn sector_size(&self) -> u16
+  {
+    self.sector_size
+  }
+
+  pub fn mft_record_size(&self) -> u32
+  {
+    self.mft_record_size
+  }
+
+  /// None if the MFT was opened without its partition
+  pub fn cluster_size(&self) -> Option<u64>
+  {
+    self.cluster_size
+  }
+
+  pub fn master_mft_builder(&self) -> &Arc<dyn VFileBuilder>
+  {
+    &self.master_mft_builder
+  }
+
+  /// hash of the MFT content, used to know if a volume changed since it was cached
+  pub fn content_hash(&self) -> Result<u64>
+  {
+    let mut file = self.master_mft_builder.open()?;
+    let mut remaining = self.master_mft_builder.size();
+    let mut buffer = vec![0u8; std::cmp::min(remaining, 1024 * 1024) as usize];
+    let mut hash = FNV_OFFSET;
+
+    while remaining > 0
+    {
+      let size = std::cmp::min(remaining, buffer.len() as u64) as usize;
+      file.read_exact(&mut buffer[..size])?;
+      hash = fnv1a(hash, &buffer[..size]);
+      remaining -= size as u64;
+    }
+    Ok(hash)
+  }
+
+  /// number of (hits, misses) of `cached_entry` 
+  pub fn cache_stats(&self) -> (u64, u64)
+  {
+    (self.cache_hits.load(Ordering::Relaxed), self.cache_misses.load(Ordering::Relaxed))
+  }
+
+  /// read a record directly, used for random lookups (attribute lists, paths) so they never move the prefetch window
   pub fn entry(&self, entry_id : u64) -> Result<MftEntry> 
   {
-    MftEntry::from_offset(entry_id * self.mft_record_size as u64, self.partition_builder.clone(), self.master_mft_builder.clone(), self.zero_builder.clone(), self.mft_record_size, self.sector_size, self.cluster_size)
+    self.read_entry(entry_id, false)
+  }
+
+  /// read a record through the prefetch window, for callers reading the records in increasing order
+  pub fn sequential_entry(&self, entry_id : u64) -> Result<MftEntry> 
+  {
+    self.read_entry(entry_id, true)
+  }
+
+  /// iterate over every entry of the MFT in order
+  pub fn iter(&self) -> MftEntriesIter<'_>
+  {
+    self.iter_range(0..self.number_of_entry)
+  }
+
+  /// iterate over the entries of `ids`, skipped entries are not read
+  pub fn iter_range(&self, ids : Range<u64>) -> MftEntriesIter<'_>
+  {
+    MftEntriesIter{ mft_entries : self, ids }
+  }
+
+  /// parse every entry of the MFT in parallel, records are read without the prefetch window 
+  /// as the threads don't read them sequentially
+  pub fn par_iter(&self) -> impl ParallelIterator<Item = (u64, Result<MftEntry>)> + '_
+  {
+    (0..self.number_of_entry).into_par_iter().map(move |entry_id| (entry_id, self.read_entry(entry_id, false)))
+  }
+
+  fn read_entry(&self, entry_id : u64, prefetch : bool) -> Result<MftEntry>
+  {
+    let offset = entry_id.checked_mul(self.mft_record_size as u64).ok_or(NtfsError::Overflow("MFT entry offset"))?;
+    let data = match prefetch
+    {
+      true => self.read_record(offset)?,
+      false => self.read_record_direct(offset)?,
+    };
+    MftEntry::from_data(data, offset, self.partition_builder.clone(), self.master_mft_builder.clone(), self.zero_builder.clone(), self.sector_size, self.cluster_size)
+  }
+
+  /// read a record through the prefetch window, the window only slides when the record is at or past its end
+  /// as only sequential readers use it, a record before the end of the window is read directly
+  fn read_record(&self, offset : u64) -> Result<Vec<u8>>
+  {
+    let size = self.mft_record_size as u64;
+
+    if let Ok(mut window) = self.prefetch.lock()
+    {
+      let window_end = window.start + window.data.len() as u64;
+      if window.get(offset, size).is_none() && (window.data.is_empty() || offset >= window_end)
+      {
+        //on error the record is read directly so the caller get the real error
+        let _ = window.fill(&self.master_mft_builder, offset, std::cmp::max(PREFETCH_SIZE, size));
+      }
+      if let Some(record) = window.get(offset, size)
+      {
+        return Ok(record.to_vec())
+      }
+    }
+
+    self.read_record_direct(offset)
+  }
+
+  fn read_record_direct(&self, offset : u64) -> Result<Vec<u8>>
+  {
+    let mut file = self.master_mft_builder.open()?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut data = vec![0u8; self.mft_record_size as usize];
+    file.read_exact(&mut data)?;
+    Ok(data)
+  }
+}
+
+/**
+ *  Iterator over the entries of the MFT returning the entry id 
+ *  with the parsed entry or the error that prevented parsing it
+ */
+pub struct MftEntriesIter<'a>
+{
+  mft_entries : &'a MftEntries,
+  ids : Range<u64>,
+}
+
+impl Iterator for MftEntriesIter<'_>
+{
+  type Item = (u64, Result<MftEntry>);
+
+  fn next(&mut self) -> Option<Self::Item>
+  {
+    let entry_id = self.ids.next()?;
+    Some((entry_id, self.mft_entries.sequential_entry(entry_id)))
+  }
+
+  /// skipped entries are not read
+  fn nth(&mut self, n : usize) -> Option<Self::Item>
+  {
+    let entry_id = self.ids.nth(n)?;
+    Some((entry_id, self.mft_entries.sequential_entry(entry_id)))
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>)
+  {
+    self.ids.size_hint()
   }
 }
@@ -1,16 +1,26 @@
+use std::fmt;
 use std::sync::Arc;
+use std::borrow::Cow;
 use std::io::SeekFrom;
 use std::io::Seek;
 use std::io::Read;
 
 use tap::vfile::VFileBuilder;
-use tap::mappedvfile::{MappedVFileBuilder,FileRanges};
+#[cfg(feature = "plugin")]
+use tap::reflect::ReflectStruct;
+#[cfg(feature = "plugin")]
+use tap::value::Value;
+#[cfg(feature = "plugin")]
+use tap_derive::Reflect;
+use serde::{Serialize, Deserialize};
+use serde_json::json;
 
 use crate::error::NtfsError;
 use crate::mft::MftEntries;
+use crate::mapping::{MappedRange, to_builder};
 use crate::ntfsattributes::{NtfsAttribute, NtfsAttributes, NtfsAttributeType};
-use crate::attributecontent::MftAttributeContent;
-use crate::attributecontent::pad_u64;
+use crate::attributecontent::{MftAttributeContent, ResidentType};
+use crate::attributecontent::{pad_u64, read_builder};
 use crate::attributes::standard::StandardInformation;
 use crate::attributes::filename::FileName;
 use crate::attributes::list::AttributeList;
@@ -25,12 +35,145 @@ use byteorder::{ByteOrder, LittleEndian};
  */
 pub const MFT_SIGNATURE_FILE : u32 = 0x454C4946; //FILE
 pub const MFT_SIGNATURE_BAAD : u32 = 0x44414142; //BAAD
+pub const MFT_HEADER_SIZE : u32 = 48; //NTFS 3.1 header, 1.x use the first 42 bytes
+/// number of bytes of resident contents shown by describe
+const DESCRIBE_PREVIEW_SIZE : usize = 64;
 
-#[derive(Debug)]
+/**
+ *  Update sequence of a record
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "plugin", derive(Reflect))]
+pub struct RecordFixup
+{
+  pub update_sequence_number : u16,
+  /// original end of sector values stored in the fixup array
+  pub original_values : String,
+  /// number of sectors whose last two bytes doesn't match the update sequence number
+  pub mismatch_count : u32,
+}
+
+/**
+ *  Decoded record header flags
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "plugin", derive(Reflect))]
+pub struct RecordFlags
+{
+  pub in_use : bool,
+  pub directory : bool,
+  /// record is in $Extend (0x4)
+  pub in_extend : bool,
+  /// record has a view index like $Secure or $Quota (0x8)
+  pub view_index : bool,
+  pub value : u16,
+}
+
+/**
+ *  Parsing context of a record shared by all its attributes,
+ *  resident content is borrowed from the record buffer instead of reopening a builder
+ */
+pub struct RecordContext<'a>
+{
+  /// record content with the fixups applied
+  pub data : &'a [u8],
+  /// fixup aware mapping of the record, used by resident data builders
+  pub mapping : Arc<Vec<MappedRange>>,
+}
+
+impl<'a> RecordContext<'a>
+{
+  /// content of an attribute, borrowed from the record when resident or read once from its builder
+  pub fn content(&self, content : &MftAttributeContent) -> Result<Cow<'a, [u8]>>
+  {
+    match &content.mft_attribute.data
+    {
+      ResidentType::Resident(resident) =>
+      {
+        let offset = content.offset as u64 + resident.content_offset as u64;
+        let size = resident.content_size as u64;
+        match self.data.get(offset as usize..(offset + size) as usize)
+        {
+          Some(data) => Ok(Cow::Borrowed(data)),
+          None => Err(NtfsError::ResidentAttributeContentTooLarge{ type_id : content.mft_attribute.type_id.clone(), entry : content.entry, offset, size }.into()),
+        }
+      },
+      ResidentType::NonResident(_) => Ok(Cow::Owned(read_builder(content.builder()?)?)),
+    }
+  }
+}
+
+/**
+ *  Lazy iterator over the attributes of a record, 
+ *  so caller can stop parsing as soon as it found what it needs
+ */
+pub struct AttributesIter<'a>
+{
+  entry : &'a MftEntry,
+  context : RecordContext<'a>,
+  mft_entries : Option<&'a MftEntries>,
+  offset : Option<u32>, //next attribute offset, None at the end of the record
+  pending : std::vec::IntoIter<NtfsAttribute>, //attributes of the current attribute list
+  errors : Vec<String>,
+  types : Option<&'a [NtfsAttributeType]>, //only parse attributes of these types if set
+}
+
+impl<'a> AttributesIter<'a>
+{
+  /// skip attributes that are not of one of `types` without parsing their content
+  pub fn only(mut self, types : &'a [NtfsAttributeType]) -> Self
+  {
+    self.types = Some(types);
+    self
+  }
+
+  /// errors encountered while parsing the attributes returned so far
+  pub fn errors(&self) -> &[String]
+  {
+    &self.errors
+  }
+
+  /// parse the remaining attributes
+  pub fn into_attributes(mut self) -> NtfsAttributes
+  {
+    let attributes = self.by_ref().collect();
+    NtfsAttributes::with_errors(attributes, self.errors)
+  }
+}
+
+impl Iterator for AttributesIter<'_>
+{
+  type Item = NtfsAttribute;
+
+  fn next(&mut self) -> Option<NtfsAttribute>
+  {
+    loop
+    {
+      if let Some(attribute) = self.pending.next()
+      {
+        return Some(attribute)
+      }
+
+      let content = self.entry.next_content(&self.context, &mut self.offset, &mut self.errors)?;
+      if matches!(self.types, Some(types) if !types.contains(&content.mft_attribute.type_id))
+      {
+        continue
+      }
+      self.pending = MftEntry::content_to_attribute(&self.context, content, self.mft_entries, &mut self.errors).into_iter();
+    }
+  }
+}
+
+/// builders and raw record content are not serialized, only the parsed header
+#[derive(Debug, Serialize)]
 pub struct MftEntry
 {
+  pub id : u64, //entry number, offset / record size when read from the MFT
+  #[serde(skip)]
   pub partition_builder : Option<Arc<dyn VFileBuilder>>,
+  #[serde(skip)]
   pub mft_builder : Arc<dyn VFileBuilder>, //partition or full mft file 
+  #[serde(skip)]
   pub zero_builder : Option<Arc<dyn VFileBuilder>>,
   pub offset : u64,
   pub record_size : u32,
@@ -47,28 +190,48 @@ pub struct MftEntry
   pub file_reference_id : u64,
   pub file_reference_sequence : u16,
   pub next_attribute_id : u16,
+  pub record_number : Option<u32>,
   pub sector_size : u16,
-  pub cluster_size : Option<u32>,
+  pub cluster_size : Option<u64>,
+  /// record content with the fixups applied
+  #[serde(skip)]
+  pub data : Vec<u8>,
+  /// update sequence read when the record was loaded, None if the array doesn't fit in the record
+  pub fixup : Option<RecordFixup>,
 }
 
 impl MftEntry
 {
-  pub fn from_offset(offset : u64, partition_builder : Option<Arc<dyn VFileBuilder>>, mft_builder : Arc<dyn VFileBuilder>, zero_builder : Option<Arc<dyn VFileBuilder>>, record_size : u32, sector_size : u16, cluster_size : Option<u32>) -> Result<MftEntry>
+  pub fn from_offset(offset : u64, partition_builder : Option<Arc<dyn VFileBuilder>>, mft_builder : Arc<dyn VFileBuilder>, zero_builder : Option<Arc<dyn VFileBuilder>>, record_size : u32, sector_size : u16, cluster_size : Option<u64>) -> Result<MftEntry>
   {
-    let mut file = mft_builder.open()?;
+    if record_size < MFT_HEADER_SIZE
+    {
+      return Err(NtfsError::MftRecordSizeTooSmall(record_size).into())
+    }
 
+    //the whole record is read once, header and attributes are then parsed from memory
+    let mut file = mft_builder.open()?;
     file.seek(SeekFrom::Start(offset))?;
-
-    //let offset = file.tell(); //we get our absolute offset 
-    let mut data = [0;42]; 
+    let mut data = vec![0u8; record_size as usize];
     file.read_exact(&mut data)?;
-    //first 3 u8 contain the jmp code
+
+    MftEntry::from_data(data, offset, partition_builder, mft_builder, zero_builder, sector_size, cluster_size)
+  }
+
+  /// parse a record already read from `offset` of the MFT builder, the record size is the size of `data`
+  pub fn from_data(mut data : Vec<u8>, offset : u64, partition_builder : Option<Arc<dyn VFileBuilder>>, mft_builder : Arc<dyn VFileBuilder>, zero_builder : Option<Arc<dyn VFileBuilder>>, sector_size : u16, cluster_size : Option<u64>) -> Result<MftEntry>
+  {
+    let record_size = u32::try_from(data.len()).map_err(|_| NtfsError::Overflow("record size"))?;
+    if record_size < MFT_HEADER_SIZE
+    {
+      return Err(NtfsError::MftRecordSizeTooSmall(record_size).into())
+    }
 
     let signature = LittleEndian::read_u32(&data[0..4]);
 
     //if (signature != MFT_SIGNATURE_FILE) // && signature != MFT_SIGNATURE_BAAD) 
     //{
-      //return Err(NtfsError::MftInvalidSignature.into())
+      //return Err(NtfsError::MftInvalidSignature{ entry : id, signature }.into())
     //}
     //XXX if is baad set as deleted
 
@@ -89,17 +252,28 @@ impl MftEntry
     let first_attribute_offset = LittleEndian::read_u16(&data[20..22]);
     let flags = LittleEndian::read_u16(&data[22..24]);
     let used_size = LittleEndian::read_u32(&data[24..28]);
+    let id = offset.checked_div(record_size as u64).unwrap_or(0);
     if used_size == 0xffffffff
     {
-      return Err(NtfsError::MftUnusedEntry{}.into());
+      return Err(NtfsError::MftUnusedEntry{ entry : id }.into());
     }
     let allocated_size = LittleEndian::read_u32(&data[28..32]);
     //let file_reference_to_base_record = LittleEndian::read_u64(&data[32..40]);
     let file_reference_id = pad_u64(&data[32..38]); 
     let file_reference_sequence = LittleEndian::read_u16(&data[38..40]); 
     let next_attribute_id = LittleEndian::read_u16(&data[40..42]);
+    //NTFS 3.1 store the record number at 0x2c, before the fixup array which start at 0x30
+    let record_number = match fixup_array_offset >= 0x30
+    {
+      true => Some(LittleEndian::read_u32(&data[44..48])),
+      false => None,
+    };
+
+    let stride = MftEntry::stride(record_size as u64, fixup_array_entry_count as u64, sector_size);
+    let fixup = MftEntry::apply_fixup(&mut data, fixup_array_offset, fixup_array_entry_count, stride);
 
     let mft_entry = MftEntry{
+        id,
         partition_builder,
         mft_builder,
         zero_builder,
@@ -119,109 +293,221 @@ impl MftEntry
         file_reference_id,
         file_reference_sequence,
         next_attribute_id,
+        record_number,
         sector_size,
         cluster_size,
+        data,
+        fixup,
     };
 
     Ok(mft_entry)
   }
 
   pub fn contents(&self) -> Vec<MftAttributeContent>
+  {
+    self.parse_contents().0
+  }
+
+  /// parsing context sharing the record buffer and builder between all the attributes
+  pub fn context(&self) -> RecordContext
+  {
+    RecordContext{ data : &self.data, mapping : Arc::new(self.mapping()) }
+  }
+
+  /// return attributes content and the description of the errors that stopped the parsing
+  pub fn parse_contents(&self) -> (Vec<MftAttributeContent>, Vec<String>)
+  {
+    self.parse_contents_with(&self.context())
+  }
+
+  fn parse_contents_with(&self, context : &RecordContext) -> (Vec<MftAttributeContent>, Vec<String>)
   {
     let mut contents = Vec::new();
-    let mft_entry = self.to_builder();
-    let mut file = match mft_entry.open()
+    let mut errors = Vec::new();
+    let mut offset = Some(self.first_attribute_offset as u32);
+
+    while let Some(content) = self.next_content(context, &mut offset, &mut errors)
+    {
+      contents.push(content);
+    }
+
+    (contents, errors)
+  }
+
+  /// parse the attribute at `offset` and move it to the next attribute, `offset` is None when the end is reached
+  fn next_content(&self, context : &RecordContext, offset : &mut Option<u32>, errors : &mut Vec<String>) -> Option<MftAttributeContent>
+  {
+    let current = offset.take().filter(|current| *current < self.used_size)?;
+
+    //entry builder for resident, whole dump builder for nonresident
+    let content = match MftAttributeContent::from_slice(context.data, self.id, current, context.mapping.clone(), &self.partition_builder, &self.zero_builder, self.cluster_size)
     {
-      Ok(file) => file,
-      Err(_) => return contents,
+      Ok(content) => content,
+      Err(err) => 
+      {
+        if !matches!(err.downcast_ref::<NtfsError>(), Some(NtfsError::MftAttributesEnd))
+        {
+          errors.push(format!("attribute at offset {} : {}", current, err));
+        }
+        return None
+      }
     };
-    let mut offset : u32 = self.first_attribute_offset as u32;
 
-    while offset < self.used_size
+    let mft_attribute_length = content.mft_attribute.length;
+    if mft_attribute_length != 0
     {
-       //entry builder for resident, whole dump builder for nonresident
-       let content = match MftAttributeContent::from_file(&mut file, offset, mft_entry.clone(), &self.partition_builder, &self.zero_builder, self.cluster_size)
-       {
-         Ok(content) => content,
-         Err(_err) => break, //XXX catch end of attribute here to stop
-       };
-
-       let mft_attribute_length = content.mft_attribute.length;
-       contents.push(content);
-       if mft_attribute_length == 0
-       {    
-          break
-       }
-       offset += mft_attribute_length;
+      *offset = current.checked_add(mft_attribute_length);
+      if offset.is_none()
+      {
+        errors.push(format!("attribute at offset {} length {} overflow", current, mft_attribute_length));
+      }
     }
 
-    contents
+    Some(content)
   }
 
-  fn content_to_attribute(&self, content : MftAttributeContent, mft_entries : Option<&MftEntries>) ->Vec<NtfsAttribute>
+  fn content_to_attribute(context : &RecordContext, content : MftAttributeContent, mft_entries : Option<&MftEntries>, errors : &mut Vec<String>) ->Vec<NtfsAttribute>
   {
     let mut attributes : Vec<NtfsAttribute> = Vec::new();
-    let builder = match content.builder()
+
+    //data content is kept as is and read later from its builder, 
+    //other unhandled attributes are skipped without reading their content
+    let data = match content.mft_attribute.type_id
+    {
+      NtfsAttributeType::Data => content.builder().map(|_| Cow::Borrowed(&[][..])),
+      //index are only read on demand when looking up a path
+      NtfsAttributeType::IndexRoot | NtfsAttributeType::IndexAllocation => Ok(Cow::Borrowed(&[][..])),
+      NtfsAttributeType::StandardInformation | NtfsAttributeType::FileName | NtfsAttributeType::VolumeName |
+      NtfsAttributeType::VolumeInformation | NtfsAttributeType::AttributeList => context.content(&content),
+      _ => return Vec::new(),
+    };
+
+    let data = match data
     {
-      Ok(builder) => builder,
+      Ok(data) => data,
       //Happen if we read a non-resident on a MFT (XXX use specific error)
-      Err(_err)=> return Vec::new(),
+      Err(err)=> 
+      {
+        if !matches!(err.downcast_ref::<NtfsError>(), Some(NtfsError::NonResidentData))
+        {
+          errors.push(format!("{:?} : {}", content.mft_attribute.type_id, err));
+        }
+        return Vec::new()
+      },
     };
 
     match &content.mft_attribute.type_id
     {
-      NtfsAttributeType::StandardInformation => if let Ok(attribute) =  StandardInformation::new(builder)
+      NtfsAttributeType::StandardInformation => match StandardInformation::new(&data)
       {
-        attributes.push(NtfsAttribute::StandardInformation(attribute));
+        Ok(attribute) => attributes.push(NtfsAttribute::StandardInformation(attribute)),
+        Err(err) => errors.push(format!("StandardInformation : {}", err)),
       },
-      NtfsAttributeType::FileName => if let Ok(attribute) = FileName::new(builder)
+      NtfsAttributeType::FileName => match FileName::new(&data)
       {
-        attributes.push(NtfsAttribute::FileName(attribute));
+        Ok(attribute) => attributes.push(NtfsAttribute::FileName(FileName{ attribute_id : content.mft_attribute.id, ..attribute })),
+        Err(err) => errors.push(format!("FileName : {}", err)),
       },
       NtfsAttributeType::Data => attributes.push(NtfsAttribute::Data(content)),
-      NtfsAttributeType::VolumeName => if let Ok(attribute) = VolumeName::new(builder)
+      NtfsAttributeType::IndexRoot => attributes.push(NtfsAttribute::IndexRoot(content)),
+      NtfsAttributeType::IndexAllocation => attributes.push(NtfsAttribute::IndexAllocation(content)),
+      NtfsAttributeType::VolumeName => match VolumeName::new(&data)
       {
-        attributes.push(NtfsAttribute::VolumeName(attribute));
+        Ok(attribute) => attributes.push(NtfsAttribute::VolumeName(attribute)),
+        Err(err) => errors.push(format!("VolumeName : {}", err)),
       },
-      NtfsAttributeType::VolumeInformation => if let Ok(attribute) =  VolumeInformation::new(builder)
+      NtfsAttributeType::VolumeInformation => match VolumeInformation::new(&data)
       {
-        attributes.push(NtfsAttribute::VolumeInformation(attribute));
+        Ok(attribute) => attributes.push(NtfsAttribute::VolumeInformation(attribute)),
+        Err(err) => errors.push(format!("VolumeInformation : {}", err)),
       },
       //NtfsAttributeType::Bitmap => match Bitmap::new(&content)
       //{
         //Ok(attribute) => attributes.push(NtfsAttribute::Bitmap(attribute)),
         //Err(_) => (),
       //}
-      NtfsAttributeType::AttributeList => if let Ok(items)  = AttributeList::new(builder)
+      NtfsAttributeType::AttributeList => match AttributeList::new(&data)
       {
-        for item in items
+        Ok(items) => for item in items
         {
           if let Some(mft_entries) = mft_entries
           {
-            if let Ok(entry) = mft_entries.entry(item.mft_entry_id)
+            match mft_entries.cached_entry(item.mft_entry_id)
             {
-              for content in entry.contents()
+              Ok(entry) => 
               {
-                //if attribute id == itemid && attribute vnc start (or is non resident) 
-                if item.id == content.mft_attribute.id 
+                //extension record attributes are parsed from their own record
+                let entry_context = entry.context();
+                for content in entry.parse_contents_with(&entry_context).0
                 {
-                  let attribute = self.content_to_attribute(content, Some(mft_entries));
-                  attributes.extend(attribute);
+                  //if attribute id == itemid && attribute vnc start (or is non resident) 
+                  if item.id == content.mft_attribute.id 
+                  {
+                    let attribute = MftEntry::content_to_attribute(&entry_context, content, Some(mft_entries), errors);
+                    attributes.extend(attribute);
+                  }
                 }
-              }
+              },
+              Err(err) => errors.push(format!("AttributeList entry {} : {}", item.mft_entry_id, err)),
             }
           }
-        }
+        },
+        Err(err) => errors.push(format!("AttributeList : {}", err)),
       },
       _ => (),
     };
     attributes 
   }
 
-  //return an iterator ?
+  /// unparsed content of all the attributes, including the ones stored in extension records 
+  /// referenced by the attribute list if `mft_entries` is set
+  pub fn all_contents(&self, mft_entries : Option<&MftEntries>) -> (Vec<MftAttributeContent>, Vec<String>)
+  {
+    let context = self.context();
+    let (contents, mut errors) = self.parse_contents_with(&context);
+    let mut all_contents = Vec::with_capacity(contents.len());
+
+    for content in contents
+    {
+      if let (NtfsAttributeType::AttributeList, Some(mft_entries)) = (&content.mft_attribute.type_id, mft_entries)
+      {
+        let items = context.content(&content).and_then(|data| AttributeList::new(&data));
+        match items
+        {
+          Ok(items) => for item in items.into_iter().filter(|item| item.mft_entry_id != self.id)
+          {
+            match mft_entries.cached_entry(item.mft_entry_id)
+            {
+              Ok(entry) => all_contents.extend(entry.contents().into_iter().filter(|content| content.mft_attribute.id == item.id)),
+              Err(err) => errors.push(format!("AttributeList entry {} : {}", item.mft_entry_id, err)),
+            }
+          },
+          Err(err) => errors.push(format!("AttributeList : {}", err)),
+        }
+      }
+      all_contents.push(content);
+    }
+
+    (all_contents, errors)
+  }
+
   pub fn read_attributes(&self, mft_entries : Option<&MftEntries>) -> NtfsAttributes 
   {
-    NtfsAttributes::new(self.contents().into_iter().flat_map(|content| self.content_to_attribute(content, mft_entries)).collect())
+    self.attributes_iter(mft_entries).into_attributes()
+  }
+
+  /// iterate lazily over the attributes, each attribute is parsed only when requested
+  pub fn attributes_iter<'a>(&'a self, mft_entries : Option<&'a MftEntries>) -> AttributesIter<'a>
+  {
+    AttributesIter{
+      entry : self,
+      context : self.context(),
+      mft_entries,
+      offset : Some(self.first_attribute_offset as u32),
+      pending : Vec::new().into_iter(),
+      errors : Vec::new(),
+      types : None,
+    }
   }
 
   pub fn data_attribute(&self) -> Result<Arc<dyn VFileBuilder>>
@@ -235,7 +521,7 @@ impl MftEntry
         _ => continue,
       }
     }
-    Err(NtfsError::MftAttributeNotFound("data").into())
+    Err(NtfsError::MftAttributeNotFound{ entry : self.id, attribute : "data" }.into())
   }
 
   pub fn size(&self) -> u64
@@ -245,7 +531,14 @@ impl MftEntry
 
   pub fn is_valid(&self) -> bool
   {
-    self.signature != MFT_SIGNATURE_FILE && self.signature != MFT_SIGNATURE_BAAD
+    self.signature == MFT_SIGNATURE_FILE || self.signature == MFT_SIGNATURE_BAAD
+  }
+
+  /// check the NTFS 3.1 record number against the expected entry id, 
+  /// used to validate carved records, None if the record doesn't store it
+  pub fn is_record_number_valid(&self, entry_id : u64) -> Option<bool>
+  {
+    self.record_number.map(|record_number| record_number as u64 == entry_id)
   }
 
   pub fn is_used(&self) -> bool
@@ -258,37 +551,212 @@ impl MftEntry
     self.flags & 0x2 != 0 
   }
 
+  /// file is stored in $Extend like $UsnJrnl or $ObjId (0x4)
+  pub fn is_in_extend(&self) -> bool
+  {
+    self.flags & 0x4 != 0
+  }
+
+  /// false for the extension records holding the attributes of a base record
+  pub fn is_base_record(&self) -> bool
+  {
+    self.file_reference_id == 0
+  }
+
+  pub fn is_view_index(&self) -> bool
+  {
+    self.flags & 0x8 != 0
+  }
+
+  pub fn record_flags(&self) -> RecordFlags
+  {
+    RecordFlags{
+      in_use : self.is_used(),
+      directory : self.is_directory(),
+      in_extend : self.is_in_extend(),
+      view_index : self.is_view_index(),
+      value : self.flags,
+    }
+  }
+
+  /// size of the block protected by each fixup value, derived from the update sequence array
+  /// so 4096 bytes records or odd sizes are handled, fallback to the sector size
+  pub fn fixup_stride(&self) -> u64
+  {
+    MftEntry::stride(self.size(), self.fixup_array_entry_count as u64, self.sector_size)
+  }
+
+  pub(crate) fn stride(size : u64, count : u64, sector_size : u16) -> u64
+  {
+    if count != 0 && size % count == 0 && size / count >= 2
+    {
+      return size / count
+    }
+    std::cmp::max(sector_size as u64, 2)
+  }
+
+  /// check the update sequence array against the end of each protected block
+  /// and restore the original values in the record buffer
+  pub(crate) fn apply_fixup(data : &mut [u8], fixup_array_offset : u16, fixup_array_entry_count : u16, stride : u64) -> Option<RecordFixup>
+  {
+    let count = fixup_array_entry_count as usize;
+    let array_offset = fixup_array_offset as usize;
+    let array_size = (count + 1) * 2;
+
+    if array_offset + array_size > data.len()
+    {
+      return None
+    }
+
+    let update_sequence_number = LittleEndian::read_u16(&data[array_offset..array_offset + 2]);
+    let values : Vec<u16> = data[array_offset + 2..array_offset + array_size].chunks_exact(2).map(LittleEndian::read_u16).collect();
+
+    let mut mismatch_count = 0;
+    for (index, value) in values.iter().enumerate()
+    {
+      let end = (index as u64 + 1) * stride;
+      if end > data.len() as u64
+      {
+        break
+      }
+
+      let end = end as usize;
+      if LittleEndian::read_u16(&data[end - 2..end]) != update_sequence_number
+      {
+        mismatch_count += 1;
+      }
+      LittleEndian::write_u16(&mut data[end - 2..end], *value);
+    }
+
+    let original_values = values.iter().map(|value| format!("{:#06x}", value)).collect::<Vec<String>>().join(" ");
+
+    Some(RecordFixup{ update_sequence_number, original_values, mismatch_count })
+  }
+
+  /// update sequence array of the record and its check against the end of each protected block
+  pub fn fixup(&self) -> Result<RecordFixup>
+  {
+    self.fixup.clone().ok_or_else(|| NtfsError::MftFixupArrayTooLarge{ entry : self.id, offset : self.fixup_array_offset, count : self.fixup_array_entry_count }.into())
+  }
+
+  /// size of the unused bytes at the end of the record
+  pub fn slack_size(&self) -> u64
+  {
+    self.size().saturating_sub(self.used_size as u64)
+  }
+
+  /// istat like description of the record : header, header of each attribute with a summary of
+  /// resident contents and the runs of non resident ones, and the attributes parsed using the attribute list if `mft_entries` is set
+  pub fn describe(&self, mft_entries : Option<&MftEntries>) -> serde_json::Value
+  {
+    let context = self.context();
+    let (contents, errors) = self.parse_contents_with(&context);
+
+    let attributes : Vec<serde_json::Value> = contents.iter().map(|content|
+    {
+      let summary = match &content.mft_attribute.data
+      {
+        ResidentType::Resident(resident) => match context.content(content)
+        {
+          Ok(data) => json!({
+            "size" : resident.content_size,
+            "preview" : data.iter().take(DESCRIBE_PREVIEW_SIZE).map(|byte| format!("{:02x}", byte)).collect::<String>(),
+          }),
+          Err(err) => json!({ "error" : err.to_string() }),
+        },
+        ResidentType::NonResident(non_resident) =>
+        {
+          let mut vcn = non_resident.vnc_start;
+          let runs : Vec<serde_json::Value> = non_resident.runs.iter().map(|run|
+          {
+            //sparse runs have no lcn
+            let described = json!({ "vcn" : vcn, "lcn" : (run.offset != 0).then_some(run.offset), "length" : run.length });
+            vcn = vcn.saturating_add(run.length);
+            described
+          }).collect();
+          json!({ "size" : non_resident.content_actual_size, "runs" : runs })
+        },
+      };
+      json!({ "offset" : content.offset, "header" : &content.mft_attribute, "content" : summary })
+    }).collect();
+
+    let parsed = self.read_attributes(mft_entries);
+    json!({
+      "header" : self,
+      "slack_size" : self.slack_size(),
+      "attributes" : attributes,
+      "errors" : errors,
+      "parsed" : parsed,
+    })
+  }
+
+  /// `describe` as an indented JSON string
+  pub fn to_json(&self, mft_entries : Option<&MftEntries>) -> String
+  {
+    serde_json::to_string_pretty(&self.describe(mft_entries)).unwrap_or_default()
+  }
+
   pub fn to_builder(&self) -> Arc<dyn VFileBuilder>
   {
-    let mut file_ranges = FileRanges::new();
+    to_builder(&self.mapping())
+  }
+
+  /// ranges of the MFT builder composing the record with the original values of the fixups
+  pub fn mapping(&self) -> Vec<MappedRange>
+  {
+    let mut mapping = Vec::new();
     let mut offset : u64 = 0;
-    let sector_size = self.sector_size as u64;
+    let stride = self.fixup_stride();
+    let fixup_count = self.fixup_array_entry_count as u64;
 
     while offset < self.size()
     {
-      if self.size() - offset >= sector_size
+      let index = offset / stride;
+      if self.size() - offset >= stride && index < fixup_count
       {
-        let range = offset..offset + (sector_size - 2);
+        let range = offset..offset + (stride - 2);
         let start = self.offset + offset;
-        file_ranges.push(range, start, self.mft_builder.clone());
+        mapping.push(MappedRange::new(range, start, self.mft_builder.clone()));
         
-        offset +=  sector_size - 2;
+        offset +=  stride - 2;
 
         let range = offset..offset + 2;
-        let start =  self.offset + self.fixup_array_offset as u64 + 2 + (2 * (offset / sector_size));          
-        file_ranges.push(range, start, self.mft_builder.clone());
+        let start =  self.offset + self.fixup_array_offset as u64 + 2 + (2 * index);
+        mapping.push(MappedRange::new(range, start, self.mft_builder.clone()));
         offset += 2;
       }
       else
       {
-        //XXX check if ok 
-        let range = offset..self.size() - offset;
+        //no fixup protect the end of the record
+        let range = offset..self.size();
         let start = self.offset + offset;
-        file_ranges.push(range, start, self.mft_builder.clone());
-        offset += self.size() - offset;
+        mapping.push(MappedRange::new(range, start, self.mft_builder.clone()));
+        offset = self.size();
       }
     }
 
-    Arc::new(MappedVFileBuilder::new(file_ranges))
+    mapping
+  }
+}
+
+impl fmt::Display for MftEntry
+{
+  fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result
+  {
+    let flags = self.record_flags();
+    let state = match (flags.in_use, flags.directory)
+    {
+      (true, true) => "directory",
+      (true, false) => "file",
+      (false, true) => "deleted directory",
+      (false, false) => "deleted file",
+    };
+    write!(f, "entry {} sequence {} {} links {} lsn {} used {}/{} bytes", self.id, self.sequence, state,
+           self.link_count, self.lsn, self.used_size, self.allocated_size)?;
+    if self.file_reference_id != 0
+    {
+      write!(f, " base entry {}", self.file_reference_id)?;
+    }
+    Ok(())
   }
 }
@@ -1,45 +1,302 @@
 use std::sync::Arc;
 use std::fmt::Debug;
-use std::collections::HashMap;
+use std::collections::HashSet;
+use std::ops::Range;
+use std::sync::OnceLock;
+use std::path::Path;
 
-use tap::tree::{Tree, TreeNodeId};
-use tap::node::Node;
+use tap::tree::TreeNodeId;
 use tap::vfile::VFileBuilder;
+#[cfg(feature = "plugin")]
 use tap::reflect::ReflectStruct;
+#[cfg(feature = "plugin")]
 use tap::value::Value;
+#[cfg(feature = "plugin")]
 use tap_derive::Reflect;
 
 use log::warn;
 use anyhow::Result;
+use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
+use chrono::{DateTime, Utc};
+use smallvec::SmallVec;
 
 use crate::bootsector::BootSector;
+use crate::error::NtfsError;
+use crate::report::ParseReport;
+use crate::ntfs::summary::VolumeSummary;
+use crate::anomaly::Timestomp;
+use crate::deleted::{DeletionHints, DeletedTime};
+use crate::usnjrnl::UsnRecords;
 use crate::mft::MftEntries;
-use crate::mftentry::{MftEntry};
-use crate::unallocated::freespace_builder;
+use crate::mapping::{MappedRange, resolve};
+use crate::mftentry::{MftEntry, RecordFixup, RecordFlags};
 use crate::attributes::standard::StandardInformation;
-use crate::attributes::filename::{FileName};
+use crate::attributes::filename::{FileName, NameSpace, NameSpacePreference};
+#[cfg(feature = "plugin")]
+use crate::attributes::filename::name_space_to_value;
+use crate::attributecontent::{MftAttributeContent, ResidentType, read_builder};
+use crate::clustermap::{ClusterMap, ClusterOwner, ClusterRun, OffsetOwner};
+use crate::ntfsattributes::{NtfsAttribute, NtfsAttributeType};
+use crate::attributes::volume::VolumeInformation;
+use crate::attributes::index::{IndexEntry, IndexRoot, IndexAllocation};
+use crate::attributes::upcase::UpCase;
+use crate::options::NtfsOptions;
+use crate::progress::ProgressCallback;
+use crate::pathvfile::PathVFileBuilder;
+use crate::unallocated::FreespaceMap;
+use crate::attributes::{FileAttributes, FileReference};
+#[cfg(feature = "plugin")]
+use crate::attributes::{option_copy_to_value, option_file_attributes_to_value};
+
+#[cfg(feature = "plugin")]
+mod tree;
+pub mod walk;
+pub mod search;
+pub mod verify;
+pub mod extract;
+pub mod summary;
+pub mod allocation;
+pub mod lost;
+
+/// how nodes whose parent can't be found are linked
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
+pub enum OrphanPolicy
+{
+  /// link all orphans under the orphan node
+  #[default]
+  Flat,
+  /// recreate a placeholder node for each missing parent under the orphan node
+  Reconstruct,
+  /// don't link orphans in the tree
+  Drop,
+}
+
+/// where the $MFT record describing the MFT runs is read
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
+pub enum MftSource
+{
+  /// first record of $MFT
+  #[default]
+  Mft,
+  /// copy of the first record in $MFTMirr, when the first cluster of $MFT is unreadable
+  Mirror,
+}
+
+/// attributes parsed in metadata only mode, attribute list can reference file names in other records
+const METADATA_ATTRIBUTES : [NtfsAttributeType; 3] = [NtfsAttributeType::StandardInformation, NtfsAttributeType::FileName, NtfsAttributeType::AttributeList];
+
+/// attributes of a directory file name index
+const INDEX_ATTRIBUTES : [NtfsAttributeType; 3] = [NtfsAttributeType::IndexRoot, NtfsAttributeType::IndexAllocation, NtfsAttributeType::AttributeList];
+pub(crate) const FILE_NAME_INDEX : &str = "$I30";
+pub(crate) const ROOT_ENTRY : u64 = 5;
+const UPCASE_ENTRY : u64 = 10;
+const BITMAP_ENTRY : u64 = 6;
+pub(crate) const USNJRNL_PATH : &str = "/$Extend/$UsnJrnl";
+pub(crate) const USNJRNL_STREAM : &str = "$J";
+/// $MFT to $Extend and the reserved records, always parsed when filtering as the volume nodes need them
+#[cfg_attr(not(feature = "plugin"), allow(dead_code))]
+const SYSTEM_ENTRIES : u64 = 16;
+
+/// tree nodes created for an entry with the entry id of their parent, 
+/// most entries have a single node so it's stored inline
+type NodeIds = SmallVec<[(Option<u64>, TreeNodeId); 1]>;
 
 /**
  *   Ntfs parser
  */
+#[cfg_attr(not(feature = "plugin"), allow(dead_code))] //some options are only used to create the tree
 pub struct Ntfs
 {
   mft_entries : MftEntries,
-  nodes_ids : HashMap::<u64, Vec<(Option<u64>, TreeNodeId)>>,
+  nodes_ids : Vec<NodeIds>, //indexed by entry id
+  volume_information : Option<VolumeInformation>,
+  volume_name : Option<String>,
+  unused_entries : Vec<Range<u64>>, //never used records (used size == 0xffffffff)
+  report : ParseReport,
+  summary : VolumeSummary, //totals of the nodes created by create_nodes
+  deleted_nodes_ids : Vec<TreeNodeId>, //copy of deleted nodes if deleted_branch is set
+  deletion_hints : Option<DeletionHints>, //journal events used to estimate deletion time
+  options : NtfsOptions,
+  volume_serial : Option<u64>, //None if opened from an extracted MFT
+  upcase : OnceLock<UpCase>, //read on first path lookup
+  cluster_map : Option<ClusterMap>, //owner of each cluster, built on demand
+  progress : Option<ProgressCallback>, //called while creating and linking the nodes
 }
 
 impl Ntfs
 {
-  pub fn from_partition(partition_builder : Arc<dyn VFileBuilder>, boot_sector : &BootSector) -> Result<Ntfs>
+  pub fn from_partition(partition_builder : Arc<dyn VFileBuilder>, boot_sector : &BootSector, options : NtfsOptions) -> Result<Ntfs>
   {
     //we create a builder from the main MFT so we can read attributes
-    let mft_entries = MftEntries::from_partition(partition_builder,
-                                               boot_sector.bpb.mft_logical_cluster_number,
-                                               boot_sector.cluster_size,
-                                               boot_sector.bpb.bytes_per_sector,
-                                               boot_sector.mft_record_size)?;
+    let mft_entries = match options.mft_source
+    {
+      MftSource::Mft => MftEntries::from_partition(partition_builder,
+                                                 boot_sector.bpb.mft_logical_cluster_number,
+                                                 boot_sector.cluster_size,
+                                                 boot_sector.bpb.bytes_per_sector,
+                                                 boot_sector.mft_record_size,
+                                                 options.stream_mft)?,
+      MftSource::Mirror => MftEntries::from_mirror(partition_builder,
+                                                 boot_sector.bpb.mft_mirror_logical_cluster_number,
+                                                 boot_sector.cluster_size,
+                                                 boot_sector.bpb.bytes_per_sector,
+                                                 boot_sector.mft_record_size,
+                                                 options.stream_mft)?,
+    };
+
+    Ok(Ntfs::new(mft_entries, options, Some(boot_sector.bpb.volume_serial_number)))
+  }
+
+  /// parse the records of an extracted MFT and read the non-resident data from the partition,
+  /// when the MFT can't be read from the partition
+  pub fn from_partition_with_mft(partition_builder : Arc<dyn VFileBuilder>, master_mft_builder : Arc<dyn VFileBuilder>, boot_sector : &BootSector, options : NtfsOptions) -> Result<Ntfs>
+  {
+    let mft_entries = MftEntries::from_master_mft_with_partition(master_mft_builder,
+                                                                 partition_builder,
+                                                                 boot_sector.cluster_size,
+                                                                 boot_sector.bpb.bytes_per_sector,
+                                                                 boot_sector.mft_record_size,
+                                                                 options.stream_mft)?;
+
+    Ok(Ntfs::new(mft_entries, options, Some(boot_sector.bpb.volume_serial_number)))
+  }
+
+  /// open a volume or a partition image from the local file system
+  pub fn from_path(path : &Path, options : NtfsOptions) -> Result<Ntfs>
+  {
+    let partition_builder : Arc<dyn VFileBuilder> = Arc::new(PathVFileBuilder::new(path)?);
+    let mut file = partition_builder.open()?;
+    let boot_sector = BootSector::from_file(&mut file)?;
+    Ntfs::from_partition(partition_builder, &boot_sector, options)
+  }
+
+  fn new(mut mft_entries : MftEntries, options : NtfsOptions, volume_serial : Option<u64>) -> Ntfs
+  {
+    if let Some(entry_cache_size) = options.entry_cache_size
+    {
+      mft_entries.set_entry_cache_size(entry_cache_size);
+    }
+    let (volume_information, volume_name) = Ntfs::read_volume(&mft_entries);
+    Ntfs{
+      mft_entries,
+      nodes_ids : Vec::new(),
+      volume_information,
+      volume_name,
+      unused_entries : Vec::new(),
+      report : ParseReport::default(),
+      summary : VolumeSummary::default(),
+      deleted_nodes_ids : Vec::new(),
+      deletion_hints : None,
+      options,
+      volume_serial,
+      upcase : OnceLock::new(),
+      cluster_map : None,
+      progress : None,
+    }
+  }
+
+  /// read $Volume (entry 3) version, used to handle NTFS 1.x quirks, and label
+  fn read_volume(mft_entries : &MftEntries) -> (Option<VolumeInformation>, Option<String>)
+  {
+    match mft_entries.entry(3)
+    {
+      Ok(entry) => 
+      {
+        let mut volume_information = None;
+        let mut volume_name = None;
+        //stop as soon as both attributes are found
+        for attribute in entry.attributes_iter(None)
+        {
+          match attribute
+          {
+            NtfsAttribute::VolumeInformation(information) => volume_information = Some(information),
+            NtfsAttribute::VolumeName(name) => volume_name = Some(name.name),
+            _ => (),
+          }
+          if volume_information.is_some() && volume_name.is_some()
+          {
+            break
+          }
+        }
+        (volume_information, volume_name)
+      },
+      Err(_) => (None, None),
+    }
+  }
+
+  /// volume label from $Volume
+  pub fn volume_name(&self) -> Option<&str>
+  {
+    self.volume_name.as_deref()
+  }
+
+  pub fn volume_information(&self) -> Option<&VolumeInformation>
+  {
+    self.volume_information.as_ref()
+  }
+
+  /// serial number from the boot sector, None if opened from an extracted MFT
+  pub fn volume_serial(&self) -> Option<u64>
+  {
+    self.volume_serial
+  }
 
-    Ok(Ntfs{mft_entries, nodes_ids : HashMap::new()})
+  /// true if the volume was formatted by NT4 (NTFS 1.x)
+  pub fn is_legacy(&self) -> bool
+  {
+    self.volume_information.as_ref().map(|info| info.is_legacy()).unwrap_or(false)
+  }
+
+  /// set journal events used to estimate deletion time of deleted nodes
+  pub fn set_deletion_hints(&mut self, deletion_hints : DeletionHints)
+  {
+    self.deletion_hints = Some(deletion_hints);
+  }
+
+  /// hints of the FILE_DELETE records of $UsnJrnl:$J, empty if the volume has no journal,
+  /// must be set before `create_nodes` to add a deletion time to the deleted nodes
+  pub fn usn_deletion_hints(&self) -> Result<DeletionHints>
+  {
+    let mut hints = DeletionHints::new();
+    let (entry_id, _) = match self.open_path(USNJRNL_PATH)
+    {
+      Ok(usnjrnl) => usnjrnl,
+      Err(_) => return Ok(hints),
+    };
+    let builder = self.attribute_builder(entry_id, NtfsAttributeType::Data, Some(USNJRNL_STREAM))?;
+    //invalid records are skipped by the iterator, an error is a read error so we stop there
+    for record in UsnRecords::new(builder)?.map_while(Result::ok)
+    {
+      record.add_to_hints(&mut hints);
+    }
+    Ok(hints)
+  }
+
+  /// report the progress of `create_nodes` and `link_nodes` to `callback`
+  pub fn set_progress_callback(&mut self, callback : ProgressCallback)
+  {
+    self.progress = Some(callback);
+  }
+
+  pub fn options(&self) -> &NtfsOptions
+  {
+    &self.options
+  }
+
+  /// attributes to parse when creating the nodes, None to parse all of them
+  fn attribute_types(&self) -> Option<&[NtfsAttributeType]>
+  {
+    match self.options.metadata_only
+    {
+      true => Some(&METADATA_ATTRIBUTES),
+      false => self.options.attributes.as_deref(),
+    }
+  }
+
+  pub fn mft_entries(&self) -> &MftEntries
+  {
+    &self.mft_entries
   }
 
   pub fn mft_node(&self) -> Option<NtfsNode>
@@ -47,107 +304,240 @@ impl Ntfs
     self.mft_entries.master_mft()
   }
 
-  pub fn from_mft(master_mft_builder : Arc<dyn VFileBuilder>, sector_size : Option<u16>, mft_record_size : Option<u32>) -> Result<Ntfs>
+  pub fn from_mft(master_mft_builder : Arc<dyn VFileBuilder>, sector_size : Option<u16>, mft_record_size : Option<u32>, options : NtfsOptions) -> Result<Ntfs>
   {
-    let mft_entries = MftEntries::from_master_mft(master_mft_builder, sector_size, mft_record_size)?;
-    Ok(Ntfs{mft_entries, nodes_ids : HashMap::new()})
+    let mft_entries = MftEntries::from_master_mft(master_mft_builder, sector_size, mft_record_size, options.stream_mft)?;
+    Ok(Ntfs::new(mft_entries, options, None))
   }
 
-  pub fn create_nodes(&mut self, tree : &Tree)
+  /// find an entry from its path without using the tree, names are compared case-insensitively using $UpCase.
+  /// Return the entry id and a node for each of its data streams
+  pub fn open_path(&self, path : &str) -> Result<(u64, Vec<NtfsNode>)>
   {
-    //here we read each entry in the mft
-    //we could use par_iter to multithread that 
-    let entry_count = self.mft_entries.count();
-    //we start from 1 as 0 is the $MFT and we already parsed it, 1 is $MFTMirror
-    for i in 1..entry_count
+    let mut entry_id = ROOT_ENTRY;
+
+    for name in path.split(['/', '\\']).filter(|name| !name.is_empty())
     {
-      if i % 10000 == 0 { warn!("entry {}/{}", i, entry_count); }
+      entry_id = self.find_child(entry_id, name)?.ok_or_else(|| NtfsError::PathNotFound(path.to_string()))?;
+    }
 
-      let entry = match self.mft_entries.entry(i)
-      {
-        Ok(entry) => entry,
-        Err(err) => { warn!("Can't read mft entry {} : {}", i, err); continue }
-      };
+    let entry = self.mft_entries.entry(entry_id)?;
+    Ok((entry_id, NtfsNode::from_entry(entry_id, &entry, Some(&self.mft_entries), self.options.name_space, None)))
+  }
 
-      let ntfs_nodes = NtfsNode::from_entry(i, &entry, &self.mft_entries);
+  /// entry id of the child `name` of a directory, index entries pointing to a reused record are ignored
+  fn find_child(&self, directory_id : u64, name : &str) -> Result<Option<u64>>
+  {
+    let upcase = self.upcase();
 
-      for ntfs_node in ntfs_nodes.into_iter()  //we can return multiple nodes because of ADS 
+    for index_entry in self.directory_entries(directory_id)?
+    {
+      if !upcase.equals(&index_entry.file_name.file_name, name)
       {
-        let parent_id = ntfs_node.attributes.file_name.as_ref().map(|file_name| file_name.parent_mft_entry_id);
+        continue
+      }
+      let entry_id = index_entry.file_reference.entry;
+      match self.mft_entries.entry(entry_id)
+      {
+        Ok(entry) if entry.is_used() && entry.sequence == index_entry.file_reference.sequence => return Ok(Some(entry_id)),
+        _ => continue,
+      }
+    }
+    Ok(None)
+  }
 
-        let tree_node = ntfs_node.to_node();
-        let tree_node_id = tree.new_node(tree_node);
-        match parent_id
-        {
-          Some(parent_id) if parent_id != i => 
-          {
-            //we check for loop
-            match self.nodes_ids.get_mut(&i)
-            {
-              Some(nodes) => {nodes.push((Some(parent_id), tree_node_id)); } ,
-              None => {self.nodes_ids.insert(i, vec![(Some(parent_id), tree_node_id)]);} ,
-            }
-          },
-          _ => match self.nodes_ids.get_mut(&i)
-          {
-            Some(nodes) => { nodes.push((None, tree_node_id)); },
-            None => { self.nodes_ids.insert(i, vec![(None, tree_node_id)]); },
-          },
-        }
+  /// entries of the file name index of a directory 
+  pub fn directory_entries(&self, entry_id : u64) -> Result<Vec<IndexEntry>>
+  {
+    let entry = self.mft_entries.entry(entry_id)?;
+    let attributes = entry.attributes_iter(Some(&self.mft_entries)).only(&INDEX_ATTRIBUTES).into_attributes();
+
+    let mut entries = Vec::new();
+    if let Some(index_root) = attributes.find(NtfsAttributeType::IndexRoot, Some(FILE_NAME_INDEX)).and_then(NtfsAttribute::content)
+    {
+      let index_root = IndexRoot::new(&read_builder(index_root.builder()?)?)?;
+      if let Some(index_allocation) = attributes.find(NtfsAttributeType::IndexAllocation, Some(FILE_NAME_INDEX)).and_then(NtfsAttribute::content)
+      {
+        entries.extend(IndexAllocation::new(index_allocation.builder()?, index_root.index_record_size, self.mft_entries.sector_size())?.entries);
       }
+      entries.extend(index_root.entries);
     }
+
+    Ok(entries)
   }
 
-  pub fn link_nodes(&self, tree : &Tree, ntfs_node_id : TreeNodeId, orphan_node_id : TreeNodeId) 
+  /// builder of the content of any attribute of an entry, `name` None match the unnamed attribute
+  /// e.g. attribute_builder(2, NtfsAttributeType::Data, None) for $LogFile 
+  /// or attribute_builder(5, NtfsAttributeType::IndexAllocation, Some("$I30")) for the root directory index
+  pub fn attribute_builder(&self, entry_id : u64, type_id : NtfsAttributeType, name : Option<&str>) -> Result<Arc<dyn VFileBuilder>>
   {
-    warn!("Linking tree");
-    let mut i = 0;
-    let valid_entry_count = self.nodes_ids.len();
+    let entry = self.mft_entries.entry(entry_id)?;
+    let (contents, _) = entry.all_contents(Some(&self.mft_entries));
+    let content = contents.into_iter()
+                          .find(|content| content.mft_attribute.type_id == type_id && content.mft_attribute.name.as_deref() == name)
+                          .ok_or(NtfsError::MftAttributeNotFound{ entry : entry_id, attribute : "requested attribute" })?;
+    content.builder()
+  }
+
+  /// $UpCase table, fallback to ASCII upper case if it can't be read
+  fn upcase(&self) -> &UpCase
+  {
+    self.upcase.get_or_init(||
+    {
+      let upcase = self.mft_entries.entry(UPCASE_ENTRY)
+                       .and_then(|entry| entry.data_attribute())
+                       .and_then(read_builder)
+                       .and_then(|content| UpCase::new(&content));
+      match upcase
+      {
+        Ok(upcase) => upcase,
+        Err(err) => { warn!("Can't read $UpCase : {}", err); UpCase::default() },
+      }
+    })
+  }
+
+  /// ranges of records that were never used
+  pub fn unused_entries(&self) -> &[Range<u64>]
+  {
+    &self.unused_entries
+  }
+
+  /// number of records that were never used
+  pub fn unused_entry_count(&self) -> u64
+  {
+    self.unused_entries.iter().map(|range| range.end - range.start).sum()
+  }
+
+  pub fn report(&self) -> &ParseReport
+  {
+    &self.report
+  }
+
+  /// index the clusters used by the non-resident attributes of every entry,
+  /// this read the whole MFT so it's only done on demand
+  pub fn build_cluster_map(&mut self)
+  {
+    let (cluster_size, partition_builder) = match (self.mft_entries.cluster_size(), self.mft_entries.partition_builder())
+    {
+      (Some(cluster_size), Some(partition_builder)) if cluster_size != 0 => (cluster_size, partition_builder.clone()),
+      _ => { warn!("Can't build cluster map without the partition"); return },
+    };
 
-    for (id, nodes) in &self.nodes_ids
+    let mut runs = Vec::new();
+    for (entry_id, entry) in self.mft_entries.iter()
     {
-      if i % 10_000 == 0 { warn!("linking {}/{}", i, valid_entry_count); }
-      for (parent_id, tree_node_id) in nodes
+      let entry = match entry
+      {
+        Ok(entry) => entry,
+        Err(_) => continue,
+      };
+      let owner_id = match entry.file_reference_id
       {
-        //root node is a special case as it link to itself but we want to add it to our root
-        //we should maybe create a fake root if it doesn't exist to avoid having everything as
-        //orphan
-        if *id == 5 
+        0 => entry_id,
+        base_id => base_id,
+      };
+
+      for content in entry.contents()
+      {
+        if !matches!(content.mft_attribute.data, ResidentType::NonResident(_))
         {
-          tree.add_child_from_id(ntfs_node_id, nodes[0].1);
           continue
         }
-
-        //check if node as a parent id to link to
-        let parent_id = match parent_id
+        let mapping = match content.mapping()
         {
-          Some(parent_id) => parent_id,
-          None => { tree.add_child_from_id(orphan_node_id, *tree_node_id); continue; }
+          Ok(mapping) => mapping,
+          Err(_) => continue,
         };
+        let owner = Arc::new(ClusterOwner{ entry_id : owner_id, type_id : content.mft_attribute.type_id.clone(), name : content.mft_attribute.name.clone(), is_deleted : !entry.is_used() });
 
-        //link node to it's parent
-        match self.nodes_ids.get(parent_id)
+        //sparse runs are mapped to the zero builder and don't use clusters
+        for mapped in mapping.iter().filter(|mapped| Arc::ptr_eq(&mapped.builder, &partition_builder))
         {
-          //we check if we have a parent node and avoid loop by checking if parent_id != node_id
-          Some(parent_nodes) if !parent_nodes.is_empty() && parent_nodes[0].1 != *tree_node_id =>
-          { 
-            tree.add_child_from_id(parent_nodes[0].1, *tree_node_id);
-          },
-          //if parent didn't exist we add node as orphan
-          _ => tree.add_child_from_id(orphan_node_id, *tree_node_id),
+          let lcn = mapped.offset / cluster_size;
+          let count = (mapped.range.end - mapped.range.start).div_ceil(cluster_size);
+          runs.push(ClusterRun{ lcn : lcn..lcn + count, vcn : mapped.range.start / cluster_size, owner : owner.clone() });
         }
       }
-      i += 1;
     }
+    self.cluster_map = Some(ClusterMap::new(runs));
+  }
+
+  /// free extents of $Bitmap, to read the freespace and translate its offsets to offsets of the partition
+  pub fn freespace_map(&self) -> Result<FreespaceMap>
+  {
+    let cluster_size = self.mft_entries.cluster_size().filter(|cluster_size| *cluster_size != 0).ok_or(NtfsError::NonResidentData)?;
+    let partition_size = self.mft_entries.partition_builder().ok_or(NtfsError::NonResidentData)?.size();
+    FreespaceMap::from_bitmap(self.attribute_builder(BITMAP_ENTRY, NtfsAttributeType::Data, None)?, cluster_size, partition_size)
   }
 
-  pub fn freespace(&self, tree : &Tree, ntfs_node_id : TreeNodeId, partition_builder : Arc<dyn VFileBuilder>, cluster_size : u64) -> Option<Arc<dyn VFileBuilder>>
+  /// None if `build_cluster_map` wasn't called
+  pub fn cluster_map(&self) -> Option<&ClusterMap>
   {
-    tree.find_node_from_id(ntfs_node_id, "/root/$Bitmap")
-        .and_then(|node_id| tree.get_node_from_id(node_id))
-        .and_then(|node| node.value().get_value("data"))
-        .and_then(|value| value.try_as_vfile_builder())
-        .map(|bitmap| freespace_builder(bitmap, partition_builder, cluster_size))
+    self.cluster_map.as_ref()
+  }
+
+  /// runs of the attributes using the cluster `lcn`, empty if the cluster map wasn't built
+  pub fn cluster_owners(&self, lcn : u64) -> Vec<&ClusterRun>
+  {
+    self.cluster_map.as_ref().map(|cluster_map| cluster_map.find(lcn)).unwrap_or_default()
+  }
+
+  /// streams containing the byte at `offset` of the partition, like ifind, 
+  /// empty if the cluster map wasn't built or if the cluster is unallocated
+  pub fn owner_of_offset(&self, offset : u64) -> Vec<OffsetOwner>
+  {
+    let cluster_size = match self.mft_entries.cluster_size()
+    {
+      Some(cluster_size) if cluster_size != 0 => cluster_size,
+      _ => return Vec::new(),
+    };
+    let lcn = offset / cluster_size;
+
+    self.cluster_owners(lcn).into_iter().map(|run|
+    {
+      let vcn = run.vcn + (lcn - run.lcn.start);
+      let owner = &run.owner;
+      let path = self.entry_path(owner.entry_id).unwrap_or_else(|_| format!("Unknown_{}", owner.entry_id));
+      OffsetOwner{
+        path,
+        entry_id : owner.entry_id,
+        type_id : owner.type_id.clone(),
+        name : owner.name.clone(),
+        vcn,
+        stream_offset : vcn * cluster_size + offset % cluster_size,
+        is_deleted : owner.is_deleted,
+      }
+    }).collect()
+  }
+
+  /// path of an entry from the root of the volume built by following the parent of its $FILE_NAME, 
+  /// if a parent can't be found the path start with Unknown_{parent id}
+  pub fn entry_path(&self, entry_id : u64) -> Result<String>
+  {
+    let mut names = Vec::new();
+    let mut visited = HashSet::new();
+    let mut current = entry_id;
+
+    while current != ROOT_ENTRY && visited.insert(current)
+    {
+      let file_name = self.mft_entries.entry(current)
+                          .map(|entry| entry.attributes_iter(Some(&self.mft_entries)).only(&METADATA_ATTRIBUTES).into_attributes())
+                          .ok()
+                          .and_then(|attributes| attributes.find_filename_with(self.options.name_space));
+      match file_name
+      {
+        Some(file_name) => 
+        {
+          names.push(file_name.file_name);
+          current = file_name.parent_mft_entry_id;
+        },
+        None if current == entry_id => return Err(NtfsError::MftAttributeNotFound{ entry : entry_id, attribute : "file name" }.into()),
+        None => { names.push(format!("Unknown_{}", current)); break },
+      }
+    }
+
+    names.reverse();
+    Ok(format!("/{}", names.join("/")))
   }
 
   pub fn recovery(&self) 
@@ -156,58 +546,249 @@ impl Ntfs
   }
 }
 
+#[cfg(feature = "plugin")]
 fn option_to_value<T>(value : &Option<Arc<T>>) -> Option<Value>
  where T : ReflectStruct + Sync + Send + 'static
 {
   value.as_ref().map(|value| Value::ReflectStruct(value.clone()))
 }
 
-#[derive(Debug, Reflect, Clone)]
+#[cfg(feature = "plugin")]
+fn errors_to_value(errors : &[String]) -> Option<Value>
+{
+  match errors.is_empty()
+  {
+    true => None,
+    false => Some(Value::String(errors.join("; "))),
+  }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "plugin", derive(Reflect))]
 pub struct NtfsNodeAttribute
 {
-  #[reflect(with = "option_to_value")]
+  entry_id : u64,
+  sequence : u16,
+  /// $LogFile sequence number of the last record modification
+  lsn : u64,
+  /// decoded flags of the record header
+  #[cfg_attr(feature = "plugin", reflect(with = "option_to_value"))]
+  record_flags : Option<Arc<RecordFlags>>,
+  /// hard link count from the record header
+  link_count : u16,
+  /// link count is different from the number of FILE_NAME attributes
+  link_count_mismatch : bool,
+  #[cfg_attr(feature = "plugin", reflect(with = "option_to_value"))]
   standard_information : Option<Arc<StandardInformation>>,
-  #[reflect(with = "option_to_value")]
+  #[cfg_attr(feature = "plugin", reflect(with = "option_to_value"))]
   file_name : Option<Arc<FileName>>,
+  /// name space of the file name used as node name
+  #[cfg_attr(feature = "plugin", reflect(with = "name_space_to_value"))]
+  name_space : Option<NameSpace>,
   is_deleted : bool,
+  /// record allocation state from $MFT:$BITMAP (fallback to the record in-use flag)
+  is_allocated : bool,
+  /// record number stored in the NTFS 3.1 record header
+  #[cfg_attr(feature = "plugin", reflect(with = "option_copy_to_value"))]
+  record_number : Option<u32>,
+  /// update sequence number and original values of the fixup array
+  #[cfg_attr(feature = "plugin", reflect(with = "option_to_value"))]
+  fixup : Option<Arc<RecordFixup>>,
+  /// reference of the parent directory from the chosen $FILE_NAME
+  #[cfg_attr(feature = "plugin", reflect(with = "option_to_value"))]
+  parent : Option<Arc<FileReference>>,
+  /// reference of the base record if this record is an extension
+  #[cfg_attr(feature = "plugin", reflect(with = "option_to_value"))]
+  base_record : Option<Arc<FileReference>>,
+  /// flags of $STANDARD_INFORMATION
+  #[cfg_attr(feature = "plugin", reflect(with = "option_file_attributes_to_value"))]
+  si_flags : Option<FileAttributes>,
+  /// copy of the flags stored in the chosen $FILE_NAME, can be stale
+  #[cfg_attr(feature = "plugin", reflect(with = "option_file_attributes_to_value"))]
+  fn_flags : Option<FileAttributes>,
+  /// $STANDARD_INFORMATION and $FILE_NAME flags disagree
+  flags_mismatch : bool,
+  /// times of the chosen $FILE_NAME
+  #[cfg_attr(feature = "plugin", reflect(with = "option_copy_to_value"))]
+  fn_creation_time : Option<DateTime<Utc>>,
+  #[cfg_attr(feature = "plugin", reflect(with = "option_copy_to_value"))]
+  fn_modification_time : Option<DateTime<Utc>>,
+  #[cfg_attr(feature = "plugin", reflect(with = "option_copy_to_value"))]
+  fn_mft_modification_time : Option<DateTime<Utc>>,
+  #[cfg_attr(feature = "plugin", reflect(with = "option_copy_to_value"))]
+  fn_accessed_time : Option<DateTime<Utc>>,
+  /// timestomping indicators found by comparing $STANDARD_INFORMATION and $FILE_NAME times
+  #[cfg_attr(feature = "plugin", reflect(with = "option_to_value"))]
+  timestomp : Option<Arc<Timestomp>>,
+  /// estimated deletion time from journals events
+  #[cfg_attr(feature = "plugin", reflect(with = "option_to_value"))]
+  deleted_time : Option<Arc<DeletedTime>>,
+  /// alias node of an other FILE_NAME of the record, share the data of the primary node
+  is_hard_link : bool,
+  /// description of what failed while parsing the record
+  #[cfg_attr(feature = "plugin", reflect(with = "errors_to_value"))]
+  parse_errors : Vec<String>,
 }
 
+impl NtfsNodeAttribute
+{
+  pub fn entry_id(&self) -> u64
+  {
+    self.entry_id
+  }
+
+  pub fn parse_errors(&self) -> &[String]
+  {
+    &self.parse_errors
+  }
+}
+
+#[derive(Clone)]
 pub struct NtfsNode
 {
   pub name : String,
   pub attributes : NtfsNodeAttribute,
   pub data  : Option<Arc<dyn VFileBuilder>>,
+  /// ranges the data is read from, used to save the node in the metadata cache
+  pub data_mapping : Option<Vec<MappedRange>>,
 }
 
 impl NtfsNode
 {
-  pub fn from_entry(entry_id : u64, entry : &MftEntry, entries : &MftEntries) -> Vec<NtfsNode>
+  /// create the nodes of an entry, only attributes in `types` are parsed if set,
+  /// if $DATA is not parsed a single node is created for each name,
+  /// `entries` is None for a record parsed alone, its attribute list can't be followed
+  pub fn from_entry(entry_id : u64, entry : &MftEntry, entries : Option<&MftEntries>, preference : NameSpacePreference, types : Option<&[NtfsAttributeType]>) -> Vec<NtfsNode>
+  {
+    NtfsNode::from_entry_with(entry_id, entry, entries, preference, types, false)
+  }
+
+  /// `from_entry` also resolving the mapping of the data streams if `with_mapping` is set, 
+  /// it's only needed to save the nodes in the metadata cache
+  pub(crate) fn from_entry_with(entry_id : u64, entry : &MftEntry, entries : Option<&MftEntries>, preference : NameSpacePreference, types : Option<&[NtfsAttributeType]>, with_mapping : bool) -> Vec<NtfsNode>
   {
     let is_deleted = !entry.is_used();
-    let attributes = entry.read_attributes(Some(entries)); //attribute list need to read other entries
+    let is_allocated = entries.and_then(|entries| entries.is_allocated(entry_id)).unwrap_or(!is_deleted);
+    let attributes = match types //attribute list need to read other entries
+    {
+      Some(types) => entry.attributes_iter(entries).only(types).into_attributes(),
+      None => entry.attributes_iter(entries).into_attributes(),
+    };
 
+    let mut parse_errors = attributes.errors.clone();
+    //link count is only maintained in base records
+    let link_count_mismatch = entry.file_reference_id == 0 && attributes.find_filenames().len() != entry.link_count as usize;
+    let fixup = match entry.fixup()
+    {
+      Ok(fixup) => Some(Arc::new(fixup)),
+      Err(err) => { parse_errors.push(err.to_string()); None },
+    };
+    if entry.is_record_number_valid(entry_id) == Some(false)
+    {
+      parse_errors.push(format!("record number {} doesn't match entry {}", entry.record_number.unwrap_or(0), entry_id));
+    }
     let datas = attributes.find_datas();
     let standard_information = attributes.find_standard_info().into_iter().next().map(Arc::new);
 
     let (name, file_name) = match entry_id
     {
       5 => ("root".into(), None),
-      _ => match attributes.find_filename()
+      _ => match attributes.find_filename_with(preference)
       {
         Some(file_name) => { (file_name.file_name.clone(), Some(Arc::new(file_name))) },
         None => (format!("Unknown_{}", entry_id), None),
       },
     };
 
+    //other hard links, DOS names are only aliases of a long name in the same directory
+    let hard_links : Vec<Arc<FileName>> = match &file_name
+    {
+      Some(primary) => attributes.find_filenames().into_iter()
+                                 .filter(|link| link.name_space != NameSpace::Dos)
+                                 .filter(|link| !(link.parent_mft_entry_id == primary.parent_mft_entry_id && (link.file_name == primary.file_name || primary.name_space == NameSpace::Dos)))
+                                 .map(|link| Arc::new(link.clone()))
+                                 .collect(),
+      None => Vec::new(),
+    };
+
+    let timestomp = match (&standard_information, &file_name)
+    {
+      (Some(standard_information), Some(file_name)) => Timestomp::detect(standard_information, file_name).map(Arc::new),
+      _ => None,
+    };
+
+    let name_space = file_name.as_ref().map(|file_name| file_name.name_space);
+    let parent = file_name.as_ref().map(|file_name| Arc::new(FileReference{ entry : file_name.parent_mft_entry_id, sequence : file_name.parent_sequence }));
+    let base_record = match entry.file_reference_id
+    {
+      0 => None,
+      id => Some(Arc::new(FileReference{ entry : id, sequence : entry.file_reference_sequence })),
+    };
+    let si_flags = standard_information.as_ref().map(|standard_information| standard_information.flags);
+    let fn_flags = file_name.as_ref().map(|file_name| file_name.flags);
+    let flags_mismatch = matches!((si_flags, fn_flags), (Some(si_flags), Some(fn_flags)) if si_flags != fn_flags);
+    let fn_creation_time = file_name.as_ref().map(|file_name| file_name.creation_time);
+    let fn_modification_time = file_name.as_ref().map(|file_name| file_name.modification_time);
+    let fn_mft_modification_time = file_name.as_ref().map(|file_name| file_name.mft_modification_time);
+    let fn_accessed_time = file_name.as_ref().map(|file_name| file_name.accessed_time);
+
     let attributes = NtfsNodeAttribute{ 
+      entry_id,
+      sequence : entry.sequence,
+      lsn : entry.lsn,
+      record_flags : Some(Arc::new(entry.record_flags())),
+      link_count : entry.link_count,
+      link_count_mismatch,
       standard_information,
       file_name,
+      name_space,
       is_deleted,
+      is_allocated,
+      record_number : entry.record_number,
+      fixup,
+      parent,
+      base_record,
+      si_flags,
+      fn_flags,
+      flags_mismatch,
+      fn_creation_time,
+      fn_modification_time,
+      fn_mft_modification_time,
+      fn_accessed_time,
+      timestomp,
+      deleted_time : None,
+      is_hard_link : false,
+      parse_errors,
     };
 
+    let mut nodes = NtfsNode::from_datas(&name, &attributes, &datas, with_mapping);
+
+    //tree nodes have only one parent so we create an alias node under each other parent
+    for link in hard_links
+    {
+      let mut link_attributes = attributes.clone();
+      link_attributes.fn_creation_time = Some(link.creation_time);
+      link_attributes.fn_modification_time = Some(link.modification_time);
+      link_attributes.fn_mft_modification_time = Some(link.mft_modification_time);
+      link_attributes.fn_accessed_time = Some(link.accessed_time);
+      link_attributes.name_space = Some(link.name_space);
+      link_attributes.parent = Some(Arc::new(FileReference{ entry : link.parent_mft_entry_id, sequence : link.parent_sequence }));
+      link_attributes.fn_flags = Some(link.flags);
+      link_attributes.flags_mismatch = matches!(link_attributes.si_flags, Some(si_flags) if si_flags != link.flags);
+      link_attributes.file_name = Some(link.clone());
+      link_attributes.is_hard_link = true;
+      nodes.extend(NtfsNode::from_datas(&link.file_name, &link_attributes, &datas, with_mapping));
+    }
+
+    nodes
+  }
+
+  /// create a node for each data stream, with its resolved mapping if `with_mapping` is set
+  fn from_datas(name : &str, attributes : &NtfsNodeAttribute, datas : &[&MftAttributeContent], with_mapping : bool) -> Vec<NtfsNode>
+  {
     if datas.is_empty()
     {
-      return vec![NtfsNode{name, attributes, data : None}] 
+      return vec![NtfsNode{name : name.to_string(), attributes : attributes.clone(), data : None, data_mapping : None}] 
     }
     
     let mut nodes = Vec::new();
@@ -216,26 +797,25 @@ impl NtfsNode
     {
       //happen when we read from MFT as we don't handle non-resident attribute
       let builder = data.builder().ok();
+      //the cached mapping stops at the end of the stream like its builder
+      let data_mapping = builder.as_ref().filter(|_| with_mapping).and_then(|builder| Some(resolve(&data.mapping().ok()?, 0, 0, builder.size())));
       let stream_name = match &data.mft_attribute.name
       {
         Some(data_name) => format!("{}:{}", name, data_name),
-        None => name.clone(),
+        None => name.to_string(),
       };
 
-      nodes.push(NtfsNode{name : stream_name, attributes : attributes.clone(), data : builder }); 
+      nodes.push(NtfsNode{name : stream_name, attributes : attributes.clone(), data : builder, data_mapping }); 
     }
       
     nodes
   }
 
-  pub fn to_node(self) -> Node
+  /// node for a record that can't be read, only identifiable by its entry id
+  pub fn from_error(entry_id : u64, error : String) -> NtfsNode
   {
-    let node = Node::new(self.name);
-    node.value().add_attribute("ntfs", Arc::new(self.attributes), None);
-    if let Some(data) = self.data 
-    {
-      node.value().add_attribute("data", data, None);
-    }
-    node
+    let attributes = NtfsNodeAttribute{ entry_id, parse_errors : vec![error], ..Default::default() };
+    NtfsNode{ name : format!("Unknown_{}", entry_id), attributes, data : None, data_mapping : None }
   }
+
 }
@@ -0,0 +1,149 @@
+//! allocation and fragmentation of a volume, combining $Bitmap, the runs of the files and the MFT usage
+
+use std::collections::HashMap;
+
+#[cfg(feature = "plugin")]
+use tap::reflect::ReflectStruct;
+#[cfg(feature = "plugin")]
+use tap::value::Value;
+#[cfg(feature = "plugin")]
+use tap_derive::Reflect;
+
+use anyhow::Result;
+use serde::{Serialize, Deserialize};
+
+use crate::error::NtfsError;
+use crate::ntfs::{Ntfs, BITMAP_ENTRY};
+use crate::attributes::bitmap::Bitmap;
+use crate::ntfsattributes::NtfsAttributeType;
+
+/// number of files listed in `most_fragmented`
+const MOST_FRAGMENTED_COUNT : usize = 10;
+
+/// live data stream and its number of extents
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FragmentedFile
+{
+  pub entry_id : u64,
+  /// name of the stream, None for the unnamed stream
+  pub name : Option<String>,
+  pub extents : u64,
+}
+
+#[cfg(feature = "plugin")]
+fn fragmented_to_value(files : &[FragmentedFile]) -> Option<Value>
+{
+  match files.is_empty()
+  {
+    true => None,
+    false => Some(Value::String(files.iter().map(|file| match &file.name
+    {
+      Some(name) => format!("entry {}:{} {} extents", file.entry_id, name, file.extents),
+      None => format!("entry {} {} extents", file.entry_id, file.extents),
+    }).collect::<Vec<String>>().join("; "))),
+  }
+}
+
+/**
+ *  AllocationReport
+ *  Usage of the clusters from $Bitmap, fragmentation of the live data streams and usage of the MFT records
+ */
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "plugin", derive(Reflect))]
+pub struct AllocationReport
+{
+  pub cluster_size : u64,
+  /// clusters of the volume, the padding bits at the end of $Bitmap are not counted
+  pub total_clusters : u64,
+  pub allocated_clusters : u64,
+  pub free_clusters : u64,
+  /// allocated clusters in percent of the volume
+  pub used_percent : f64,
+  /// ranges of contiguous free clusters
+  pub free_extents : u64,
+  pub largest_free_extent : u64,
+  /// live data streams using clusters
+  pub non_resident_streams : u64,
+  /// live data streams with more than one extent
+  pub fragmented_streams : u64,
+  /// extents of the live data streams
+  pub total_extents : u64,
+  /// average extents by non resident stream
+  pub average_extents : f64,
+  #[cfg_attr(feature = "plugin", reflect(with = "fragmented_to_value"))]
+  pub most_fragmented : Vec<FragmentedFile>,
+  pub mft_records : u64,
+  /// records marked as allocated in $MFT:$BITMAP, 0 if it can't be read
+  pub allocated_records : u64,
+  /// records that were never used
+  pub unused_records : u64,
+  /// allocated records in percent of the MFT
+  pub mft_used_percent : f64,
+}
+
+fn percent(part : u64, total : u64) -> f64
+{
+  match total
+  {
+    0 => 0.0,
+    total => part as f64 * 100.0 / total as f64,
+  }
+}
+
+impl Ntfs
+{
+  /// read $Bitmap and count the extents of the live data streams, the cluster map is built if needed
+  pub fn allocation_report(&mut self) -> Result<AllocationReport>
+  {
+    let cluster_size = self.mft_entries.cluster_size().filter(|cluster_size| *cluster_size != 0).ok_or(NtfsError::NonResidentData)?;
+    let partition_builder = self.mft_entries.partition_builder().ok_or(NtfsError::NonResidentData)?;
+    let total_clusters = partition_builder.size() / cluster_size;
+
+    let mut report = AllocationReport{ cluster_size, total_clusters, ..Default::default() };
+    let free = Bitmap::ranges(self.attribute_builder(BITMAP_ENTRY, NtfsAttributeType::Data, None)?, total_clusters)?.free;
+    report.free_clusters = free.count();
+    report.free_extents = free.len() as u64;
+    report.largest_free_extent = free.largest();
+    report.allocated_clusters = total_clusters.saturating_sub(report.free_clusters);
+    report.used_percent = percent(report.allocated_clusters, total_clusters);
+
+    if self.cluster_map.is_none()
+    {
+      self.build_cluster_map();
+    }
+    let cluster_map = self.cluster_map.as_ref().ok_or(NtfsError::NonResidentData)?;
+    //runs of each live data stream sorted by vcn, a run following the previous one on the volume continues the same extent
+    let mut streams : HashMap<(u64, Option<String>), Vec<(u64, u64, u64)>> = HashMap::new();
+    for run in cluster_map.runs().iter().filter(|run| !run.owner.is_deleted && run.owner.type_id == NtfsAttributeType::Data)
+    {
+      streams.entry((run.owner.entry_id, run.owner.name.clone())).or_default().push((run.vcn, run.lcn.start, run.lcn.end));
+    }
+    let mut fragmented = Vec::new();
+    for ((entry_id, name), mut runs) in streams
+    {
+      runs.sort_unstable();
+      let extents = 1 + runs.windows(2).filter(|pair| pair[1].1 != pair[0].2).count() as u64;
+      report.non_resident_streams += 1;
+      report.total_extents += extents;
+      if extents > 1
+      {
+        fragmented.push(FragmentedFile{ entry_id, name, extents });
+      }
+    }
+    report.fragmented_streams = fragmented.len() as u64;
+    report.average_extents = match report.non_resident_streams
+    {
+      0 => 0.0,
+      streams => report.total_extents as f64 / streams as f64,
+    };
+    fragmented.sort_by(|a, b| b.extents.cmp(&a.extents).then(a.entry_id.cmp(&b.entry_id)));
+    fragmented.truncate(MOST_FRAGMENTED_COUNT);
+    report.most_fragmented = fragmented;
+
+    report.mft_records = self.mft_entries.count();
+    report.allocated_records = self.mft_entries.allocated_count().unwrap_or(0);
+    report.unused_records = self.unused_entry_count();
+    report.mft_used_percent = percent(report.allocated_records, report.mft_records);
+    Ok(report)
+  }
+}
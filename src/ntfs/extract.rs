@@ -0,0 +1,214 @@
+//! export of the data streams of selected entries to a directory, sparse runs are not written
+
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use tap::vfile::VFileBuilder;
+
+use anyhow::Result;
+use log::warn;
+use serde::{Serialize, Deserialize};
+
+use crate::ntfs::Ntfs;
+use crate::ntfs::walk::WalkFilter;
+use crate::ntfsattributes::NtfsAttributeType;
+
+/// size of the buffer used to copy the streams
+const COPY_BUFFER_SIZE : usize = 1024 * 1024;
+/// directory of the output where deleted entries are written, like the fuse mount
+const DELETED_DIRECTORY : &str = "$Deleted";
+
+/**
+ *  ExtractFilter
+ *  Entries exported by `Ntfs::extract`, an entry must match the walk filter, be under one of the paths and in the entry range
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractFilter
+{
+  pub walk : WalkFilter,
+  /// path prefixes compared case-insensitively, every entry if empty
+  pub paths : Vec<String>,
+  pub entry_range : Option<Range<u64>>,
+  /// export alternate data streams next to their file as `name:stream`
+  pub ads : bool,
+}
+
+impl Default for ExtractFilter
+{
+  fn default() -> Self
+  {
+    ExtractFilter{ walk : WalkFilter::default().directories(false), paths : Vec::new(), entry_range : None, ads : true }
+  }
+}
+
+impl ExtractFilter
+{
+  pub fn new() -> Self
+  {
+    ExtractFilter::default()
+  }
+
+  pub fn walk(mut self, walk : WalkFilter) -> Self
+  {
+    self.walk = walk.directories(false);
+    self
+  }
+
+  pub fn path(mut self, path : &str) -> Self
+  {
+    self.paths.push(path.to_string());
+    self
+  }
+
+  pub fn entry_range(mut self, entry_range : Range<u64>) -> Self
+  {
+    self.entry_range = Some(entry_range);
+    self
+  }
+
+  pub fn ads(mut self, ads : bool) -> Self
+  {
+    self.ads = ads;
+    self
+  }
+
+  fn matches_path(&self, path : &str) -> bool
+  {
+    let path = path.to_lowercase();
+    self.paths.is_empty() || self.paths.iter().any(|prefix|
+    {
+      let prefix = prefix.replace('\\', "/").to_lowercase();
+      let prefix = prefix.trim_end_matches('/');
+      path == prefix || path.starts_with(&format!("{}/", prefix)) || prefix.is_empty()
+    })
+  }
+}
+
+/// data stream selected by an extract filter
+#[derive(Clone)]
+pub struct ExtractStream
+{
+  /// path of the entry, `name:stream` for alternate data streams
+  pub path : String,
+  pub entry_id : u64,
+  pub is_deleted : bool,
+  /// None for the unnamed stream
+  pub stream : Option<String>,
+  pub builder : Arc<dyn VFileBuilder>,
+}
+
+impl ExtractStream
+{
+  /// path relative to the output directory, deleted entries are under $Deleted prefixed by their entry id
+  pub fn relative_path(&self) -> PathBuf
+  {
+    let components : Vec<&str> = self.path.split('/').filter(|name| !name.is_empty() && *name != "." && *name != "..").collect();
+    let mut relative = PathBuf::new();
+    match (self.is_deleted, components.split_last())
+    {
+      (true, Some((name, _))) => { relative.push(DELETED_DIRECTORY); relative.push(format!("{}_{}", self.entry_id, name)); },
+      (false, Some((name, parents))) => { relative.extend(parents); relative.push(name); },
+      (_, None) => relative.push(self.entry_id.to_string()),
+    }
+    relative
+  }
+}
+
+/// number of streams and bytes written by `Ntfs::extract`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExtractReport
+{
+  pub streams : u64,
+  pub bytes : u64,
+  pub errors : Vec<String>,
+}
+
+impl Ntfs
+{
+  /// data streams of the files matching `filter`
+  pub fn extract_streams(&self, filter : &ExtractFilter) -> Vec<ExtractStream>
+  {
+    let mut streams = Vec::new();
+    for walk_entry in self.walk(&filter.walk)
+    {
+      let in_range = filter.entry_range.as_ref().is_none_or(|range| range.contains(&walk_entry.entry_id));
+      if !in_range || !filter.matches_path(&walk_entry.path)
+      {
+        continue
+      }
+      let entry = match self.mft_entries.entry(walk_entry.entry_id)
+      {
+        Ok(entry) => entry,
+        Err(err) => { warn!("Can't read mft entry {} : {}", walk_entry.entry_id, err); continue },
+      };
+
+      let (contents, _) = entry.all_contents(Some(&self.mft_entries));
+      for content in contents.into_iter().filter(|content| content.mft_attribute.type_id == NtfsAttributeType::Data)
+      {
+        let stream = content.mft_attribute.name.clone();
+        if stream.is_some() && !filter.ads
+        {
+          continue
+        }
+        let builder = match content.builder()
+        {
+          Ok(builder) => builder,
+          Err(err) => { warn!("Can't read data of mft entry {} : {}", walk_entry.entry_id, err); continue },
+        };
+        let path = match &stream
+        {
+          Some(stream) => format!("{}:{}", walk_entry.path, stream),
+          None => walk_entry.path.clone(),
+        };
+        streams.push(ExtractStream{ path, entry_id : walk_entry.entry_id, is_deleted : walk_entry.is_deleted, stream, builder });
+      }
+    }
+    streams
+  }
+
+  /// write the data streams of the files matching `filter` under `output`, keeping their path
+  pub fn extract(&self, filter : &ExtractFilter, output : &Path) -> ExtractReport
+  {
+    let mut report = ExtractReport::default();
+    for stream in self.extract_streams(filter)
+    {
+      let path = output.join(stream.relative_path());
+      match write_stream(&stream.builder, &path)
+      {
+        Ok(size) => { report.streams += 1; report.bytes += size },
+        Err(err) => report.errors.push(format!("{} : {}", stream.path, err)),
+      }
+    }
+    report
+  }
+}
+
+/// copy a stream to a file, zeroed blocks are skipped so sparse files stay sparse
+fn write_stream(builder : &Arc<dyn VFileBuilder>, path : &Path) -> Result<u64>
+{
+  if let Some(parent) = path.parent()
+  {
+    fs::create_dir_all(parent)?;
+  }
+  let size = builder.size();
+  let mut input = builder.open()?;
+  let mut output = File::create(path)?;
+  let mut buffer = vec![0u8; COPY_BUFFER_SIZE];
+  let mut offset = 0;
+  while offset < size
+  {
+    let chunk = &mut buffer[..(size - offset).min(COPY_BUFFER_SIZE as u64) as usize];
+    input.read_exact(chunk)?;
+    match chunk.iter().all(|byte| *byte == 0)
+    {
+      true => { output.seek(SeekFrom::Current(chunk.len() as i64))?; },
+      false => output.write_all(chunk)?,
+    }
+    offset += chunk.len() as u64;
+  }
+  output.set_len(size)?;
+  Ok(size)
+}
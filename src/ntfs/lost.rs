@@ -0,0 +1,44 @@
+//! lost clusters, allocated in $Bitmap but used by no live attribute, they can hide data or show a corrupted volume
+
+use std::sync::Arc;
+
+use tap::vfile::VFileBuilder;
+
+use anyhow::Result;
+
+use crate::error::NtfsError;
+use crate::ntfs::{Ntfs, BITMAP_ENTRY};
+use crate::ntfsattributes::NtfsAttributeType;
+use crate::attributes::bitmap::{Bitmap, RangeSet};
+use crate::unallocated::clusters_builder;
+
+impl Ntfs
+{
+  /// ranges of clusters marked as allocated in $Bitmap that are not in the runs of an allocated entry,
+  /// the cluster map is built if needed
+  pub fn lost_clusters(&mut self) -> Result<RangeSet>
+  {
+    let cluster_size = self.mft_entries.cluster_size().filter(|cluster_size| *cluster_size != 0).ok_or(NtfsError::NonResidentData)?;
+    let partition_builder = self.mft_entries.partition_builder().ok_or(NtfsError::NonResidentData)?;
+    let total_clusters = partition_builder.size() / cluster_size;
+
+    let allocated = Bitmap::ranges(self.attribute_builder(BITMAP_ENTRY, NtfsAttributeType::Data, None)?, total_clusters)?.allocated;
+    if self.cluster_map.is_none()
+    {
+      self.build_cluster_map();
+    }
+    let cluster_map = self.cluster_map.as_ref().ok_or(NtfsError::NonResidentData)?;
+
+    //runs are sorted by first cluster so they are merged in order
+    let used : RangeSet = cluster_map.runs().iter().filter(|run| !run.owner.is_deleted).map(|run| run.lcn.clone()).collect();
+    Ok(allocated.difference(&used))
+  }
+
+  /// builder reading the lost `ranges` one after the other, None if the volume can't be read
+  pub fn lost_clusters_builder(&self, ranges : &RangeSet) -> Option<Arc<dyn VFileBuilder>>
+  {
+    let cluster_size = self.mft_entries.cluster_size().filter(|cluster_size| *cluster_size != 0)?;
+    let partition_builder = self.mft_entries.partition_builder()?;
+    Some(clusters_builder(ranges, partition_builder.clone(), cluster_size))
+  }
+}
@@ -0,0 +1,101 @@
+//! search of file names over every record of the MFT, live and deleted
+
+use anyhow::Result;
+use rayon::prelude::*;
+use regex::{Regex, RegexBuilder};
+use serde::{Serialize, Deserialize};
+
+use crate::ntfs::{Ntfs, ROOT_ENTRY};
+use crate::ntfs::walk::glob_match;
+use crate::ntfsattributes::NtfsAttributeType;
+use crate::attributes::filename::NameSpace;
+
+/// names can be stored in extension records referenced by the attribute list
+const SEARCH_ATTRIBUTES : [NtfsAttributeType; 2] = [NtfsAttributeType::FileName, NtfsAttributeType::AttributeList];
+
+/// pattern matched case-insensitively against each $FILE_NAME
+#[derive(Debug, Clone)]
+pub enum NamePattern
+{
+  /// glob using * and ?
+  Glob(String),
+  Regex(Regex),
+}
+
+impl NamePattern
+{
+  pub fn glob(glob : &str) -> Self
+  {
+    NamePattern::Glob(glob.to_lowercase())
+  }
+
+  pub fn regex(regex : &str) -> Result<Self>
+  {
+    Ok(NamePattern::Regex(RegexBuilder::new(regex).case_insensitive(true).build()?))
+  }
+
+  pub fn is_match(&self, name : &str) -> bool
+  {
+    match self
+    {
+      NamePattern::Glob(glob) => glob_match(glob, &name.to_lowercase()),
+      NamePattern::Regex(regex) => regex.is_match(name),
+    }
+  }
+}
+
+/// $FILE_NAME matching a search, an entry with multiple matching names is returned once per name
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NameMatch
+{
+  pub entry_id : u64,
+  pub name : String,
+  pub name_space : NameSpace,
+  pub parent_id : u64,
+  pub path : String,
+  pub is_deleted : bool,
+}
+
+impl Ntfs
+{
+  /// scan the $FILE_NAME of every record in parallel, paths are only resolved for the matching names
+  pub fn search_names(&self, pattern : &NamePattern) -> Vec<NameMatch>
+  {
+    let mut matches : Vec<(u64, bool, _)> = self.mft_entries.par_iter().flat_map_iter(|(entry_id, entry)|
+    {
+      let names = match entry
+      {
+        Ok(entry) if entry.is_base_record() =>
+        {
+          let is_deleted = !entry.is_used();
+          let attributes = entry.attributes_iter(Some(&self.mft_entries)).only(&SEARCH_ATTRIBUTES).into_attributes();
+          attributes.find_filenames().into_iter()
+                    .filter(|file_name| pattern.is_match(&file_name.file_name))
+                    .map(|file_name| (entry_id, is_deleted, file_name.clone()))
+                    .collect()
+        },
+        _ => Vec::new(),
+      };
+      names.into_iter()
+    }).collect();
+    matches.sort_by_key(|(entry_id, _, _)| *entry_id);
+
+    matches.into_iter().map(|(entry_id, is_deleted, file_name)|
+    {
+      let parent_id = file_name.parent_mft_entry_id;
+      let parent_path = match parent_id
+      {
+        ROOT_ENTRY => String::new(),
+        _ => self.entry_path(parent_id).unwrap_or_else(|_| format!("/Unknown_{}", parent_id)),
+      };
+      NameMatch{
+        entry_id,
+        path : format!("{}/{}", parent_path, file_name.file_name),
+        name : file_name.file_name,
+        name_space : file_name.name_space,
+        parent_id,
+        is_deleted,
+      }
+    }).collect()
+  }
+}
@@ -0,0 +1,144 @@
+//! totals of the entries of a volume, to get an overview of a case without querying the tree
+
+#[cfg(feature = "plugin")]
+use tap::reflect::ReflectStruct;
+#[cfg(feature = "plugin")]
+use tap::value::Value;
+#[cfg(feature = "plugin")]
+use tap_derive::Reflect;
+
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+
+use crate::bootsector::serial_string;
+use crate::ntfs::{Ntfs, NtfsNode, ROOT_ENTRY};
+use crate::ntfs::walk::WalkFilter;
+#[cfg(feature = "plugin")]
+use crate::attributes::option_copy_to_value;
+
+/**
+ *  VolumeSummary
+ *  Counts and sizes of the entries of a volume with the range of their $STANDARD_INFORMATION times,
+ *  deleted entries are counted apart from the live files and directories
+ */
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "plugin", derive(Reflect))]
+pub struct VolumeSummary
+{
+  pub files : u64,
+  pub directories : u64,
+  pub deleted_files : u64,
+  pub deleted_directories : u64,
+  /// named data streams of files and directories
+  pub ads_count : u64,
+  /// size of the unnamed data streams of the live files
+  pub total_size : u64,
+  /// size of the named data streams of the live entries
+  pub ads_size : u64,
+  /// size of the unnamed data streams of the deleted files
+  pub deleted_size : u64,
+  /// times before 1970 are ignored, they are unset or invalid
+  #[cfg_attr(feature = "plugin", reflect(with = "option_copy_to_value"))]
+  pub oldest_time : Option<DateTime<Utc>>,
+  #[cfg_attr(feature = "plugin", reflect(with = "option_copy_to_value"))]
+  pub newest_time : Option<DateTime<Utc>>,
+  /// version from $Volume:$VOLUME_INFORMATION
+  pub ntfs_version : Option<String>,
+  /// serial number as displayed by Windows, empty if opened from an extracted MFT
+  pub volume_serial : String,
+  pub volume_label : Option<String>,
+}
+
+impl VolumeSummary
+{
+  /// count an entry with the sizes of its streams
+  fn add(&mut self, is_directory : bool, is_deleted : bool, size : u64, ads : &[u64], times : [Option<DateTime<Utc>>; 4])
+  {
+    match (is_directory, is_deleted)
+    {
+      (true, false) => self.directories += 1,
+      (true, true) => self.deleted_directories += 1,
+      (false, false) => { self.files += 1; self.total_size += size },
+      (false, true) => { self.deleted_files += 1; self.deleted_size += size },
+    }
+    self.ads_count += ads.len() as u64;
+    if !is_deleted
+    {
+      self.ads_size += ads.iter().sum::<u64>();
+    }
+
+    for time in times.into_iter().flatten().filter(|time| time.timestamp() >= 0)
+    {
+      self.oldest_time = Some(self.oldest_time.map_or(time, |oldest| oldest.min(time)));
+      self.newest_time = Some(self.newest_time.map_or(time, |newest| newest.max(time)));
+    }
+  }
+
+  /// count the nodes created for an entry, hard links are counted once and the sizes are 0 in metadata only mode
+  #[cfg_attr(not(feature = "plugin"), allow(dead_code))]
+  pub(crate) fn add_nodes(&mut self, entry_id : u64, nodes : &[NtfsNode])
+  {
+    //like the walk the root and the records without name are not counted
+    let attributes = match nodes.first()
+    {
+      Some(node) if entry_id != ROOT_ENTRY && node.attributes.file_name.is_some() && node.attributes.base_record.is_none() => &node.attributes,
+      _ => return,
+    };
+    let is_directory = attributes.record_flags.as_ref().is_some_and(|flags| flags.directory);
+
+    //names can't contain ':' so it only appear in alternate data stream node names
+    let streams = nodes.iter().filter(|node| !node.attributes.is_hard_link);
+    let (mut size, mut ads) = (0, Vec::new());
+    for node in streams
+    {
+      let stream_size = node.data.as_ref().map(|data| data.size()).unwrap_or(0);
+      match node.name.contains(':')
+      {
+        true => ads.push(stream_size),
+        false => size += stream_size,
+      }
+    }
+
+    let times = match &attributes.standard_information
+    {
+      Some(info) => [Some(info.creation_time), Some(info.altered_time), Some(info.mft_altered_time), Some(info.accessed_time)],
+      None => [None; 4],
+    };
+    self.add(is_directory, attributes.is_deleted, size, &ads, times);
+  }
+}
+
+impl Ntfs
+{
+  /// count the entries of the MFT by walking it, deleted entries included
+  pub fn summary(&self) -> VolumeSummary
+  {
+    let mut summary = self.volume_summary(VolumeSummary::default());
+
+    let filter = WalkFilter::new();
+    for entry in self.walk(&filter)
+    {
+      let ads : Vec<u64> = entry.ads.iter().map(|(_, size)| *size).collect();
+      let times = [entry.creation_time, entry.modification_time, entry.mft_modification_time, entry.accessed_time];
+      summary.add(entry.is_directory, entry.is_deleted, entry.size, &ads, times);
+    }
+    summary
+  }
+
+  /// totals of the nodes created by `create_nodes`, without a second pass on the MFT,
+  /// only the entries selected by the options are counted
+  pub fn nodes_summary(&self) -> VolumeSummary
+  {
+    self.volume_summary(self.summary.clone())
+  }
+
+  fn volume_summary(&self, summary : VolumeSummary) -> VolumeSummary
+  {
+    VolumeSummary{
+      ntfs_version : self.volume_information().map(|information| information.version.clone()),
+      volume_serial : self.volume_serial().map(serial_string).unwrap_or_default(),
+      volume_label : self.volume_name().map(str::to_string),
+      ..summary
+    }
+  }
+}
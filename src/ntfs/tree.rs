@@ -0,0 +1,519 @@
+//! Creation of the tap tree nodes from the parsed entries, only built with the plugin feature
+
+use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use tap::tree::{Tree, TreeNodeId};
+use tap::node::Node;
+use tap::vfile::VFileBuilder;
+
+use log::{warn, debug};
+use anyhow::Result;
+
+use crate::error::NtfsError;
+use crate::deleted::DeletedTime;
+use crate::mapping::LazyVFileBuilder;
+use crate::cache::{MetadataCache, CachedEntry, CachedNode, CachedRange, fnv1a};
+use crate::unallocated::FreespaceMap;
+use crate::progress::{ProgressTracker, Phase};
+use crate::report::EntryWarnings;
+use crate::ntfs::{Ntfs, NtfsNode, NodeIds, OrphanPolicy, METADATA_ATTRIBUTES, ROOT_ENTRY, SYSTEM_ENTRIES, BITMAP_ENTRY};
+
+/// result of the parsing of a record before it's added to the tree
+enum EntryOutcome
+{
+  Nodes(Vec<NtfsNode>),
+  Reserved(String),
+  Unused,
+  Error(String),
+  /// excluded by the options
+  Skipped,
+}
+
+impl Ntfs
+{
+  pub fn create_nodes(&mut self, tree : &Tree) -> Result<()>
+  {
+    let entry_count = self.mft_entries.count();
+    self.nodes_ids.resize_with(entry_count as usize, NodeIds::new);
+    let path_entries = match &self.options.path_prefix
+    {
+      Some(path_prefix) => Some(self.path_entries(path_prefix)?),
+      None => None,
+    };
+
+    let cache_path = self.metadata_cache_path();
+    if let Some(path) = &cache_path
+    {
+      match MetadataCache::load(path)
+      {
+        Ok(cache) => 
+        {
+          warn!("loading nodes from metadata cache {}", path.display());
+          for (i, cached_entry) in cache.entries
+          {
+            let outcome = self.restore_cached(cached_entry);
+            self.add_entry(tree, i, outcome);
+          }
+          return Ok(())
+        },
+        Err(err) => debug!("Can't load metadata cache {} : {}", path.display(), err),
+      }
+    }
+
+    //here we read each entry in the mft
+    //we could use par_iter to multithread that 
+    let mut cached_entries = cache_path.as_ref().map(|_| Vec::new());
+    //extension records referenced by attribute lists are parsed once for the whole pass
+    self.mft_entries.pin_entries();
+    let progress = self.progress.clone();
+    let tracker = ProgressTracker::new(Phase::Parsing, entry_count, self.options.progress_interval, progress.as_ref());
+    let mut read_count = 0;
+    //we start from 1 as 0 is the $MFT and we already parsed it, 1 is $MFTMirror
+    for i in 1..entry_count
+    {
+      tracker.update(i);
+
+      if !self.is_selected(i, path_entries.as_ref())
+      {
+        self.report.skipped += 1;
+        continue
+      }
+
+      if self.options.max_entries.is_some_and(|max_entries| read_count >= max_entries)
+      {
+        warn!("max entries {} reached, stopping at entry {}/{}", read_count, i, entry_count);
+        self.report.truncated = true;
+        self.report.warn(i, format!("parsing stopped after {} records, max entries reached", read_count));
+        //a partial tree is not saved in the cache
+        cached_entries = None;
+        break
+      }
+      read_count += 1;
+
+      //records free in $MFT:$BITMAP still hold the deleted files, only the ones that were never used are skipped
+      let skip_never_used = self.options.skip_unallocated && !self.options.recovery && self.mft_entries.is_allocated(i) == Some(false);
+      let outcome = self.read_entry(i, skip_never_used);
+      //stop recording if a node can't be described by the cache
+      cached_entries = cached_entries.and_then(|mut entries| { entries.push((i, self.to_cached(&outcome)?)); Some(entries) });
+      self.add_entry(tree, i, outcome);
+    }
+    self.mft_entries.unpin_entries();
+    tracker.finish();
+
+    if let (Some(path), Some(entries)) = (cache_path, cached_entries)
+    {
+      if let Err(err) = MetadataCache::new(entries).save(&path)
+      {
+        warn!("Can't save metadata cache {} : {}", path.display(), err);
+      }
+    }
+    Ok(())
+  }
+
+  /// check if an entry is in the entry range and under the path prefix
+  fn is_selected(&self, entry_id : u64, path_entries : Option<&HashSet<u64>>) -> bool
+  {
+    if entry_id < SYSTEM_ENTRIES
+    {
+      return true
+    }
+    let in_range = self.options.entry_range.as_ref().is_none_or(|range| range.contains(&entry_id));
+    in_range && path_entries.is_none_or(|entries| entries.contains(&entry_id))
+  }
+
+  /// entries of the directories of a path and of everything found under it by walking the directory indexes
+  fn path_entries(&self, path_prefix : &str) -> Result<HashSet<u64>>
+  {
+    let mut entries = HashSet::from([ROOT_ENTRY]);
+    let mut entry_id = ROOT_ENTRY;
+    for name in path_prefix.split(['/', '\\']).filter(|name| !name.is_empty())
+    {
+      entry_id = self.find_child(entry_id, name)?.ok_or_else(|| NtfsError::PathNotFound(path_prefix.to_string()))?;
+      entries.insert(entry_id);
+    }
+
+    let mut pending = vec![entry_id];
+    while let Some(directory_id) = pending.pop()
+    {
+      let index_entries = match self.directory_entries(directory_id)
+      {
+        Ok(index_entries) => index_entries,
+        Err(err) => { debug!("Can't read index of mft entry {} : {}", directory_id, err); continue },
+      };
+      //files have no index so they are only read once
+      pending.extend(index_entries.iter().map(|index_entry| index_entry.file_reference.entry).filter(|child| entries.insert(*child)));
+    }
+    Ok(entries)
+  }
+
+  /// entries are read in increasing order by `create_nodes` so they go through the prefetch window,
+  /// records without signature are skipped if `skip_never_used` is set
+  fn read_entry(&self, entry_id : u64, skip_never_used : bool) -> EntryOutcome
+  {
+    match self.mft_entries.sequential_entry(entry_id)
+    {
+      Ok(entry) if skip_never_used && entry.signature == 0 => EntryOutcome::Skipped,
+      Ok(entry) if !self.options.include_deleted && !entry.is_used() => EntryOutcome::Skipped,
+      Ok(entry) =>
+      {
+        //runs are only resolved to be saved in the metadata cache
+        let with_mapping = self.options.metadata_cache.is_some();
+        let nodes = NtfsNode::from_entry_with(entry_id, &entry, Some(&self.mft_entries), self.options.name_space, self.attribute_types(), with_mapping);
+        match nodes.first()
+        {
+          Some(node) if !self.options.lenient && !node.attributes.parse_errors.is_empty() => EntryOutcome::Error(node.attributes.parse_errors.join(", ")),
+          _ => EntryOutcome::Nodes(nodes),
+        }
+      },
+      //entries 11 to 15 are reserved and never initialized on NTFS 1.x volumes
+      Err(err) if self.is_legacy() && (11..16).contains(&entry_id) => EntryOutcome::Reserved(err.to_string()),
+      Err(err) if matches!(err.downcast_ref::<NtfsError>(), Some(NtfsError::MftUnusedEntry{ .. })) => EntryOutcome::Unused,
+      Err(err) => EntryOutcome::Error(err.to_string()),
+    }
+  }
+
+  /// create the tree nodes of an entry and update the report
+  fn add_entry(&mut self, tree : &Tree, i : u64, outcome : EntryOutcome)
+  {
+    let mut ntfs_nodes = match outcome
+    {
+      EntryOutcome::Nodes(ntfs_nodes) => ntfs_nodes,
+      EntryOutcome::Reserved(err) => { debug!("Can't read reserved mft entry {} : {}", i, err); self.report.skipped += 1; return }
+      EntryOutcome::Unused => { self.add_unused_entry(i); return }
+      EntryOutcome::Skipped => { self.report.skipped += 1; return }
+      EntryOutcome::Error(err) => 
+      { 
+        if self.options.entry_warnings == EntryWarnings::Log
+        {
+          warn!("Can't read mft entry {} : {}", i, err);
+        }
+        self.report.corrupt += 1;
+        if self.options.entry_warnings != EntryWarnings::Off
+        {
+          self.report.warn(i, err.clone());
+        }
+        let tree_node_id = tree.new_node(NtfsNode::from_error(i, err).to_node());
+        self.nodes_ids[i as usize].push((None, tree_node_id));
+        return 
+      }
+    };
+
+    let hint = ntfs_nodes.first()
+                         .filter(|ntfs_node| ntfs_node.attributes.is_deleted)
+                         .and_then(|ntfs_node| self.deletion_hints.as_ref()?.find(i, ntfs_node.attributes.sequence));
+    if let Some(hint) = hint
+    {
+      let deleted_time = Arc::new(DeletedTime::from_hint(hint));
+      for ntfs_node in ntfs_nodes.iter_mut()
+      {
+        ntfs_node.attributes.deleted_time = Some(deleted_time.clone());
+      }
+    }
+    self.report.parsed += 1;
+    self.summary.add_nodes(i, &ntfs_nodes);
+    //names can't contain ':' so it only appear in alternate data stream node names
+    self.report.ads += ntfs_nodes.iter().filter(|ntfs_node| !ntfs_node.attributes.is_hard_link && ntfs_node.name.contains(':')).count() as u64;
+    if let Some(ntfs_node) = ntfs_nodes.first()
+    {
+      match ntfs_node.attributes.record_flags.as_ref().is_some_and(|flags| flags.directory)
+      {
+        true => self.report.directories += 1,
+        false => self.report.files += 1,
+      }
+      if ntfs_node.attributes.is_deleted
+      {
+        self.report.deleted += 1;
+      }
+      if !ntfs_node.attributes.parse_errors.is_empty()
+      {
+        self.report.corrupt += 1;
+        let errors = match self.options.entry_warnings
+        {
+          EntryWarnings::Off => &[][..],
+          _ => &ntfs_node.attributes.parse_errors[..],
+        };
+        for error in errors
+        {
+          self.report.warn(i, error.clone());
+        }
+      }
+    }
+
+    for ntfs_node in ntfs_nodes.into_iter()  //we can return multiple nodes because of ADS 
+    {
+      let parent_id = ntfs_node.attributes.file_name.as_ref().map(|file_name| file_name.parent_mft_entry_id);
+
+      //a node can only have one parent so we link a copy under the deleted node
+      if self.options.deleted_branch && ntfs_node.attributes.is_deleted
+      {
+        let deleted_node_id = tree.new_node(ntfs_node.clone().to_node());
+        self.deleted_nodes_ids.push(deleted_node_id);
+      }
+
+      let tree_node = ntfs_node.to_node();
+      let tree_node_id = tree.new_node(tree_node);
+      //we check for loop
+      let parent_id = parent_id.filter(|parent_id| *parent_id != i);
+      self.nodes_ids[i as usize].push((parent_id, tree_node_id));
+    }
+  }
+
+  /// path of the cache file of this volume, the key change if the MFT or the options changing the nodes change
+  fn metadata_cache_path(&self) -> Option<PathBuf>
+  {
+    let directory = self.options.metadata_cache.as_ref()?;
+    let volume_serial = self.volume_serial.unwrap_or(0);
+    let hash = match self.mft_entries.content_hash()
+    {
+      Ok(hash) => hash,
+      Err(err) => { warn!("Can't hash MFT for metadata cache : {}", err); return None },
+    };
+    let hash = fnv1a(hash, format!("{:?}{:?}{:?}{}{}{:?}{:?}", self.options.name_space, self.attribute_types(), self.options.lenient,
+                                           self.options.skip_unallocated && !self.options.recovery, self.options.include_deleted,
+                                           self.options.entry_range, self.options.path_prefix).as_bytes());
+    Some(directory.join(format!("{:016x}-{:016x}.ntfscache", volume_serial, hash)))
+  }
+
+  fn to_cached(&self, outcome : &EntryOutcome) -> Option<CachedEntry>
+  {
+    match outcome
+    {
+      EntryOutcome::Nodes(ntfs_nodes) => 
+      {
+        let nodes = ntfs_nodes.iter().map(|ntfs_node|
+        {
+          let data = match (&ntfs_node.data, &ntfs_node.data_mapping)
+          {
+            (None, _) => None,
+            (Some(_), Some(mapping)) => Some(CachedRange::from_mapping(mapping, &self.mft_entries)?),
+            (Some(_), None) => return None,
+          };
+          Some(CachedNode{ name : ntfs_node.name.clone(), attributes : ntfs_node.attributes.clone(), data })
+        }).collect::<Option<Vec<CachedNode>>>()?;
+        Some(CachedEntry::Nodes(nodes))
+      },
+      EntryOutcome::Reserved(err) => Some(CachedEntry::Reserved(err.clone())),
+      EntryOutcome::Unused => Some(CachedEntry::Unused),
+      EntryOutcome::Error(err) => Some(CachedEntry::Error(err.clone())),
+      EntryOutcome::Skipped => Some(CachedEntry::Skipped),
+    }
+  }
+
+  fn restore_cached(&self, cached_entry : CachedEntry) -> EntryOutcome
+  {
+    match cached_entry
+    {
+      CachedEntry::Nodes(nodes) => EntryOutcome::Nodes(nodes.into_iter().map(|node|
+      {
+        let data_mapping = node.data.and_then(|ranges| CachedRange::to_mapping(&ranges, &self.mft_entries));
+        let data = data_mapping.clone().map(|mapping| Arc::new(LazyVFileBuilder::new(mapping)) as Arc<dyn VFileBuilder>);
+        NtfsNode{ name : node.name, attributes : node.attributes, data, data_mapping }
+      }).collect()),
+      CachedEntry::Reserved(err) => EntryOutcome::Reserved(err),
+      CachedEntry::Unused => EntryOutcome::Unused,
+      CachedEntry::Error(err) => EntryOutcome::Error(err),
+      CachedEntry::Skipped => EntryOutcome::Skipped,
+    }
+  }
+
+  fn add_unused_entry(&mut self, entry_id : u64)
+  {
+    self.report.skipped += 1;
+    match self.unused_entries.last_mut()
+    {
+      Some(range) if range.end == entry_id => range.end += 1,
+      _ => self.unused_entries.push(entry_id..entry_id + 1),
+    }
+  }
+
+  /// link the nodes to their parent, metadata files of the root are linked under `system_node_id` if set
+  pub fn link_nodes(&mut self, tree : &Tree, ntfs_node_id : TreeNodeId, orphan_node_id : TreeNodeId, system_node_id : Option<TreeNodeId>) 
+  {
+    let mut i = 0;
+    let mut orphaned = 0;
+    let mut orphan_parents : HashMap<u64, TreeNodeId> = HashMap::new();
+    let valid_entry_count = self.nodes_ids.iter().filter(|nodes| !nodes.is_empty()).count();
+    let progress = self.progress.clone();
+    let tracker = ProgressTracker::new(Phase::Linking, valid_entry_count as u64, self.options.progress_interval, progress.as_ref());
+
+    for (id, nodes) in self.nodes_ids.iter().enumerate().filter(|(_, nodes)| !nodes.is_empty())
+    {
+      tracker.update(i);
+      for (parent_id, tree_node_id) in nodes
+      {
+        //root node is a special case as it link to itself but we want to add it to our root
+        //we should maybe create a fake root if it doesn't exist to avoid having everything as
+        //orphan
+        if id == 5 
+        {
+          tree.add_child_from_id(ntfs_node_id, nodes[0].1);
+          continue
+        }
+
+        //check if node as a parent id to link to
+        let parent_id = match parent_id
+        {
+          Some(parent_id) => parent_id,
+          None => { self.link_orphan(tree, orphan_node_id, *tree_node_id, None, &mut orphan_parents); orphaned += 1; continue; }
+        };
+
+        //metadata files are moved out of the root to keep it for user files
+        if let Some(system_node_id) = system_node_id.filter(|_| *parent_id == ROOT_ENTRY && (id as u64) < SYSTEM_ENTRIES)
+        {
+          tree.add_child_from_id(system_node_id, *tree_node_id);
+          continue
+        }
+
+        //link node to it's parent
+        match self.node_ids(*parent_id).first()
+        {
+          //we check if we have a parent node and avoid loop by checking if parent_id != node_id
+          Some((_, parent_node_id)) if parent_node_id != tree_node_id =>
+          { 
+            tree.add_child_from_id(*parent_node_id, *tree_node_id);
+          },
+          //if parent didn't exist we add node as orphan
+          _ => { self.link_orphan(tree, orphan_node_id, *tree_node_id, Some(*parent_id), &mut orphan_parents); orphaned += 1; },
+        }
+      }
+      i += 1;
+    }
+    self.report.orphaned += orphaned;
+  }
+
+  /// add data streams to the nodes of `entry_id` and of its descendants created in metadata only mode,
+  /// should be called once for a subtree as alternate data streams nodes are created on each call
+  pub fn upgrade_subtree(&self, tree : &Tree, entry_id : u64)
+  {
+    let mut children : HashMap<u64, Vec<u64>> = HashMap::new();
+    for (id, nodes) in self.nodes_ids.iter().enumerate()
+    {
+      for parent_id in nodes.iter().filter_map(|(parent_id, _)| *parent_id)
+      {
+        children.entry(parent_id).or_default().push(id as u64);
+      }
+    }
+
+    let mut visited = HashSet::new();
+    let mut pending = vec![entry_id];
+    while let Some(id) = pending.pop()
+    {
+      if !visited.insert(id)
+      {
+        continue
+      }
+      self.upgrade_entry(tree, id);
+      if let Some(ids) = children.get(&id)
+      {
+        pending.extend(ids);
+      }
+    }
+  }
+
+  /// add data to the nodes of an entry, alternate data streams are linked under the parent of the file
+  fn upgrade_entry(&self, tree : &Tree, entry_id : u64)
+  {
+    let node_ids = self.node_ids(entry_id);
+    if node_ids.is_empty()
+    {
+      return
+    }
+
+    let entry = match self.mft_entries.entry(entry_id)
+    {
+      Ok(entry) => entry,
+      Err(err) => { warn!("Can't upgrade mft entry {} : {}", entry_id, err); return },
+    };
+    let attributes = entry.read_attributes(Some(&self.mft_entries));
+    let datas = attributes.find_datas();
+
+    //nodes are recreated in the same order as when they were first added
+    let metadata_nodes = NtfsNode::from_entry(entry_id, &entry, Some(&self.mft_entries), self.options.name_space, Some(&METADATA_ATTRIBUTES));
+    for (metadata_node, (parent_id, tree_node_id)) in metadata_nodes.iter().zip(node_ids.iter())
+    {
+      for data_node in NtfsNode::from_datas(&metadata_node.name, &metadata_node.attributes, &datas, false)
+      {
+        if data_node.name == metadata_node.name
+        {
+          if let (Some(data), Some(node)) = (data_node.data, tree.get_node_from_id(*tree_node_id))
+          {
+            node.value().add_attribute("data", data, None);
+          }
+        }
+        else if let Some((_, parent_node_id)) = parent_id.and_then(|parent_id| self.node_ids(parent_id).first())
+        {
+          let data_node_id = tree.new_node(data_node.to_node());
+          tree.add_child_from_id(*parent_node_id, data_node_id);
+        }
+      }
+    }
+  }
+
+  /// tree nodes created for an entry, empty if the entry wasn't parsed
+  fn node_ids(&self, entry_id : u64) -> &[(Option<u64>, TreeNodeId)]
+  {
+    usize::try_from(entry_id).ok().and_then(|index| self.nodes_ids.get(index)).map(|nodes| nodes.as_slice()).unwrap_or(&[])
+  }
+
+  /// link a node without parent according to the orphan policy
+  fn link_orphan(&self, tree : &Tree, orphan_node_id : TreeNodeId, tree_node_id : TreeNodeId, parent_id : Option<u64>, orphan_parents : &mut HashMap<u64, TreeNodeId>)
+  {
+    match (self.options.orphan_policy, parent_id)
+    {
+      (OrphanPolicy::Drop, _) => (),
+      //create a placeholder for the missing parent so siblings stay grouped
+      (OrphanPolicy::Reconstruct, Some(parent_id)) => 
+      {
+        let parent_node_id = match orphan_parents.get(&parent_id)
+        {
+          Some(parent_node_id) => *parent_node_id,
+          None => 
+          {
+            let parent_node_id = tree.new_node(Node::new(format!("Unknown_{}", parent_id)));
+            tree.add_child_from_id(orphan_node_id, parent_node_id);
+            orphan_parents.insert(parent_id, parent_node_id);
+            parent_node_id
+          },
+        };
+        tree.add_child_from_id(parent_node_id, tree_node_id);
+      },
+      _ => { tree.add_child_from_id(orphan_node_id, tree_node_id); },
+    }
+  }
+
+  /// link the copy of deleted nodes created if deleted branch is set
+  pub fn link_deleted(&self, tree : &Tree, deleted_node_id : TreeNodeId)
+  {
+    for node_id in self.deleted_nodes_ids.iter()
+    {
+      tree.add_child_from_id(deleted_node_id, *node_id);
+    }
+  }
+
+  /// free extents of the $Bitmap node, its builder reads the freespace of the partition
+  pub fn freespace(&self, tree : &Tree, cluster_size : u64, partition_size : u64) -> Option<FreespaceMap>
+  {
+    //$Bitmap can be under the root or the system node
+    self.node_ids(BITMAP_ENTRY).first()
+        .and_then(|(_, node_id)| tree.get_node_from_id(*node_id))
+        .and_then(|node| node.value().get_value("data"))
+        .and_then(|value| value.try_as_vfile_builder())
+        .and_then(|bitmap| FreespaceMap::from_bitmap(bitmap, cluster_size, partition_size).map_err(|err| warn!("Can't read $Bitmap : {}", err)).ok())
+  }
+}
+
+impl NtfsNode
+{
+  pub fn to_node(self) -> Node
+  {
+    let node = Node::new(self.name);
+    node.value().add_attribute("ntfs", Arc::new(self.attributes), None);
+    if let Some(data) = self.data 
+    {
+      node.value().add_attribute("data", data, None);
+    }
+    node
+  }
+}
@@ -0,0 +1,284 @@
+//! read-only integrity checks of a volume, like chkdsk without repairing anything
+
+use std::collections::HashSet;
+use std::io::SeekFrom;
+
+#[cfg(feature = "plugin")]
+use tap::reflect::ReflectStruct;
+#[cfg(feature = "plugin")]
+use tap::value::Value;
+#[cfg(feature = "plugin")]
+use tap_derive::Reflect;
+
+use anyhow::Result;
+use log::debug;
+use serde::{Serialize, Deserialize};
+
+use crate::bootsector::BootSector;
+use crate::error::NtfsError;
+use crate::ntfs::{Ntfs, ROOT_ENTRY, BITMAP_ENTRY};
+use crate::attributes::bitmap::Bitmap;
+use crate::ntfsattributes::NtfsAttributeType;
+
+const MFT_MIRROR_ENTRY : u64 = 1;
+/// only the first issues are described, the counters are always complete
+const MAX_ISSUES : usize = 1000;
+/// names are needed to check that each entry is in the index of its parent
+const VERIFY_ATTRIBUTES : [NtfsAttributeType; 2] = [NtfsAttributeType::FileName, NtfsAttributeType::AttributeList];
+
+/// inconsistency found by a check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifyIssue
+{
+  pub check : String,
+  pub entry : Option<u64>,
+  pub message : String,
+}
+
+#[cfg(feature = "plugin")]
+fn issues_to_value(issues : &[VerifyIssue]) -> Option<Value>
+{
+  match issues.is_empty()
+  {
+    true => None,
+    false => Some(Value::String(issues.iter().map(|issue| match issue.entry
+    {
+      Some(entry) => format!("{} entry {} : {}", issue.check, entry, issue.message),
+      None => format!("{} : {}", issue.check, issue.message),
+    }).collect::<Vec<String>>().join("; "))),
+  }
+}
+
+/**
+ *  VerifyReport
+ *  Result of the integrity checks, a check that can't be done leaves its counters to 0 and adds an issue
+ */
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "plugin", derive(Reflect))]
+pub struct VerifyReport
+{
+  /// records of $MFT compared with their copy in $MFTMirr
+  pub mirror_records : u64,
+  /// records different from their copy in $MFTMirr
+  pub mirror_mismatches : u64,
+  /// the backup boot sector at the end of the volume is identical to the boot sector, false if it can't be read
+  pub boot_backup_match : bool,
+  /// records whose fixups don't match their update sequence number
+  pub fixup_errors : u64,
+  /// records that can't be parsed for another reason than being unused
+  pub unreadable_records : u64,
+  /// clusters used by allocated entries but marked free in $Bitmap
+  pub free_used_clusters : u64,
+  /// index entries referencing an unused or reused record, or a record with no name in this directory
+  pub stale_index_entries : u64,
+  /// names of live entries missing from the index of their parent directory
+  pub unindexed_names : u64,
+  #[cfg_attr(feature = "plugin", reflect(with = "issues_to_value"))]
+  pub issues : Vec<VerifyIssue>,
+}
+
+impl VerifyReport
+{
+  /// true if no check found an inconsistency
+  pub fn is_clean(&self) -> bool
+  {
+    self.mirror_mismatches == 0 && self.boot_backup_match && self.fixup_errors == 0 && self.unreadable_records == 0 &&
+    self.free_used_clusters == 0 && self.stale_index_entries == 0 && self.unindexed_names == 0 && self.issues.is_empty()
+  }
+
+  fn issue(&mut self, check : &str, entry : Option<u64>, message : String)
+  {
+    if self.issues.len() < MAX_ISSUES
+    {
+      self.issues.push(VerifyIssue{ check : check.to_string(), entry, message });
+    }
+  }
+}
+
+impl Ntfs
+{
+  /// run every check, the cluster map is built if needed to compare the runs with $Bitmap
+  pub fn verify(&mut self, boot_sector : &BootSector) -> VerifyReport
+  {
+    let mut report = VerifyReport::default();
+
+    if let Err(err) = self.verify_mirror(&mut report)
+    {
+      report.issue("mft mirror", None, err.to_string());
+    }
+    match self.verify_boot_backup(boot_sector)
+    {
+      Ok(true) => report.boot_backup_match = true,
+      Ok(false) => report.issue("boot sector", None, "backup boot sector is different".to_string()),
+      Err(err) => report.issue("boot sector", None, format!("can't read backup boot sector : {}", err)),
+    }
+    self.verify_records(&mut report);
+    if let Err(err) = self.verify_bitmap(&mut report)
+    {
+      report.issue("bitmap", None, err.to_string());
+    }
+    self.verify_indexes(&mut report);
+
+    report
+  }
+
+  /// compare the first records of $MFT with $MFTMirr
+  fn verify_mirror(&self, report : &mut VerifyReport) -> Result<()>
+  {
+    let mirror = self.attribute_builder(MFT_MIRROR_ENTRY, NtfsAttributeType::Data, None)?;
+    let record_size = self.mft_entries.mft_record_size() as usize;
+    let count = mirror.size() / record_size as u64;
+
+    let mut mirror_file = mirror.open()?;
+    let mut mft_file = self.mft_entries.master_mft_builder().open()?;
+    let mut mirror_record = vec![0u8; record_size];
+    let mut mft_record = vec![0u8; record_size];
+    for entry_id in 0..count.min(self.mft_entries.count())
+    {
+      mirror_file.read_exact(&mut mirror_record)?;
+      mft_file.read_exact(&mut mft_record)?;
+      report.mirror_records += 1;
+      if mirror_record != mft_record
+      {
+        report.mirror_mismatches += 1;
+        report.issue("mft mirror", Some(entry_id), "record is different from its copy in $MFTMirr".to_string());
+      }
+    }
+    Ok(())
+  }
+
+  /// the backup boot sector is in the sector following the last sector of the volume
+  fn verify_boot_backup(&self, boot_sector : &BootSector) -> Result<bool>
+  {
+    let partition_builder = self.mft_entries.partition_builder().ok_or(NtfsError::NonResidentData)?;
+    let sector_size = boot_sector.bpb.bytes_per_sector as usize;
+    let backup_offset = boot_sector.bpb.total_sectors.checked_mul(sector_size as u64).ok_or(NtfsError::Overflow("backup boot sector offset"))?;
+
+    let mut file = partition_builder.open()?;
+    let mut boot = vec![0u8; sector_size];
+    let mut backup = vec![0u8; sector_size];
+    file.read_exact(&mut boot)?;
+    file.seek(SeekFrom::Start(backup_offset))?;
+    file.read_exact(&mut backup)?;
+    Ok(boot == backup)
+  }
+
+  /// check that every record can be read and that its fixups are valid
+  fn verify_records(&self, report : &mut VerifyReport)
+  {
+    for (entry_id, entry) in self.mft_entries.iter()
+    {
+      match entry
+      {
+        Ok(entry) => match entry.fixup()
+        {
+          Ok(fixup) if fixup.mismatch_count == 0 => (),
+          Ok(fixup) =>
+          {
+            report.fixup_errors += 1;
+            report.issue("fixup", Some(entry_id), format!("{} sectors don't match the update sequence number", fixup.mismatch_count));
+          },
+          Err(err) =>
+          {
+            report.fixup_errors += 1;
+            report.issue("fixup", Some(entry_id), err.to_string());
+          },
+        },
+        Err(err) if matches!(err.downcast_ref::<NtfsError>(), Some(NtfsError::MftUnusedEntry{ .. })) => (),
+        Err(err) =>
+        {
+          report.unreadable_records += 1;
+          report.issue("record", Some(entry_id), err.to_string());
+        },
+      }
+    }
+  }
+
+  /// check that the clusters of the runs of allocated entries are marked as used in $Bitmap
+  fn verify_bitmap(&mut self, report : &mut VerifyReport) -> Result<()>
+  {
+    let bitmap = self.attribute_builder(BITMAP_ENTRY, NtfsAttributeType::Data, None)?;
+    if self.cluster_map.is_none()
+    {
+      self.build_cluster_map();
+    }
+    let cluster_map = self.cluster_map.as_ref().ok_or(NtfsError::NonResidentData)?;
+
+    let mut issues = Vec::new();
+    for run in cluster_map.runs().iter().filter(|run| !run.owner.is_deleted)
+    {
+      //the clusters after the end of the bitmap are not counted as free
+      let covered = run.lcn.start..std::cmp::min(run.lcn.end, bitmap.size().saturating_mul(8));
+      let free = covered.end.saturating_sub(covered.start).saturating_sub(Bitmap::count_allocated(bitmap.clone(), covered)?);
+      if free != 0
+      {
+        report.free_used_clusters += free;
+        issues.push((run.owner.entry_id, format!("{} clusters of run {}..{} are marked free", free, run.lcn.start, run.lcn.end)));
+      }
+    }
+    for (entry_id, message) in issues
+    {
+      report.issue("bitmap", Some(entry_id), message);
+    }
+    Ok(())
+  }
+
+  /// compare the $I30 index of each directory with the names of the records
+  fn verify_indexes(&self, report : &mut VerifyReport)
+  {
+    //(directory, entry) of every valid index entry
+    let mut indexed : HashSet<(u64, u64)> = HashSet::new();
+    let mut names : Vec<(u64, u64)> = Vec::new();
+
+    for (entry_id, entry) in self.mft_entries.iter()
+    {
+      let entry = match entry
+      {
+        Ok(entry) if entry.is_used() && entry.is_base_record() => entry,
+        _ => continue,
+      };
+      let attributes = entry.attributes_iter(Some(&self.mft_entries)).only(&VERIFY_ATTRIBUTES).into_attributes();
+      //the root directory is its own parent
+      names.extend(attributes.find_filenames().iter().filter(|file_name| entry_id != ROOT_ENTRY).map(|file_name| (file_name.parent_mft_entry_id, entry_id)));
+      if !entry.is_directory()
+      {
+        continue
+      }
+
+      let index_entries = match self.directory_entries(entry_id)
+      {
+        Ok(index_entries) => index_entries,
+        Err(err) => { report.issue("index", Some(entry_id), format!("can't read index : {}", err)); continue },
+      };
+      for index_entry in index_entries
+      {
+        let reference = index_entry.file_reference;
+        let valid = match self.mft_entries.entry(reference.entry)
+        {
+          Ok(child) => child.is_used() && child.sequence == reference.sequence && index_entry.file_name.parent_mft_entry_id == entry_id,
+          Err(_) => false,
+        };
+        match valid
+        {
+          true => { indexed.insert((entry_id, reference.entry)); },
+          false =>
+          {
+            report.stale_index_entries += 1;
+            report.issue("index", Some(entry_id), format!("index entry {} references record {}-{} which is not in use or was reused",
+                                                          index_entry.file_name.file_name, reference.entry, reference.sequence));
+          },
+        }
+      }
+    }
+
+    for (parent_id, entry_id) in names
+    {
+      if !indexed.contains(&(parent_id, entry_id))
+      {
+        report.unindexed_names += 1;
+        report.issue("index", Some(entry_id), format!("name is missing from the index of directory {}", parent_id));
+      }
+    }
+    debug!("verify : {} index entries checked", indexed.len());
+  }
+}
@@ -0,0 +1,267 @@
+//! fls like listing of the entries of the MFT without creating a tree
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+
+use crate::ntfs::{Ntfs, ROOT_ENTRY, FILE_NAME_INDEX};
+use crate::ntfsattributes::{NtfsAttribute, NtfsAttributeType};
+use crate::attributes::FileAttributes;
+
+/// attributes needed to describe an entry, attribute list can reference names or data in other records,
+/// the index root is only used for its attribute id
+const WALK_ATTRIBUTES : [NtfsAttributeType; 5] = [NtfsAttributeType::StandardInformation, NtfsAttributeType::FileName,
+                                                  NtfsAttributeType::AttributeList, NtfsAttributeType::Data, NtfsAttributeType::IndexRoot];
+
+/**
+ *  WalkFilter
+ *  Entries returned by `Ntfs::walk`, names and extensions are compared case-insensitively
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalkFilter
+{
+  pub deleted : bool,
+  pub directories : bool,
+  pub files : bool,
+  /// globs using * and ?, an entry must match one of them if not empty
+  pub names : Vec<String>,
+  /// globs using * and ?, entries matching one of them are excluded
+  pub exclude_names : Vec<String>,
+  /// extensions without the dot, an entry must have one of them if not empty
+  pub extensions : Vec<String>,
+  pub exclude_extensions : Vec<String>,
+}
+
+impl Default for WalkFilter
+{
+  fn default() -> Self
+  {
+    WalkFilter{
+      deleted : true,
+      directories : true,
+      files : true,
+      names : Vec::new(),
+      exclude_names : Vec::new(),
+      extensions : Vec::new(),
+      exclude_extensions : Vec::new(),
+    }
+  }
+}
+
+impl WalkFilter
+{
+  pub fn new() -> Self
+  {
+    WalkFilter::default()
+  }
+
+  pub fn deleted(mut self, deleted : bool) -> Self
+  {
+    self.deleted = deleted;
+    self
+  }
+
+  pub fn directories(mut self, directories : bool) -> Self
+  {
+    self.directories = directories;
+    self
+  }
+
+  pub fn files(mut self, files : bool) -> Self
+  {
+    self.files = files;
+    self
+  }
+
+  pub fn name(mut self, glob : &str) -> Self
+  {
+    self.names.push(glob.to_string());
+    self
+  }
+
+  pub fn exclude_name(mut self, glob : &str) -> Self
+  {
+    self.exclude_names.push(glob.to_string());
+    self
+  }
+
+  pub fn extension(mut self, extension : &str) -> Self
+  {
+    self.extensions.push(extension.trim_start_matches('.').to_string());
+    self
+  }
+
+  pub fn exclude_extension(mut self, extension : &str) -> Self
+  {
+    self.exclude_extensions.push(extension.trim_start_matches('.').to_string());
+    self
+  }
+
+  fn matches_type(&self, is_deleted : bool, is_directory : bool) -> bool
+  {
+    (self.deleted || !is_deleted) && (if is_directory { self.directories } else { self.files })
+  }
+
+  fn matches_name(&self, name : &str) -> bool
+  {
+    let name = name.to_lowercase();
+    let extension = name.rsplit_once('.').map(|(_, extension)| extension).unwrap_or("");
+    let has_extension = |extensions : &[String]| extensions.iter().any(|wanted| wanted.to_lowercase() == extension);
+    let has_glob = |globs : &[String]| globs.iter().any(|glob| glob_match(&glob.to_lowercase(), &name));
+
+    (self.names.is_empty() || has_glob(&self.names)) && !has_glob(&self.exclude_names) &&
+    (self.extensions.is_empty() || has_extension(&self.extensions)) && !has_extension(&self.exclude_extensions)
+  }
+}
+
+/// match `name` against a glob where * match any sequence and ? any character
+pub(crate) fn glob_match(glob : &str, name : &str) -> bool
+{
+  let glob : Vec<char> = glob.chars().collect();
+  let name : Vec<char> = name.chars().collect();
+  let (mut g, mut n) = (0, 0);
+  let mut star : Option<(usize, usize)> = None; //position of the last * and of the name when it was met
+
+  while n < name.len()
+  {
+    match glob.get(g)
+    {
+      Some('*') => { star = Some((g, n)); g += 1 },
+      Some(c) if *c == '?' || *c == name[n] => { g += 1; n += 1 },
+      _ => match star
+      {
+        Some((star_g, star_n)) => { g = star_g + 1; n = star_n + 1; star = Some((star_g, star_n + 1)) },
+        None => return false,
+      },
+    }
+  }
+  glob[g..].iter().all(|c| *c == '*')
+}
+
+/**
+ *  WalkEntry
+ *  Description of an entry returned by `Ntfs::walk`, timestamps are read from $STANDARD_INFORMATION,
+ *  the timestamps of the chosen $FILE_NAME are prefixed by file_name
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalkEntry
+{
+  pub path : String,
+  pub entry_id : u64,
+  pub sequence : u16,
+  pub is_deleted : bool,
+  pub is_directory : bool,
+  pub flags : Option<FileAttributes>,
+  /// size of the unnamed data stream
+  pub size : u64,
+  /// size of the clusters of the unnamed data stream, the size if it's resident
+  pub allocated_size : u64,
+  /// size of the named data streams
+  pub ads_size : u64,
+  /// name and size of each named data stream
+  pub ads : Vec<(String, u64)>,
+  /// attribute id of the unnamed $DATA of a file or of the $I30 $INDEX_ROOT of a directory
+  pub attribute_id : Option<u16>,
+  /// attribute id of the chosen $FILE_NAME
+  pub file_name_attribute_id : u16,
+  pub creation_time : Option<DateTime<Utc>>,
+  pub modification_time : Option<DateTime<Utc>>,
+  pub mft_modification_time : Option<DateTime<Utc>>,
+  pub accessed_time : Option<DateTime<Utc>>,
+  pub file_name_creation_time : DateTime<Utc>,
+  pub file_name_modification_time : DateTime<Utc>,
+  pub file_name_mft_modification_time : DateTime<Utc>,
+  pub file_name_accessed_time : DateTime<Utc>,
+}
+
+impl Ntfs
+{
+  /// list the entries of the MFT matching `filter` in record order,
+  /// each entry is returned once with the name chosen by the name space preference
+  pub fn walk<'a>(&'a self, filter : &'a WalkFilter) -> impl Iterator<Item = WalkEntry> + 'a
+  {
+    let mut parent_paths : HashMap<(u64, u16), String> = HashMap::new();
+
+    self.mft_entries.iter().filter_map(move |(entry_id, entry)|
+    {
+      let entry = entry.ok()?;
+      let is_deleted = !entry.is_used();
+      let is_directory = entry.is_directory();
+      if entry_id == ROOT_ENTRY || !entry.is_base_record() || !filter.matches_type(is_deleted, is_directory)
+      {
+        return None
+      }
+
+      let attributes = entry.attributes_iter(Some(&self.mft_entries)).only(&WALK_ATTRIBUTES).into_attributes();
+      let file_name = attributes.find_filename_with(self.options.name_space)?;
+      if !filter.matches_name(&file_name.file_name)
+      {
+        return None
+      }
+
+      let parent = (file_name.parent_mft_entry_id, file_name.parent_sequence);
+      let parent_path = parent_paths.entry(parent).or_insert_with(|| self.parent_path(parent.0, parent.1));
+      let path = format!("{}/{}", parent_path, file_name.file_name);
+
+      let (mut size, mut allocated_size, mut ads_size, mut ads) = (0, 0, 0, Vec::new());
+      for data in attributes.find_datas()
+      {
+        let data_size = data.size();
+        match &data.mft_attribute.name
+        {
+          Some(name) => { ads_size += data_size; ads.push((name.clone(), data_size)) },
+          None => { size += data_size; allocated_size += data.allocated_size() },
+        }
+      }
+
+      let attribute_id = match is_directory
+      {
+        true => attributes.find(NtfsAttributeType::IndexRoot, Some(FILE_NAME_INDEX)).and_then(NtfsAttribute::content).map(|content| content.mft_attribute.id),
+        false => attributes.find_datas().into_iter().find(|data| data.mft_attribute.name.is_none()).map(|data| data.mft_attribute.id),
+      };
+
+      let info = attributes.find_standard_info().into_iter().next();
+      Some(WalkEntry{
+        path,
+        entry_id,
+        sequence : entry.sequence,
+        is_deleted,
+        is_directory,
+        flags : info.as_ref().map(|info| info.flags),
+        size,
+        allocated_size,
+        ads_size,
+        ads,
+        attribute_id,
+        file_name_attribute_id : file_name.attribute_id,
+        creation_time : info.as_ref().map(|info| info.creation_time),
+        modification_time : info.as_ref().map(|info| info.altered_time),
+        mft_modification_time : info.as_ref().map(|info| info.mft_altered_time),
+        accessed_time : info.as_ref().map(|info| info.accessed_time),
+        file_name_creation_time : file_name.creation_time,
+        file_name_modification_time : file_name.modification_time,
+        file_name_mft_modification_time : file_name.mft_modification_time,
+        file_name_accessed_time : file_name.accessed_time,
+      })
+    })
+  }
+
+  /// path of the directory referenced by a $FILE_NAME, a record reused since by another entry
+  /// doesn't give the path of the directory the entry was in
+  fn parent_path(&self, parent_id : u64, parent_sequence : u16) -> String
+  {
+    if parent_id == ROOT_ENTRY
+    {
+      return String::new()
+    }
+    //the sequence is incremented when a record is freed so a deleted directory is one ahead of its children
+    let is_same_parent = self.mft_entries.entry(parent_id).is_ok_and(|parent| parent.sequence == parent_sequence ||
+                                                            (!parent.is_used() && parent.sequence == parent_sequence.wrapping_add(1)));
+    match is_same_parent
+    {
+      true => self.entry_path(parent_id).unwrap_or_else(|_| format!("/Unknown_{}", parent_id)),
+      false => format!("/Unknown_{}", parent_id),
+    }
+  }
+}
@@ -1,11 +1,14 @@
+use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
+
 use crate::attributecontent::MftAttributeContent;
 use crate::attributes::bitmap::Bitmap;
 use crate::attributes::list::AttributeListItem;
 use crate::attributes::standard::StandardInformation;
-use crate::attributes::filename::{FileName, NameSpace};
+use crate::attributes::filename::{FileName, NameSpacePreference};
 use crate::attributes::volume::{VolumeName, VolumeInformation};
 
-#[derive(Debug, Clone, FromPrimitive, ToPrimitive, PartialOrd, PartialEq)]
+#[derive(Debug, Clone, FromPrimitive, ToPrimitive, PartialOrd, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[repr(u32)]
 pub enum NtfsAttributeType {
     StandardInformation = 16_u32,
@@ -16,8 +19,8 @@ pub enum NtfsAttributeType {
     VolumeName = 96_u32,
     VolumeInformation = 112_u32,
     Data = 128_u32,
-    IndexRoot = 144_u32, //not implemented
-    IndexAllocation = 160_u32, //not implemented
+    IndexRoot = 144_u32, //only $I30 is parsed
+    IndexAllocation = 160_u32, //only $I30 is parsed
     Bitmap = 176_u32,
     ReparsePoint = 192_u32, //$SYMBOLIC_LINK to implem
     EaInformation = 208_u32, //not implemented
@@ -26,7 +29,7 @@ pub enum NtfsAttributeType {
     LoggedUtilityStream = 246_u32, //not implemented
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub enum NtfsAttribute
 {
   StandardInformation(StandardInformation),
@@ -36,82 +39,128 @@ pub enum NtfsAttribute
   VolumeName(VolumeName),
   VolumeInformation(VolumeInformation),
   Bitmap(Bitmap),
+  IndexRoot(MftAttributeContent),
+  IndexAllocation(MftAttributeContent),
   Unknown(MftAttributeContent),
 }
 
+#[derive(Debug, Serialize)]
 pub struct NtfsAttributes
 {
-  pub attributes : Vec<NtfsAttribute>
+  pub attributes : Vec<NtfsAttribute>,
+  pub errors : Vec<String>, //description of attributes that failed to parse
+}
+
+impl NtfsAttribute
+{
+  pub fn type_id(&self) -> NtfsAttributeType
+  {
+    match self
+    {
+      NtfsAttribute::StandardInformation(_) => NtfsAttributeType::StandardInformation,
+      NtfsAttribute::FileName(_) => NtfsAttributeType::FileName,
+      NtfsAttribute::AttributeList(_) => NtfsAttributeType::AttributeList,
+      NtfsAttribute::VolumeName(_) => NtfsAttributeType::VolumeName,
+      NtfsAttribute::VolumeInformation(_) => NtfsAttributeType::VolumeInformation,
+      NtfsAttribute::Bitmap(_) => NtfsAttributeType::Bitmap,
+      NtfsAttribute::Data(content) | NtfsAttribute::IndexRoot(content) | 
+      NtfsAttribute::IndexAllocation(content) | NtfsAttribute::Unknown(content) => content.mft_attribute.type_id.clone(),
+    }
+  }
+
+  /// raw content of the attribute, None for attributes that are parsed 
+  pub fn content(&self) -> Option<&MftAttributeContent>
+  {
+    match self
+    {
+      NtfsAttribute::Data(content) | NtfsAttribute::IndexRoot(content) | 
+      NtfsAttribute::IndexAllocation(content) | NtfsAttribute::Unknown(content) => Some(content),
+      _ => None,
+    }
+  }
+
+  /// name of the attribute, parsed attributes are always unnamed
+  pub fn name(&self) -> Option<&str>
+  {
+    self.content().and_then(|content| content.mft_attribute.name.as_deref())
+  }
 }
 
 impl NtfsAttributes
 {
   pub fn new(attributes : Vec<NtfsAttribute>) -> Self
   {
-    NtfsAttributes{ attributes }
+    NtfsAttributes{ attributes, errors : Vec::new() }
   }
 
-  pub fn find_standard_info(&self) -> Vec<StandardInformation>
+  pub fn with_errors(attributes : Vec<NtfsAttribute>, errors : Vec<String>) -> Self
   {
-    let mut attributes = Vec::new();
+    NtfsAttributes{ attributes, errors }
+  }
 
-    for attribute in self.attributes.iter()
-    {
-      match &attribute
-      {
-        NtfsAttribute::StandardInformation(info) => attributes.push(info.clone()),
-        _ => continue,
-      }
-    }
+  /// attributes of type `type_id`
+  pub fn find_all(&self, type_id : NtfsAttributeType) -> impl Iterator<Item = &NtfsAttribute>
+  {
+    self.attributes.iter().filter(move |attribute| attribute.type_id() == type_id)
+  }
 
-    attributes
+  /// first attribute of type `type_id` named `name`, None match the unnamed attribute
+  /// e.g. find(NtfsAttributeType::Data, Some("$J")) or find(NtfsAttributeType::IndexRoot, Some("$I30"))
+  pub fn find(&self, type_id : NtfsAttributeType, name : Option<&str>) -> Option<&NtfsAttribute>
+  {
+    self.find_all(type_id).find(|attribute| attribute.name() == name)
+  }
+
+  pub fn find_standard_info(&self) -> Vec<StandardInformation>
+  {
+    self.find_all(NtfsAttributeType::StandardInformation).filter_map(|attribute| match attribute
+    {
+      NtfsAttribute::StandardInformation(info) => Some(info.clone()),
+      _ => None,
+    }).collect()
   }
 
   pub fn find_datas(&self) -> Vec<&MftAttributeContent>
   {
-    let mut attributes = Vec::new();
+    self.find_all(NtfsAttributeType::Data).filter_map(NtfsAttribute::content).collect()
+  }
 
-    for attribute in self.attributes.iter()
+  pub fn find_volume_information(&self) -> Option<VolumeInformation>
+  {
+    match self.find(NtfsAttributeType::VolumeInformation, None)
     {
-      match &attribute
-      {
-        NtfsAttribute::Data(data) => attributes.push(data),
-        _ => continue,
-      }
+      Some(NtfsAttribute::VolumeInformation(info)) => Some(info.clone()),
+      _ => None,
+    }
+  }
 
+  pub fn find_volume_name(&self) -> Option<VolumeName>
+  {
+    match self.find(NtfsAttributeType::VolumeName, None)
+    {
+      Some(NtfsAttribute::VolumeName(name)) => Some(name.clone()),
+      _ => None,
     }
+  }
 
-    attributes
+  pub fn find_filenames(&self) -> Vec<&FileName>
+  {
+    self.find_all(NtfsAttributeType::FileName).filter_map(|attribute| match attribute
+    {
+      NtfsAttribute::FileName(file_name) => Some(file_name),
+      _ => None,
+    }).collect()
   }
 
-  pub fn find_filename(&self) -> Option<FileName>
+  /// return the file name whose name space rank best with the preference
+  pub fn find_filename_with(&self, preference : NameSpacePreference) -> Option<FileName>
   {
-    let mut file_name = None;
-    let mut name_space : Option<NameSpace> = None;
+    self.find_filenames().into_iter().min_by_key(|file_name| preference.rank(file_name.name_space)).cloned()
+  }
 
-    for attribute in self.attributes.iter()
-    {
-      match attribute
-      {
-        NtfsAttribute::FileName(filename) => 
-        {
-          if let Some(_current_name_space) = name_space //XXX current_name_space is unused ? 
-          {
-            if filename.name_space == NameSpace::Win32 || filename.name_space == NameSpace::DosWin32
-            {
-              name_space = Some(filename.name_space);
-              file_name = Some(filename.clone());
-            }
-          }
-          else
-          {
-            name_space = Some(filename.name_space);
-            file_name =  Some(filename.clone());
-          }
-        }
-        _ => continue,
-      }
-    }
-    file_name
+  /// return the Win32 file name if there is one, as Windows display it
+  pub fn find_filename(&self) -> Option<FileName>
+  {
+    self.find_filename_with(NameSpacePreference::Win32)
   }
 }
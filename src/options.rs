@@ -0,0 +1,200 @@
+use std::ops::Range;
+use std::path::PathBuf;
+
+use serde::{Serialize, Deserialize};
+
+use crate::attributes::filename::NameSpacePreference;
+use crate::ntfs::{OrphanPolicy, MftSource};
+use crate::ntfsattributes::NtfsAttributeType;
+use crate::progress::DEFAULT_PROGRESS_INTERVAL;
+use crate::report::EntryWarnings;
+
+/**
+ *  NtfsOptions
+ *  Configuration of the parser used by `Ntfs::from_partition` and `Ntfs::from_mft`,
+ *  created with `NtfsOptions::new()` then modified with the builder methods
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NtfsOptions
+{
+  /// name space preferred when choosing the name of a node
+  pub name_space : NameSpacePreference,
+  /// how entries without a valid parent are linked
+  pub orphan_policy : OrphanPolicy,
+  /// create nodes for records that are not in use, false to only create a tree of the live files
+  pub include_deleted : bool,
+  /// link a copy of deleted nodes under a dedicated node
+  pub deleted_branch : bool,
+  /// records with a fixup mismatch or attributes that failed to parse are still turned into nodes
+  pub lenient : bool,
+  /// only parse $STANDARD_INFORMATION and $FILE_NAME, data can be added later with upgrade_subtree
+  pub metadata_only : bool,
+  /// only parse these attributes, attribute list must be kept to parse attributes stored in extension records
+  pub attributes : Option<Vec<NtfsAttributeType>>,
+  /// directory where parsed records are saved and reused when the same volume is parsed again
+  pub metadata_cache : Option<PathBuf>,
+  /// memory in bytes used to cache records referenced by attribute lists, 0 to disable
+  pub entry_cache_size : Option<u64>,
+  /// record used to find the MFT runs, $MFTMirr can be used if the first cluster of $MFT is unreadable
+  pub mft_source : MftSource,
+  /// read the MFT by window instead of loading it in memory, automatic if None
+  pub stream_mft : Option<bool>,
+  /// don't parse the records free in $MFT:$BITMAP that were never used, records of deleted files are still parsed,
+  /// ignored in recovery mode
+  pub skip_unallocated : bool,
+  /// recover files and folders by carving MFT records in unallocated clusters
+  pub recovery : bool,
+  /// only parse the records in this range, system records and the root directory are always parsed
+  pub entry_range : Option<Range<u64>>,
+  /// only parse the entries found under this path in the directory indexes, deleted entries are not indexed
+  pub path_prefix : Option<String>,
+  /// stop parsing after this number of records, to avoid an unexpectedly long parse of a huge MFT
+  pub max_entries : Option<u64>,
+  /// records between two progress reports, 0 to only report the start and the end of each phase
+  pub progress_interval : u64,
+  /// log the warnings about single records, or only add them to the report, or drop them
+  pub entry_warnings : EntryWarnings,
+}
+
+impl Default for NtfsOptions
+{
+  fn default() -> Self
+  {
+    NtfsOptions{
+      name_space : NameSpacePreference::default(),
+      orphan_policy : OrphanPolicy::default(),
+      include_deleted : true,
+      deleted_branch : false,
+      lenient : true,
+      metadata_only : false,
+      attributes : None,
+      metadata_cache : None,
+      entry_cache_size : None,
+      mft_source : MftSource::default(),
+      stream_mft : None,
+      skip_unallocated : false,
+      recovery : false,
+      entry_range : None,
+      path_prefix : None,
+      max_entries : None,
+      progress_interval : DEFAULT_PROGRESS_INTERVAL,
+      entry_warnings : EntryWarnings::default(),
+    }
+  }
+}
+
+impl NtfsOptions
+{
+  pub fn new() -> Self
+  {
+    NtfsOptions::default()
+  }
+
+  pub fn name_space(mut self, name_space : NameSpacePreference) -> Self
+  {
+    self.name_space = name_space;
+    self
+  }
+
+  pub fn orphan_policy(mut self, orphan_policy : OrphanPolicy) -> Self
+  {
+    self.orphan_policy = orphan_policy;
+    self
+  }
+
+  pub fn include_deleted(mut self, include_deleted : bool) -> Self
+  {
+    self.include_deleted = include_deleted;
+    self
+  }
+
+  pub fn deleted_branch(mut self, deleted_branch : bool) -> Self
+  {
+    self.deleted_branch = deleted_branch;
+    self
+  }
+
+  pub fn lenient(mut self, lenient : bool) -> Self
+  {
+    self.lenient = lenient;
+    self
+  }
+
+  pub fn metadata_only(mut self, metadata_only : bool) -> Self
+  {
+    self.metadata_only = metadata_only;
+    self
+  }
+
+  pub fn attributes(mut self, attributes : Vec<NtfsAttributeType>) -> Self
+  {
+    self.attributes = Some(attributes);
+    self
+  }
+
+  pub fn metadata_cache(mut self, directory : PathBuf) -> Self
+  {
+    self.metadata_cache = Some(directory);
+    self
+  }
+
+  pub fn entry_cache_size(mut self, entry_cache_size : u64) -> Self
+  {
+    self.entry_cache_size = Some(entry_cache_size);
+    self
+  }
+
+  pub fn mft_source(mut self, mft_source : MftSource) -> Self
+  {
+    self.mft_source = mft_source;
+    self
+  }
+
+  pub fn stream_mft(mut self, stream_mft : bool) -> Self
+  {
+    self.stream_mft = Some(stream_mft);
+    self
+  }
+
+  pub fn skip_unallocated(mut self, skip_unallocated : bool) -> Self
+  {
+    self.skip_unallocated = skip_unallocated;
+    self
+  }
+
+  pub fn recovery(mut self, recovery : bool) -> Self
+  {
+    self.recovery = recovery;
+    self
+  }
+
+  pub fn entry_range(mut self, entry_range : Range<u64>) -> Self
+  {
+    self.entry_range = Some(entry_range);
+    self
+  }
+
+  pub fn path_prefix(mut self, path_prefix : &str) -> Self
+  {
+    self.path_prefix = Some(path_prefix.to_string());
+    self
+  }
+
+  pub fn max_entries(mut self, max_entries : u64) -> Self
+  {
+    self.max_entries = Some(max_entries);
+    self
+  }
+
+  pub fn progress_interval(mut self, progress_interval : u64) -> Self
+  {
+    self.progress_interval = progress_interval;
+    self
+  }
+
+  pub fn entry_warnings(mut self, entry_warnings : EntryWarnings) -> Self
+  {
+    self.entry_warnings = entry_warnings;
+    self
+  }
+}
@@ -0,0 +1,43 @@
+use std::fs::File;
+use std::io::{Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use tap::vfile::{VFile, VFileBuilder};
+
+use anyhow::Result;
+use serde::{Serialize, Deserialize};
+
+/**
+ *  Builder reading a volume or an image from the local file system,
+ *  used when the parser is run outside of tap
+ */
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PathVFileBuilder
+{
+  path : PathBuf,
+  size : u64,
+}
+
+impl PathVFileBuilder
+{
+  pub fn new(path : &Path) -> Result<Self>
+  {
+    let mut file = File::open(path)?;
+    let size = file.seek(SeekFrom::End(0))?;
+    Ok(PathVFileBuilder{ path : path.to_path_buf(), size })
+  }
+}
+
+#[typetag::serde]
+impl VFileBuilder for PathVFileBuilder
+{
+  fn open(&self) -> Result<Box<dyn VFile>>
+  {
+    Ok(Box::new(File::open(&self.path)?))
+  }
+
+  fn size(&self) -> u64
+  {
+    self.size
+  }
+}
@@ -0,0 +1,506 @@
+//! tap plugin adapter, parse the partition found in the data of a node and create the tree of its files
+
+use std::fmt::Debug;
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::Instant;
+
+use tap::plugin;
+use tap::config_schema;
+use tap::node::Node;
+use tap::error::RustructError;
+use tap::tree::{TreeNodeId, TreeNodeIdSchema};
+use tap::plugin::{PluginInfo, PluginInstance, PluginConfig, PluginArgument, PluginResult, PluginEnvironment};
+
+use serde::{Serialize, Deserialize};
+use anyhow::{anyhow, Result};
+use schemars::JsonSchema;
+use log::{info, warn};
+
+use crate::bootsector::{BootSector, Geometry};
+use crate::mapping::offset_builder;
+use crate::ntfs::{Ntfs, OrphanPolicy, MftSource};
+use crate::options::NtfsOptions;
+use crate::ntfsattributes::NtfsAttributeType;
+use crate::report::{ParseReport, PerfCounters, EntryWarnings};
+use crate::progress::{Progress, DEFAULT_PROGRESS_INTERVAL};
+use crate::unallocated::FreespaceMap;
+use crate::attributes::filename::NameSpacePreference;
+
+plugin!("ntfs", "File system", "Read and parse NTFS filesystem", NtfsPlugin, Arguments);
+
+/// well-known files tagged in aggressive mode, so the plugin parsing them can be chained
+const TAGGED_FILES : [(&str, &str); 3] = [("$LogFile", "ntfs/logfile"), ("$Extend/$UsnJrnl:$J", "ntfs/usnjrnl"), ("$Secure:$SDS", "ntfs/secure")];
+
+/// datatype attributes added to the nodes of the volume, other plugins can be run automatically on tagged nodes
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
+pub enum DatatypeTagging
+{
+  /// no datatype except on $Boot whose data is replaced by the boot sector
+  Off,
+  /// $MFT and $MFTMirr are tagged as ntfs/mft
+  #[default]
+  Default,
+  /// also tag $LogFile, $UsnJrnl:$J and $Secure:$SDS
+  Aggressive,
+}
+
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Arguments
+{
+  ///node containing the volume
+  #[schemars(with = "Option<TreeNodeIdSchema>")]
+  file : Option<TreeNodeId>,
+  ///other nodes parsed in the same run each in their own ntfs node, e.g. every partition of a disk
+  #[schemars(with = "Option<Vec<TreeNodeIdSchema>>")]
+  files : Option<Vec<TreeNodeId>>,
+  ///if set the module will try to recover files and folders by carving MFT in unallocated clusters
+  recovery : Option<bool>,
+  ///if set the freespace node is not created and $Bitmap is not read, recovery needs the freespace so it's disabled
+  skip_freespace : Option<bool>,
+  ///if set each free extent is also added as a child of the freespace node, to carve or search the extents separately
+  freespace_extents : Option<bool>,
+  ///if set the ranges of never used MFT records are returned in the results
+  unused_ranges : Option<bool>,
+  ///name space preferred when choosing the name of a node (Win32 by default)
+  name_space : Option<NameSpacePreference>,
+  ///if set to false records that are not in use are not parsed, to create a tree of the live files only (true by default)
+  include_deleted : Option<bool>,
+  ///if set the metadata files of the root ($MFT, $Bitmap, $LogFile, $Extend, ...) are linked under a "system" node
+  system_branch : Option<bool>,
+  ///if set to false the "summary" node with the counts, sizes and time range of the entries is not created (true by default)
+  summary : Option<bool>,
+  ///if set an "allocation" node is created with the usage of the clusters, the fragmentation of the files and the usage of the MFT
+  allocation_report : Option<bool>,
+  ///if set the clusters allocated in $Bitmap but used by no live file are counted and added as the data of a "lost_clusters" node
+  lost_clusters : Option<bool>,
+  ///if set every deleted node is also linked under a "deleted" node
+  deleted_branch : Option<bool>,
+  ///if set deleted nodes get an estimated deletion time from the FILE_DELETE records of $UsnJrnl
+  deletion_time : Option<bool>,
+  ///how entries without a valid parent are linked (Flat by default)
+  orphan_policy : Option<OrphanPolicy>,
+  ///datatype added to metadata files to chain other plugins (Default tags $MFT and $MFTMirr)
+  tag_datatypes : Option<DatatypeTagging>,
+  ///if set the ntfs node is named with the volume label and serial number
+  label_node_name : Option<bool>,
+  ///if set only $STANDARD_INFORMATION and $FILE_NAME are parsed to quickly create a tree without data
+  metadata_only : Option<bool>,
+  ///directory where parsed records are saved and reused when the same volume is parsed again
+  metadata_cache : Option<String>,
+  ///memory in bytes used to cache records referenced by attribute lists (16MB by default, 0 to disable)
+  entry_cache_size : Option<u64>,
+  ///record used to find the MFT runs, Mirror reads it from $MFTMirr when the first cluster of $MFT is unreadable (Mft by default)
+  mft_source : Option<MftSource>,
+  ///node containing an extracted MFT used instead of the MFT of the volume, only with a single volume
+  #[schemars(with = "Option<TreeNodeIdSchema>")]
+  mft : Option<TreeNodeId>,
+  ///if set the MFT is read by window instead of being loaded in memory (automatic for MFT bigger than 1GB)
+  stream_mft : Option<bool>,
+  ///if set records free in $MFT:$BITMAP that were never used are not parsed, records of deleted files are still parsed (ignored in recovery mode)
+  skip_unallocated : Option<bool>,
+  ///byte offset of the volume in the data of the nodes, to parse a volume inside a disk image (0 by default)
+  offset : Option<u64>,
+  ///sector size used instead of the boot sector value, when the boot sector is damaged
+  sector_size : Option<u16>,
+  ///cluster size used instead of the boot sector value, when the boot sector is damaged
+  cluster_size : Option<u64>,
+  ///MFT record size used instead of the boot sector value, when the boot sector is damaged
+  mft_record_size : Option<u32>,
+  ///if set to false records with attributes that failed to parse are reported as corrupt instead of creating nodes (true by default)
+  lenient : Option<bool>,
+  ///only parse these attributes, all attributes are parsed by default
+  attributes : Option<Vec<NtfsAttributeType>>,
+  ///first record to parse, system records and the root directory are always parsed
+  first_entry : Option<u64>,
+  ///record after the last record to parse
+  end_entry : Option<u64>,
+  ///only parse the entries under this path (e.g. /Users), found with the directory indexes so deleted entries are not parsed
+  path_prefix : Option<String>,
+  ///stop parsing after this number of records, the report is marked as truncated when the limit is reached
+  max_entries : Option<u64>,
+  ///records between two progress logs, 0 to only log the start and end of each phase (10000 by default)
+  progress_interval : Option<u64>,
+  ///log the warnings about single records (Log), only add them to the report (Report) or drop them (Off)
+  entry_warnings : Option<EntryWarnings>,
+}
+
+impl Arguments
+{
+  /// parser options corresponding to the arguments
+  fn options(&self) -> NtfsOptions
+  {
+    NtfsOptions{
+      name_space : self.name_space.unwrap_or_default(),
+      orphan_policy : self.orphan_policy.unwrap_or_default(),
+      include_deleted : self.include_deleted.unwrap_or(true),
+      deleted_branch : self.deleted_branch.unwrap_or(false),
+      lenient : self.lenient.unwrap_or(true),
+      metadata_only : self.metadata_only.unwrap_or(false),
+      attributes : self.attributes.clone(),
+      metadata_cache : self.metadata_cache.as_ref().map(Into::into),
+      entry_cache_size : self.entry_cache_size,
+      mft_source : self.mft_source.unwrap_or_default(),
+      stream_mft : self.stream_mft,
+      skip_unallocated : self.skip_unallocated.unwrap_or(false),
+      recovery : self.recovery.unwrap_or(false),
+      entry_range : match (self.first_entry, self.end_entry)
+      {
+        (None, None) => None,
+        (first, end) => Some(first.unwrap_or(0)..end.unwrap_or(u64::MAX)),
+      },
+      path_prefix : self.path_prefix.clone(),
+      max_entries : self.max_entries,
+      progress_interval : self.progress_interval.unwrap_or(DEFAULT_PROGRESS_INTERVAL),
+      entry_warnings : self.entry_warnings.unwrap_or_default(),
+    }
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize,Default)]
+pub struct Results
+{
+  ///results of each volume in the order of the arguments, file first
+  volumes : Vec<VolumeResults>,
+  ///nodes that couldn't be parsed when several volumes are parsed
+  errors : Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VolumeResults
+{
+  ///node containing the volume
+  file : TreeNodeId,
+  ///node of the parsed volume and of its root, orphan and freespace nodes
+  ntfs_node : Option<TreeNodeId>,
+  root_node : Option<TreeNodeId>,
+  orphan_node : Option<TreeNodeId>,
+  ///node containing the metadata files if system branch is set
+  system_node : Option<TreeNodeId>,
+  freespace_node : Option<TreeNodeId>,
+  ///node with the totals of the volume
+  summary_node : Option<TreeNodeId>,
+  ///node with the allocation and fragmentation report if allocation report is set
+  allocation_node : Option<TreeNodeId>,
+  ///node containing the lost clusters if lost clusters is set
+  lost_clusters_node : Option<TreeNodeId>,
+  ///number of clusters allocated in $Bitmap but used by no live file
+  lost_clusters : Option<u64>,
+  volume_label : Option<String>,
+  ///serial number as displayed by Windows
+  volume_serial : String,
+  ///full 64 bits serial number (XXXX-XXXX-XXXX-XXXX)
+  volume_serial_hex : String,
+  ///NTFS version from $Volume:$VOLUME_INFORMATION
+  ntfs_version : Option<String>,
+  cluster_size : u64,
+  mft_record_size : u32,
+  index_record_size : u32,
+  ///number of records in the MFT
+  records : u64,
+  ///number of MFT records marked as allocated in $MFT:$BITMAP
+  allocated_records : Option<u64>,
+  ///number of MFT records marked as free in $MFT:$BITMAP
+  unallocated_records : Option<u64>,
+  ///number of MFT records that were never used
+  unused_records : u64,
+  ///ranges of never used MFT records
+  unused_record_ranges : Option<Vec<Range<u64>>>,
+  ///summary of the MFT parsing
+  report : ParseReport,
+  ///time spent in each phase of the parsing
+  perf : PerfCounters,
+}
+
+#[derive(Default)]
+pub struct NtfsPlugin
+{
+}
+
+impl NtfsPlugin
+{
+  fn run(&mut self, args : Arguments, env : PluginEnvironment) -> Result<Results>
+  {
+    let files : Vec<TreeNodeId> = args.file.iter().chain(args.files.iter().flatten()).copied().collect();
+    //a single volume keep failing like before, otherwise the other volumes are still parsed
+    match files.as_slice()
+    {
+      [] => Err(RustructError::ArgumentNotFound("file").into()),
+      [file] => Ok(Results{ volumes : vec![self.parse_volume(*file, &args, &env)?], errors : Vec::new() }),
+      _ if args.mft.is_some() => Err(anyhow!("an extracted MFT can only be used to parse a single volume")),
+      files =>
+      {
+        let mut results = Results::default();
+        for file in files
+        {
+          match self.parse_volume(*file, &args, &env)
+          {
+            Ok(volume) => results.volumes.push(volume),
+            Err(err) => { warn!("Can't parse volume of node {:?} : {}", file, err); results.errors.push(format!("{:?} : {}", file, err)) },
+          }
+        }
+        Ok(results)
+      },
+    }
+  }
+
+  fn parse_volume(&self, file : TreeNodeId, args : &Arguments, env : &PluginEnvironment) -> Result<VolumeResults>
+  {
+    let file_node = env.tree.get_node_from_id(file).ok_or(RustructError::ArgumentNotFound("file"))?;
+    file_node.value().add_attribute(self.name(), None, None); 
+    let value = file_node.value().get_value("data").ok_or(RustructError::ValueNotFound("data"))?;
+    let partition_builder = value.try_as_vfile_builder().ok_or(RustructError::ValueTypeMismatch)?;
+    let partition_builder = match args.offset
+    {
+      Some(offset) if offset != 0 => offset_builder(partition_builder, offset)?,
+      _ => partition_builder,
+    };
+
+    let mut perf = PerfCounters::default();
+    let start = Instant::now();
+    let mut file = partition_builder.open()?;
+    let geometry = Geometry{ sector_size : args.sector_size, cluster_size : args.cluster_size, mft_record_size : args.mft_record_size };
+    let boot_sector = BootSector::from_file_with(&mut file, &geometry)?;
+    perf.boot_sector_ms = start.elapsed().as_millis() as u64;
+    //the boot sector is moved to the $Boot node at the end
+    let volume_serial = boot_sector.volume_serial_string();
+    let volume_serial_hex = boot_sector.volume_serial_hex();
+    let (cluster_size, mft_record_size, index_record_size) = (boot_sector.cluster_size, boot_sector.mft_record_size, boot_sector.index_record_size);
+
+    let start = Instant::now();
+    let mut ntfs = match args.mft
+    {
+      Some(mft) =>
+      {
+        let mft_node = env.tree.get_node_from_id(mft).ok_or(RustructError::ArgumentNotFound("mft"))?;
+        let value = mft_node.value().get_value("data").ok_or(RustructError::ValueNotFound("data"))?;
+        let master_mft_builder = value.try_as_vfile_builder().ok_or(RustructError::ValueTypeMismatch)?;
+        Ntfs::from_partition_with_mft(partition_builder.clone(), master_mft_builder, &boot_sector, args.options())?
+      },
+      None => Ntfs::from_partition(partition_builder.clone(), &boot_sector, args.options())?,
+    };
+    ntfs.set_progress_callback(Arc::new(|progress : &Progress|
+      info!("{:?} {}/{} {:.1}% eta {:?}", progress.phase, progress.done, progress.total, progress.percent(), progress.eta)));
+    if args.deletion_time == Some(true)
+    {
+      match ntfs.usn_deletion_hints()
+      {
+        Ok(hints) => ntfs.set_deletion_hints(hints),
+        Err(err) => warn!("Can't read $UsnJrnl deletion hints : {}", err),
+      }
+    }
+    ntfs.create_nodes(&env.tree)?;
+    let mft_parse_time = start.elapsed();
+    perf.mft_parse_ms = mft_parse_time.as_millis() as u64;
+    if mft_parse_time.as_secs_f64() > 0.0
+    {
+      perf.entries_per_second = ntfs.report().parsed as f64 / mft_parse_time.as_secs_f64();
+    }
+    let ntfs_node_name = match args.label_node_name
+    {
+      Some(true) => match ntfs.volume_name()
+      {
+        Some(label) if !label.is_empty() => format!("ntfs ({}, {})", label, volume_serial),
+        _ => format!("ntfs ({})", volume_serial),
+      },
+      _ => "ntfs".to_string(),
+    };
+    let ntfs_node = Node::new(ntfs_node_name);
+    let ntfs_node_id = env.tree.add_child(file, ntfs_node)?;
+    let orphan_node = Node::new("orphan");
+    let orphan_node_id = env.tree.add_child(ntfs_node_id, orphan_node)?;
+    let system_node_id = match args.system_branch
+    {
+      Some(true) => Some(env.tree.add_child(ntfs_node_id, Node::new("system"))?),
+      _ => None,
+    };
+    //path of the node containing the metadata files
+    let system_path = match system_node_id
+    {
+      Some(_) => "/system",
+      None => "/root",
+    };
+    let start = Instant::now();
+    ntfs.link_nodes(&env.tree, ntfs_node_id, orphan_node_id, system_node_id);
+    if ntfs.options().deleted_branch
+    {
+      let deleted_node_id = env.tree.add_child(ntfs_node_id, Node::new("deleted"))?;
+      ntfs.link_deleted(&env.tree, deleted_node_id);
+    }
+    perf.linking_ms = start.elapsed().as_millis() as u64;
+
+    //Create freespace and recover MFT entries if options is set
+    let start = Instant::now();
+    let mut freespace_node_id = None;
+    let freespace_map = match args.skip_freespace
+    {
+      Some(true) => None,
+      _ => ntfs.freespace(&env.tree, cluster_size, partition_builder.size()), //cath error we can continue 
+    };
+    if let Some(freespace_map) = freespace_map
+    {
+      let freespace_node = Node::new("freespace");
+      freespace_node.value().add_attribute("data", freespace_map.builder(partition_builder.clone()), None);
+      //extent table to translate an offset of the freespace to an offset of the partition
+      let freespace_map = Arc::new(freespace_map);
+      freespace_node.value().add_attribute("freespace", freespace_map.clone(), None);
+      let node_id = env.tree.add_child(ntfs_node_id, freespace_node)?;
+      freespace_node_id = Some(node_id);
+      if args.freespace_extents == Some(true)
+      {
+        for extent in freespace_map.extents.iter()
+        {
+          let start_lcn = freespace_map.start_lcn(extent);
+          let extent_node = Node::new(format!("lcn_{}", start_lcn));
+          extent_node.value().add_attribute("data", FreespaceMap::extent_builder(extent, partition_builder.clone()), None);
+          extent_node.value().add_attribute("start_lcn", start_lcn, None);
+          extent_node.value().add_attribute("offset", extent.partition_offset, None);
+          extent_node.value().add_attribute("length", extent.size, None);
+          env.tree.add_child(node_id, extent_node)?;
+        }
+      }
+      perf.freespace_ms = start.elapsed().as_millis() as u64;
+
+      if ntfs.options().recovery
+      { 
+        warn!("recovering data by carving"); 
+        let start = Instant::now();
+        ntfs.recovery(); 
+        perf.recovery_ms = start.elapsed().as_millis() as u64;
+      }
+        //carve and add node to free space
+        //let entries = ntfs.recovery()
+        //for each entry link to unallocated /freespace /tree ? 
+    }
+
+    //Add attribute of our parsed bootsector to $Boot
+    if let Some(boot_node_id) = env.tree.find_node_from_id(ntfs_node_id, &format!("{}/$Boot", system_path))
+    {
+      let boot_node = env.tree.get_node_from_id(boot_node_id).unwrap();
+      boot_sector.add_attribute(&boot_node, partition_builder);
+    }
+
+    //Add our parsed $MFT with attribute to the tree 
+    let tag_datatypes = args.tag_datatypes.unwrap_or_default();
+    if let Some(root) = env.tree.find_node_from_id(ntfs_node_id, system_path)
+    {
+      if let Some(mft_ntfs_node) = ntfs.mft_node() 
+      {
+        let node = mft_ntfs_node.to_node();
+        //avoid to recurse infinitely on a magic scan
+        if tag_datatypes != DatatypeTagging::Off
+        {
+          node.value().add_attribute("datatype", "ntfs/mft", None);
+        }
+        env.tree.add_child(root, node)?;
+      }
+    }
+
+    if tag_datatypes != DatatypeTagging::Off
+    {
+      if let Some(mft_mirror) = env.tree.find_node_from_id(ntfs_node_id, &format!("{}/$MFTMirr", system_path))
+      {
+        let mft_mirror_node = env.tree.get_node_from_id(mft_mirror).unwrap();
+        mft_mirror_node.value().add_attribute("datatype", "ntfs/mft", None);
+      }
+    }
+
+    if tag_datatypes == DatatypeTagging::Aggressive
+    {
+      for (path, datatype) in TAGGED_FILES
+      {
+        if let Some(node) = env.tree.find_node_from_id(ntfs_node_id, &format!("{}/{}", system_path, path)).and_then(|node_id| env.tree.get_node_from_id(node_id))
+        {
+          node.value().add_attribute("datatype", datatype, None);
+        }
+      }
+    }
+
+    //totals of the parsed nodes, the deleted and system branches don't change them
+    let summary_node_id = match args.summary
+    {
+      Some(false) => None,
+      _ =>
+      {
+        let summary_node = Node::new("summary");
+        summary_node.value().add_attribute("summary", Arc::new(ntfs.nodes_summary()), None);
+        Some(env.tree.add_child(ntfs_node_id, summary_node)?)
+      },
+    };
+
+    let allocation_node_id = match args.allocation_report
+    {
+      Some(true) => match ntfs.allocation_report()
+      {
+        Ok(report) =>
+        {
+          let allocation_node = Node::new("allocation");
+          allocation_node.value().add_attribute("allocation", Arc::new(report), None);
+          Some(env.tree.add_child(ntfs_node_id, allocation_node)?)
+        },
+        Err(err) => { warn!("Can't create allocation report : {}", err); None },
+      },
+      _ => None,
+    };
+
+    let (mut lost_clusters, mut lost_clusters_node_id) = (None, None);
+    if args.lost_clusters == Some(true)
+    {
+      match ntfs.lost_clusters()
+      {
+        Ok(ranges) =>
+        {
+          lost_clusters = Some(ranges.count());
+          if let Some(builder) = ntfs.lost_clusters_builder(&ranges)
+          {
+            let lost_clusters_node = Node::new("lost_clusters");
+            lost_clusters_node.value().add_attribute("data", builder, None);
+            lost_clusters_node_id = Some(env.tree.add_child(ntfs_node_id, lost_clusters_node)?);
+          }
+        },
+        Err(err) => warn!("Can't find lost clusters : {}", err),
+      }
+    }
+
+    let allocated_records = ntfs.mft_entries().allocated_count();
+    let unallocated_records = allocated_records.map(|count| ntfs.mft_entries().count().saturating_sub(count));
+
+    let unused_records = ntfs.unused_entry_count();
+    let unused_record_ranges = match args.unused_ranges
+    {
+      Some(true) => Some(ntfs.unused_entries().to_vec()),
+      _ => None,
+    };
+
+    let report = ntfs.report().clone();
+    (perf.cache_hits, perf.cache_misses) = ntfs.mft_entries().cache_stats();
+
+    Ok(VolumeResults{
+      file,
+      ntfs_node : Some(ntfs_node_id),
+      root_node : env.tree.find_node_from_id(ntfs_node_id, "/root"),
+      orphan_node : Some(orphan_node_id),
+      system_node : system_node_id,
+      freespace_node : freespace_node_id,
+      summary_node : summary_node_id,
+      allocation_node : allocation_node_id,
+      lost_clusters_node : lost_clusters_node_id,
+      lost_clusters,
+      volume_label : ntfs.volume_name().map(str::to_string),
+      volume_serial,
+      volume_serial_hex,
+      ntfs_version : ntfs.volume_information().map(|information| information.version.clone()),
+      cluster_size,
+      mft_record_size,
+      index_record_size,
+      records : ntfs.mft_entries().count(),
+      allocated_records,
+      unallocated_records,
+      unused_records,
+      unused_record_ranges,
+      report,
+      perf,
+    })
+  }
+}
@@ -0,0 +1,97 @@
+//! progress of the long phases of the parsing, reported to a callback so a UI can display it
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::{Serialize, Deserialize};
+
+/// records between two reports by default
+pub const DEFAULT_PROGRESS_INTERVAL : u64 = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Phase
+{
+  /// reading the records and creating the nodes
+  Parsing,
+  /// linking the nodes to their parent
+  Linking,
+}
+
+/**
+ *  Progress
+ *  State of a phase when it's reported, `done` goes from 0 to `total`
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Progress
+{
+  pub phase : Phase,
+  pub done : u64,
+  pub total : u64,
+  pub elapsed : Duration,
+  /// estimated remaining time from the current rate, None until something is done
+  pub eta : Option<Duration>,
+}
+
+impl Progress
+{
+  pub fn percent(&self) -> f64
+  {
+    match self.total
+    {
+      0 => 100.0,
+      total => self.done as f64 * 100.0 / total as f64,
+    }
+  }
+}
+
+/// called with the progress of each phase, must be cheap as it's called from the parsing loop
+pub type ProgressCallback = Arc<dyn Fn(&Progress) + Send + Sync>;
+
+/// report the progress of a phase every `interval` steps, and at its start and end
+pub(crate) struct ProgressTracker<'a>
+{
+  phase : Phase,
+  total : u64,
+  interval : u64,
+  start : Instant,
+  callback : Option<&'a ProgressCallback>,
+}
+
+impl<'a> ProgressTracker<'a>
+{
+  pub(crate) fn new(phase : Phase, total : u64, interval : u64, callback : Option<&'a ProgressCallback>) -> Self
+  {
+    let tracker = ProgressTracker{ phase, total, interval, start : Instant::now(), callback };
+    tracker.report(0);
+    tracker
+  }
+
+  pub(crate) fn update(&self, done : u64)
+  {
+    if self.interval != 0 && done % self.interval == 0
+    {
+      self.report(done);
+    }
+  }
+
+  pub(crate) fn finish(&self)
+  {
+    self.report(self.total);
+  }
+
+  fn report(&self, done : u64)
+  {
+    let callback = match self.callback
+    {
+      Some(callback) => callback,
+      None => return,
+    };
+    let elapsed = self.start.elapsed();
+    let eta = match done
+    {
+      0 => None,
+      done => Some(elapsed.mul_f64(self.total.saturating_sub(done) as f64 / done as f64)),
+    };
+    callback(&Progress{ phase : self.phase, done, total : self.total, elapsed, eta });
+  }
+}
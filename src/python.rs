@@ -0,0 +1,234 @@
+//! Python bindings, the module is named tap_plugin_ntfs
+//!
+//! ```python
+//! import tap_plugin_ntfs
+//! ntfs = tap_plugin_ntfs.Ntfs("/dev/sdb1")
+//! entry_id = ntfs.open_path("/Windows/System32/config/SYSTEM")
+//! data = ntfs.open_stream(entry_id).read()
+//! ```
+
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Arc;
+
+use tap::vfile::{VFile, VFileBuilder};
+
+use pyo3::prelude::*;
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::types::PyBytes;
+
+use crate::ntfs::Ntfs;
+use crate::options::NtfsOptions;
+use crate::mftentry::MftEntry;
+use crate::ntfsattributes::NtfsAttributeType;
+use crate::attributes::filename::NameSpacePreference;
+
+fn to_py_err(err : anyhow::Error) -> PyErr
+{
+  PyIOError::new_err(err.to_string())
+}
+
+/**
+ *  Ntfs
+ *  Volume opened from a path, records and streams are read on demand
+ */
+#[pyclass(name = "Ntfs")]
+pub struct PyNtfs
+{
+  ntfs : Ntfs,
+}
+
+#[pymethods]
+impl PyNtfs
+{
+  #[new]
+  #[pyo3(signature = (path, stream_mft = None))]
+  fn new(path : &str, stream_mft : Option<bool>) -> PyResult<Self>
+  {
+    let options = NtfsOptions{ stream_mft, ..NtfsOptions::default() };
+    let ntfs = Ntfs::from_path(Path::new(path), options).map_err(to_py_err)?;
+    Ok(PyNtfs{ ntfs })
+  }
+
+  /// number of records in the MFT
+  fn entry_count(&self) -> u64
+  {
+    self.ntfs.mft_entries().count()
+  }
+
+  #[getter]
+  fn volume_name(&self) -> Option<String>
+  {
+    self.ntfs.volume_name().map(str::to_string)
+  }
+
+  fn entry(&self, entry_id : u64) -> PyResult<PyMftEntry>
+  {
+    let entry = self.ntfs.mft_entries().entry(entry_id).map_err(to_py_err)?;
+    Ok(PyMftEntry::new(entry_id, &entry, &self.ntfs))
+  }
+
+  /// path of an entry from the root of the volume
+  fn entry_path(&self, entry_id : u64) -> PyResult<String>
+  {
+    self.ntfs.entry_path(entry_id).map_err(to_py_err)
+  }
+
+  /// entry id of a path, names are compared case-insensitively
+  fn open_path(&self, path : &str) -> PyResult<u64>
+  {
+    self.ntfs.open_path(path).map(|(entry_id, _)| entry_id).map_err(to_py_err)
+  }
+
+  /// list of (entry id, name) of the directory `entry_id`
+  fn list_dir(&self, entry_id : u64) -> PyResult<Vec<(u64, String)>>
+  {
+    let entries = self.ntfs.directory_entries(entry_id).map_err(to_py_err)?;
+    Ok(entries.into_iter().map(|entry| (entry.file_reference.entry, entry.file_name.file_name)).collect())
+  }
+
+  /// file like object reading the data stream `name` of `entry_id`, the unnamed stream by default
+  #[pyo3(signature = (entry_id, name = None))]
+  fn open_stream(&self, entry_id : u64, name : Option<&str>) -> PyResult<PyStream>
+  {
+    let builder = self.ntfs.attribute_builder(entry_id, NtfsAttributeType::Data, name).map_err(to_py_err)?;
+    PyStream::new(builder)
+  }
+}
+
+/**
+ *  MftEntry
+ *  Parsed record, names and streams use the attribute list to read extension records
+ */
+#[pyclass(name = "MftEntry")]
+pub struct PyMftEntry
+{
+  #[pyo3(get)]
+  id : u64,
+  #[pyo3(get)]
+  is_used : bool,
+  #[pyo3(get)]
+  is_directory : bool,
+  #[pyo3(get)]
+  is_in_extend : bool,
+  #[pyo3(get)]
+  name : Option<String>,
+  ///all names of the entry, DOS names included
+  #[pyo3(get)]
+  names : Vec<String>,
+  ///names and sizes of the data streams, unnamed stream is named ""
+  #[pyo3(get)]
+  streams : Vec<(String, u64)>,
+  json : String,
+}
+
+impl PyMftEntry
+{
+  fn new(id : u64, entry : &MftEntry, ntfs : &Ntfs) -> Self
+  {
+    let attributes = entry.attributes_iter(Some(ntfs.mft_entries())).into_attributes();
+    let name = attributes.find_filename_with(NameSpacePreference::default()).map(|file_name| file_name.file_name);
+    let names = attributes.find_filenames().into_iter().map(|file_name| file_name.file_name.clone()).collect();
+    let streams = attributes.find_datas().into_iter().map(|content|
+      (content.mft_attribute.name.clone().unwrap_or_default(), content.builder().map(|builder| builder.size()).unwrap_or(0))
+    ).collect();
+    let json = serde_json::to_string(&attributes).unwrap_or_default();
+
+    PyMftEntry{ id, is_used : entry.is_used(), is_directory : entry.is_directory(), is_in_extend : entry.is_in_extend(),
+                name, names, streams, json }
+  }
+}
+
+#[pymethods]
+impl PyMftEntry
+{
+  /// parsed attributes serialized as JSON
+  fn attributes_json(&self) -> String
+  {
+    self.json.clone()
+  }
+
+  fn __repr__(&self) -> String
+  {
+    format!("MftEntry({}, {:?})", self.id, self.name.as_deref().unwrap_or(""))
+  }
+}
+
+/**
+ *  Stream
+ *  Read only file like object on an attribute content
+ */
+#[pyclass(name = "Stream", unsendable)]
+pub struct PyStream
+{
+  file : Box<dyn VFile>,
+  size : u64,
+  position : u64,
+}
+
+impl PyStream
+{
+  fn new(builder : Arc<dyn VFileBuilder>) -> PyResult<Self>
+  {
+    let file = builder.open().map_err(to_py_err)?;
+    Ok(PyStream{ file, size : builder.size(), position : 0 })
+  }
+}
+
+#[pymethods]
+impl PyStream
+{
+  #[getter]
+  fn size(&self) -> u64
+  {
+    self.size
+  }
+
+  /// read `size` bytes, or up to the end of the stream if negative
+  #[pyo3(signature = (size = -1))]
+  fn read<'py>(&mut self, py : Python<'py>, size : i64) -> PyResult<&'py PyBytes>
+  {
+    let remaining = self.size.saturating_sub(self.position);
+    let size = match size
+    {
+      size if size < 0 => remaining,
+      size => (size as u64).min(remaining),
+    };
+    let mut buffer = vec![0u8; size as usize];
+    self.file.seek(SeekFrom::Start(self.position))?;
+    self.file.read_exact(&mut buffer)?;
+    self.position += size;
+    Ok(PyBytes::new(py, &buffer))
+  }
+
+  /// move to `offset` relatively to the start (0), the current position (1) or the end (2)
+  #[pyo3(signature = (offset, whence = 0))]
+  fn seek(&mut self, offset : i64, whence : u8) -> PyResult<u64>
+  {
+    let base = match whence
+    {
+      0 => 0,
+      1 => self.position as i64,
+      2 => self.size as i64,
+      _ => return Err(PyValueError::new_err(format!("invalid whence {}", whence))),
+    };
+    let position = base.checked_add(offset).filter(|position| *position >= 0)
+                       .ok_or_else(|| PyValueError::new_err("negative seek position"))?;
+    self.position = position as u64;
+    Ok(self.position)
+  }
+
+  fn tell(&self) -> u64
+  {
+    self.position
+  }
+}
+
+#[pymodule]
+fn tap_plugin_ntfs(_py : Python, module : &PyModule) -> PyResult<()>
+{
+  module.add_class::<PyNtfs>()?;
+  module.add_class::<PyMftEntry>()?;
+  module.add_class::<PyStream>()?;
+  Ok(())
+}
@@ -0,0 +1,93 @@
+//! tap plugin parsing a single MFT record found in the data of a node (datatype ntfs/mftentry),
+//! e.g. a FILE record produced by a carver, without the volume it comes from
+
+use std::fmt::Debug;
+
+use tap::plugin;
+use tap::config_schema;
+use tap::error::RustructError;
+use tap::tree::{TreeNodeId, TreeNodeIdSchema};
+use tap::plugin::{PluginInfo, PluginInstance, PluginConfig, PluginArgument, PluginResult, PluginEnvironment};
+
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use schemars::JsonSchema;
+
+use crate::error::NtfsError;
+use crate::mftentry::{MftEntry, MFT_HEADER_SIZE};
+use crate::ntfs::NtfsNode;
+use crate::attributes::filename::NameSpacePreference;
+
+/// largest record size, records are 1024 bytes or 4096 bytes on 4K native disks
+const MAX_RECORD_SIZE : u64 = 4096;
+const DEFAULT_SECTOR_SIZE : u16 = 512;
+
+plugin!("ntfs_record", "File system", "Parse a single carved MFT record", NtfsRecordPlugin, Arguments);
+
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Arguments
+{
+  #[schemars(with = "TreeNodeIdSchema")]
+  file : TreeNodeId,
+  ///size of the sectors protected by the fixups (512 by default)
+  sector_size : Option<u16>,
+  ///name space preferred when choosing the name of the node (Win32 by default)
+  name_space : Option<NameSpacePreference>,
+}
+
+#[derive(Debug, Serialize, Deserialize,Default)]
+pub struct Results
+{
+  ///record number stored in the header, only present since NTFS 3.1
+  record_number : Option<u32>,
+  ///number of nodes created, one by data stream
+  nodes : u64,
+  ///attributes that failed to parse, non resident data can't be read without the volume
+  errors : Vec<String>,
+}
+
+#[derive(Default)]
+pub struct NtfsRecordPlugin
+{
+}
+
+impl NtfsRecordPlugin
+{
+  fn run(&mut self, args : Arguments, env : PluginEnvironment) -> Result<Results>
+  {
+    let file_node = env.tree.get_node_from_id(args.file).ok_or(RustructError::ArgumentNotFound("file"))?;
+    file_node.value().add_attribute(self.name(), None, None);
+    let value = file_node.value().get_value("data").ok_or(RustructError::ValueNotFound("data"))?;
+    let record_builder = value.try_as_vfile_builder().ok_or(RustructError::ValueTypeMismatch)?;
+
+    let record_size = record_builder.size();
+    if record_size < MFT_HEADER_SIZE as u64
+    {
+      return Err(NtfsError::MftRecordSizeTooSmall(record_size as u32).into())
+    }
+    if record_size > MAX_RECORD_SIZE
+    {
+      return Err(NtfsError::MftRecordSizeTooLarge(record_size).into())
+    }
+    let mut data = vec![0u8; record_size as usize];
+    record_builder.open()?.read_exact(&mut data)?;
+
+    //the record has no volume, so it can't follow attribute list or read non resident data
+    let entry = MftEntry::from_data(data, 0, None, record_builder.clone(), None, args.sector_size.unwrap_or(DEFAULT_SECTOR_SIZE), None)?;
+    let record_number = entry.record_number;
+    let entry_id = record_number.map(u64::from).unwrap_or(0);
+    let ntfs_nodes = NtfsNode::from_entry(entry_id, &entry, None, args.name_space.unwrap_or_default(), None);
+
+    let mut errors = Vec::new();
+    let nodes = ntfs_nodes.len() as u64;
+    for ntfs_node in ntfs_nodes
+    {
+      errors.extend(ntfs_node.attributes.parse_errors().iter().cloned());
+      env.tree.add_child(args.file, ntfs_node.to_node())?;
+    }
+    errors.dedup();
+
+    Ok(Results{ record_number, nodes, errors })
+  }
+}
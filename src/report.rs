@@ -0,0 +1,78 @@
+use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
+
+/// what is done with the warnings about a single record, like a record that can't be read
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
+pub enum EntryWarnings
+{
+  /// log the warnings and add them to the report
+  #[default]
+  Log,
+  /// only add the warnings to the report
+  Report,
+  /// drop the warnings, the counters of the report are still updated
+  Off,
+}
+
+/**
+ *  ParseReport
+ *  Summary of the MFT parsing used to assess parse quality
+ */
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ParseReport
+{
+  ///number of records turned into nodes
+  pub parsed : u64,
+  ///number of records ignored as they were never used or excluded by the options
+  pub skipped : u64,
+  ///number of records that can't be read or that have attributes that failed to parse
+  pub corrupt : u64,
+  ///number of nodes linked to the orphan node
+  pub orphaned : u64,
+  ///number of parsed records that are files
+  pub files : u64,
+  ///number of parsed records that are directories
+  pub directories : u64,
+  ///number of parsed records not in use
+  pub deleted : u64,
+  ///number of alternate data streams
+  pub ads : u64,
+  ///parsing stopped before the end of the MFT as the max entries limit was reached
+  pub truncated : bool,
+  pub warnings : Vec<ParseWarning>,
+}
+
+/**
+ *  PerfCounters
+ *  Time spent in each phase of the parsing, to see where time goes on big volumes
+ */
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct PerfCounters
+{
+  pub boot_sector_ms : u64,
+  pub mft_parse_ms : u64,
+  pub linking_ms : u64,
+  pub freespace_ms : u64,
+  pub recovery_ms : u64,
+  ///records parsed by second while creating the nodes
+  pub entries_per_second : f64,
+  ///records referenced by attribute lists found in the cache
+  pub cache_hits : u64,
+  ///records referenced by attribute lists read from the MFT
+  pub cache_misses : u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParseWarning
+{
+  pub entry : u64,
+  pub message : String,
+}
+
+impl ParseReport
+{
+  pub fn warn(&mut self, entry : u64, message : String)
+  {
+    self.warnings.push(ParseWarning{ entry, message });
+  }
+}
@@ -0,0 +1,259 @@
+//! $Secure security descriptors, $SDS stream contains the descriptors and the $SII and $SDH indexes
+//! reference them by security id and by hash
+
+use std::sync::Arc;
+use std::collections::HashSet;
+
+use tap::vfile::VFileBuilder;
+#[cfg(feature = "plugin")]
+use tap::reflect::ReflectStruct;
+#[cfg(feature = "plugin")]
+use tap::value::Value;
+#[cfg(feature = "plugin")]
+use tap_derive::Reflect;
+
+use anyhow::Result;
+use byteorder::{ByteOrder, BigEndian, LittleEndian};
+use serde::{Serialize, Deserialize};
+
+use crate::attributecontent::record_slice;
+use crate::attributes::index::{for_each_index_record, view_entries_data};
+
+/// $SDS is written by blocks of 256KB each followed by its mirror copy
+const SDS_BLOCK_SIZE : u64 = 0x40000;
+const SDS_HEADER_SIZE : usize = 0x14;
+/// entries are aligned on 16 bytes
+const SDS_ALIGNMENT : u64 = 16;
+const SECURITY_DESCRIPTOR_HEADER_SIZE : usize = 0x14;
+/// read whole $SDS streams up to this size, bigger streams are very likely damaged
+const MAX_SDS_SIZE : u64 = 256 * 1024 * 1024;
+
+/**
+ *  Header of a $SDS entry, also stored as the data of the $SII and $SDH index entries
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SdsHeader
+{
+  pub hash : u32,
+  pub security_id : u32,
+  /// offset of the entry in $SDS
+  pub offset : u64,
+  /// size of the header and of the descriptor
+  pub length : u32,
+}
+
+impl SdsHeader
+{
+  pub fn new(data : &[u8]) -> Result<Self>
+  {
+    let data = record_slice(data, 0, SDS_HEADER_SIZE)?;
+    Ok(SdsHeader{
+      hash : LittleEndian::read_u32(&data[0..4]),
+      security_id : LittleEndian::read_u32(&data[4..8]),
+      offset : LittleEndian::read_u64(&data[8..16]),
+      length : LittleEndian::read_u32(&data[16..20]),
+    })
+  }
+}
+
+/**
+ *  Access control entry, only the standard allowed, denied, audit and alarm ACE have a SID
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ace
+{
+  pub ace_type : u8,
+  pub flags : u8,
+  pub mask : u32,
+  pub sid : Option<String>,
+}
+
+impl Ace
+{
+  /// short name of the ACE type as used by SDDL
+  pub fn type_name(&self) -> &'static str
+  {
+    match self.ace_type
+    {
+      0 => "A",
+      1 => "D",
+      2 => "AU",
+      3 => "AL",
+      5 => "OA",
+      6 => "OD",
+      7 => "OU",
+      8 => "OL",
+      _ => "?",
+    }
+  }
+}
+
+#[cfg(feature = "plugin")]
+fn aces_to_value(aces : &Option<Vec<Ace>>) -> Option<Value>
+{
+  aces.as_ref().map(|aces| Value::String(aces_to_string(aces)))
+}
+
+/// SDDL like representation of an ACL, (type;flags;mask;sid) for each ACE
+pub fn aces_to_string(aces : &[Ace]) -> String
+{
+  aces.iter().map(|ace| format!("({};{:#x};{:#x};{})", ace.type_name(), ace.flags, ace.mask, ace.sid.as_deref().unwrap_or(""))).collect()
+}
+
+/**
+ *  SecurityDescriptor
+ *  Self relative security descriptor of a $SDS entry
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "plugin", derive(Reflect))]
+pub struct SecurityDescriptor
+{
+  pub security_id : u32,
+  pub hash : u32,
+  /// offset of the entry in $SDS
+  pub offset : u64,
+  pub control : u16,
+  pub owner : Option<String>,
+  pub group : Option<String>,
+  #[cfg_attr(feature = "plugin", reflect(with = "aces_to_value"))]
+  pub dacl : Option<Vec<Ace>>,
+  #[cfg_attr(feature = "plugin", reflect(with = "aces_to_value"))]
+  pub sacl : Option<Vec<Ace>>,
+}
+
+impl SecurityDescriptor
+{
+  /// parse the descriptor following a $SDS header
+  pub fn new(header : &SdsHeader, descriptor : &[u8]) -> Result<Self>
+  {
+    let data = record_slice(descriptor, 0, SECURITY_DESCRIPTOR_HEADER_SIZE)?;
+    let control = LittleEndian::read_u16(&data[2..4]);
+    let owner_offset = LittleEndian::read_u32(&data[4..8]) as usize;
+    let group_offset = LittleEndian::read_u32(&data[8..12]) as usize;
+    let sacl_offset = LittleEndian::read_u32(&data[12..16]) as usize;
+    let dacl_offset = LittleEndian::read_u32(&data[16..20]) as usize;
+
+    //an offset of 0 means the part is not present
+    let sid_at = |offset : usize| -> Result<Option<String>> { match offset { 0 => Ok(None), offset => Ok(Some(parse_sid(descriptor, offset)?.0)) } };
+    let acl_at = |offset : usize| -> Result<Option<Vec<Ace>>> { match offset { 0 => Ok(None), offset => Ok(Some(parse_acl(descriptor, offset)?)) } };
+
+    Ok(SecurityDescriptor{
+      security_id : header.security_id,
+      hash : header.hash,
+      offset : header.offset,
+      control,
+      owner : sid_at(owner_offset)?,
+      group : sid_at(group_offset)?,
+      dacl : acl_at(dacl_offset)?,
+      sacl : acl_at(sacl_offset)?,
+    })
+  }
+
+  /// every SID referenced by the descriptor
+  pub fn sids(&self) -> impl Iterator<Item = &str>
+  {
+    let aces = self.dacl.iter().chain(self.sacl.iter()).flatten().filter_map(|ace| ace.sid.as_deref());
+    self.owner.as_deref().into_iter().chain(self.group.as_deref()).chain(aces)
+  }
+}
+
+/// string representation of a SID (S-1-5-21-...) and its size
+pub fn parse_sid(data : &[u8], offset : usize) -> Result<(String, usize)>
+{
+  let header = record_slice(data, offset, 8)?;
+  let revision = header[0];
+  let count = header[1] as usize;
+  let authority = BigEndian::read_u48(&header[2..8]);
+  let sub_authorities = record_slice(data, offset + 8, count * 4)?;
+
+  let mut sid = format!("S-{}-{}", revision, authority);
+  for sub_authority in sub_authorities.chunks_exact(4)
+  {
+    sid.push_str(&format!("-{}", LittleEndian::read_u32(sub_authority)));
+  }
+  Ok((sid, 8 + count * 4))
+}
+
+/// entries of an access control list
+fn parse_acl(data : &[u8], offset : usize) -> Result<Vec<Ace>>
+{
+  let header = record_slice(data, offset, 8)?;
+  let ace_count = LittleEndian::read_u16(&header[4..6]);
+
+  let mut aces = Vec::new();
+  let mut current = offset + 8;
+  for _ in 0..ace_count
+  {
+    let ace = record_slice(data, current, 8)?;
+    let ace_type = ace[0];
+    let flags = ace[1];
+    let size = LittleEndian::read_u16(&ace[2..4]) as usize;
+    let mask = LittleEndian::read_u32(&ace[4..8]);
+    let sid = match ace_type
+    {
+      0..=3 => Some(parse_sid(data, current + 8)?.0),
+      _ => None,
+    };
+    aces.push(Ace{ ace_type, flags, mask, sid });
+    if size < 8
+    {
+      break
+    }
+    current += size;
+  }
+  Ok(aces)
+}
+
+/// parse the descriptors of a $SDS stream, mirror copies are skipped as their offset doesn't match their position
+pub fn sds_descriptors(builder : Arc<dyn VFileBuilder>) -> Result<Vec<SecurityDescriptor>>
+{
+  let size = builder.size().min(MAX_SDS_SIZE);
+  let mut data = vec![0u8; size as usize];
+  builder.open()?.read_exact(&mut data)?;
+
+  let mut descriptors = Vec::new();
+  let mut offset : u64 = 0;
+  while offset + SDS_HEADER_SIZE as u64 <= size
+  {
+    //odd blocks are the mirror of the previous one
+    if (offset / SDS_BLOCK_SIZE) % 2 == 1
+    {
+      offset = (offset / SDS_BLOCK_SIZE + 1) * SDS_BLOCK_SIZE;
+      continue
+    }
+
+    let header = SdsHeader::new(&data[offset as usize..])?;
+    let end = offset + header.length as u64;
+    if header.offset != offset || (header.length as usize) < SDS_HEADER_SIZE + SECURITY_DESCRIPTOR_HEADER_SIZE || end > size
+    {
+      offset += SDS_ALIGNMENT;
+      continue
+    }
+    //a damaged descriptor doesn't prevent reading the others
+    if let Ok(descriptor) = SecurityDescriptor::new(&header, &data[offset as usize + SDS_HEADER_SIZE..end as usize])
+    {
+      descriptors.push(descriptor);
+    }
+    offset = end.div_ceil(SDS_ALIGNMENT) * SDS_ALIGNMENT;
+  }
+  Ok(descriptors)
+}
+
+/// $SDS headers referenced by a $SII or $SDH index allocation stream
+pub fn index_headers(builder : Arc<dyn VFileBuilder>, index_record_size : u32, sector_size : u16) -> Result<Vec<SdsHeader>>
+{
+  let mut headers = Vec::new();
+  let mut seen = HashSet::new();
+  for_each_index_record(builder, index_record_size, sector_size, |record|
+  {
+    for data in view_entries_data(record).unwrap_or_default()
+    {
+      match SdsHeader::new(data)
+      {
+        Ok(header) if seen.insert(header.security_id) => headers.push(header),
+        _ => (),
+      }
+    }
+  })?;
+  Ok(headers)
+}
@@ -0,0 +1,99 @@
+//! tap plugin parsing the security descriptors of an extracted $Secure:$SDS stream,
+//! each descriptor is added as a child node and can be checked against an extracted $SII or $SDH index
+
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::collections::{BTreeSet, HashSet};
+
+use tap::plugin;
+use tap::config_schema;
+use tap::node::Node;
+use tap::error::RustructError;
+use tap::tree::{TreeNodeId, TreeNodeIdSchema};
+use tap::plugin::{PluginInfo, PluginInstance, PluginConfig, PluginArgument, PluginResult, PluginEnvironment};
+
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use schemars::JsonSchema;
+
+use crate::secure::{sds_descriptors, index_headers};
+
+/// index record size used by every Windows version
+const DEFAULT_INDEX_RECORD_SIZE : u32 = 4096;
+const DEFAULT_SECTOR_SIZE : u16 = 512;
+
+plugin!("secure", "File system", "Parse the security descriptors of an extracted $Secure:$SDS stream", SecurePlugin, Arguments);
+
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Arguments
+{
+  ///node containing the $SDS stream
+  #[schemars(with = "TreeNodeIdSchema")]
+  file : TreeNodeId,
+  ///node containing the $SII or $SDH index allocation, to find descriptors that are not indexed anymore
+  #[schemars(with = "Option<TreeNodeIdSchema>")]
+  index : Option<TreeNodeId>,
+  ///size of the INDX records of the index (4096 by default)
+  index_record_size : Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize,Default)]
+pub struct Results
+{
+  ///node containing the descriptors
+  secure_node : Option<TreeNodeId>,
+  ///number of descriptors parsed
+  descriptors : u64,
+  ///SIDs used as owner, group or in an ACE
+  sids : Vec<String>,
+  ///security ids of descriptors that are not in the index
+  unindexed : Vec<u32>,
+  ///security ids of the index not found in $SDS
+  missing : Vec<u32>,
+}
+
+#[derive(Default)]
+pub struct SecurePlugin
+{
+}
+
+impl SecurePlugin
+{
+  fn run(&mut self, args : Arguments, env : PluginEnvironment) -> Result<Results>
+  {
+    let file_node = env.tree.get_node_from_id(args.file).ok_or(RustructError::ArgumentNotFound("file"))?;
+    file_node.value().add_attribute(self.name(), None, None);
+    let value = file_node.value().get_value("data").ok_or(RustructError::ValueNotFound("data"))?;
+    let sds_builder = value.try_as_vfile_builder().ok_or(RustructError::ValueTypeMismatch)?;
+
+    let descriptors = sds_descriptors(sds_builder)?;
+    let mut results = Results{ descriptors : descriptors.len() as u64, ..Results::default() };
+    let sids : BTreeSet<&str> = descriptors.iter().flat_map(|descriptor| descriptor.sids()).collect();
+    results.sids = sids.into_iter().map(str::to_string).collect();
+
+    if let Some(index) = args.index
+    {
+      let index_node = env.tree.get_node_from_id(index).ok_or(RustructError::ArgumentNotFound("index"))?;
+      let value = index_node.value().get_value("data").ok_or(RustructError::ValueNotFound("data"))?;
+      let index_builder = value.try_as_vfile_builder().ok_or(RustructError::ValueTypeMismatch)?;
+      let headers = index_headers(index_builder, args.index_record_size.unwrap_or(DEFAULT_INDEX_RECORD_SIZE), DEFAULT_SECTOR_SIZE)?;
+
+      let indexed : HashSet<u32> = headers.iter().map(|header| header.security_id).collect();
+      let parsed : HashSet<u32> = descriptors.iter().map(|descriptor| descriptor.security_id).collect();
+      results.unindexed = descriptors.iter().map(|descriptor| descriptor.security_id).filter(|id| !indexed.contains(id)).collect();
+      results.missing = headers.iter().map(|header| header.security_id).filter(|id| !parsed.contains(id)).collect();
+    }
+
+    let secure_node_id = env.tree.add_child(args.file, Node::new("secure"))?;
+    results.secure_node = Some(secure_node_id);
+    for descriptor in descriptors
+    {
+      let node = Node::new(descriptor.security_id.to_string());
+      node.value().add_attribute("secure", Arc::new(descriptor), None);
+      env.tree.add_child(secure_node_id, node)?;
+    }
+
+    Ok(results)
+  }
+}
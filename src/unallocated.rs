@@ -2,28 +2,161 @@ use std::sync::Arc;
 
 use tap::vfile::VFileBuilder;
 use tap::mappedvfile::{MappedVFileBuilder, FileRanges};
+#[cfg(feature = "plugin")]
+use tap::reflect::ReflectStruct;
+#[cfg(feature = "plugin")]
+use tap::value::Value;
+#[cfg(feature = "plugin")]
+use tap_derive::Reflect;
 
-use crate::attributes::bitmap::Bitmap;
+use anyhow::Result;
+use serde::{Serialize, Deserialize};
 
+use crate::attributes::bitmap::{Bitmap, RangeSet};
 
-pub fn freespace_builder(builder : Arc<dyn VFileBuilder>, parent_builder : Arc<dyn VFileBuilder>, cluster_size : u64) -> Arc<dyn VFileBuilder>
+/// builder reading the clusters of `ranges` one after the other
+pub fn clusters_builder(ranges : &RangeSet, parent_builder : Arc<dyn VFileBuilder>, cluster_size : u64) -> Arc<dyn VFileBuilder>
 {
-  let bitmap = Bitmap::new(builder).unwrap();
-
   let mut current_offset = 0;
   let mut file_ranges = FileRanges::new();
+  for range in ranges
+  {
+    let (offset, size) = match (range.start.checked_mul(cluster_size), (range.end - range.start).checked_mul(cluster_size))
+    {
+      (Some(offset), Some(size)) => (offset, size),
+      _ => break,
+    };
+    file_ranges.push(current_offset..current_offset + size, offset, parent_builder.clone());
+    current_offset += size;
+  }
+  Arc::new(MappedVFileBuilder::new(file_ranges))
+}
+
+
+/// contiguous range of free clusters at `offset` in the freespace
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FreespaceExtent
+{
+  /// offset of the extent in the freespace
+  pub offset : u64,
+  /// offset of the extent in the partition
+  pub partition_offset : u64,
+  pub size : u64,
+}
 
-  for cluster_range in bitmap.iter()
+#[cfg(feature = "plugin")]
+fn extents_to_value(extents : &[FreespaceExtent]) -> Option<Value>
+{
+  match extents.is_empty()
   {
-    let offset = cluster_range.start*cluster_size;
-    let size = (1 + cluster_range.end-cluster_range.start) * cluster_size;
+    true => None,
+    false => Some(Value::String(extents.iter().map(|extent| format!("{}:{}:{}", extent.offset, extent.partition_offset, extent.size))
+                                       .collect::<Vec<String>>().join("; "))),
+  }
+}
 
-    file_ranges.push(current_offset..current_offset + size, offset, parent_builder.clone()); 
+/**
+ *  FreespaceMap
+ *  Extents concatenated in the freespace, sorted by offset in the freespace and in the partition,
+ *  used to translate an offset found in the freespace to an offset of the partition
+ */
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "plugin", derive(Reflect))]
+pub struct FreespaceMap
+{
+  pub cluster_size : u64,
+  pub extent_count : u64,
+  /// size of the freespace
+  pub size : u64,
+  /// freespace offset:partition offset:size of each extent
+  #[cfg_attr(feature = "plugin", reflect(with = "extents_to_value"))]
+  pub extents : Vec<FreespaceExtent>,
+}
 
-    current_offset += size;
+impl FreespaceMap
+{
+  /// read the free ranges of a $Bitmap of a partition of `partition_size` bytes,
+  /// the bytes after the last cluster are not used by the file system and end the freespace
+  pub fn from_bitmap(builder : Arc<dyn VFileBuilder>, cluster_size : u64, partition_size : u64) -> Result<Self>
+  {
+    let total_clusters = match cluster_size
+    {
+      0 => 0,
+      cluster_size => partition_size / cluster_size,
+    };
+    let mut extents : Vec<FreespaceExtent> = Vec::new();
+    let mut current_offset = 0;
+    for cluster_range in Bitmap::ranges(builder, total_clusters)?.free
+    {
+      let partition_offset = cluster_range.start * cluster_size;
+      let size = (cluster_range.end - cluster_range.start) * cluster_size;
+      extents.push(FreespaceExtent{ offset : current_offset, partition_offset, size });
+      current_offset += size;
+    }
+    //volume slack after the last cluster, merged with the last extent if it ends the volume
+    let slack = total_clusters * cluster_size..partition_size;
+    if !slack.is_empty()
+    {
+      let size = slack.end - slack.start;
+      match extents.last_mut()
+      {
+        Some(last) if last.partition_offset + last.size == slack.start => last.size += size,
+        _ => extents.push(FreespaceExtent{ offset : current_offset, partition_offset : slack.start, size }),
+      }
+      current_offset += size;
+    }
+    Ok(FreespaceMap{ cluster_size, extent_count : extents.len() as u64, size : current_offset, extents })
+  }
+
+  /// builder reading the extents one after the other in `parent_builder`
+  pub fn builder(&self, parent_builder : Arc<dyn VFileBuilder>) -> Arc<dyn VFileBuilder>
+  {
+    let mut file_ranges = FileRanges::new();
+    for extent in self.extents.iter()
+    {
+      file_ranges.push(extent.offset..extent.offset + extent.size, extent.partition_offset, parent_builder.clone());
+    }
+    Arc::new(MappedVFileBuilder::new(file_ranges))
+  }
+
+  /// builder reading a single extent in `parent_builder`
+  pub fn extent_builder(extent : &FreespaceExtent, parent_builder : Arc<dyn VFileBuilder>) -> Arc<dyn VFileBuilder>
+  {
+    let mut file_ranges = FileRanges::new();
+    file_ranges.push(0..extent.size, extent.partition_offset, parent_builder);
+    Arc::new(MappedVFileBuilder::new(file_ranges))
+  }
+
+  /// first cluster of an extent
+  pub fn start_lcn(&self, extent : &FreespaceExtent) -> u64
+  {
+    match self.cluster_size
+    {
+      0 => 0,
+      cluster_size => extent.partition_offset / cluster_size,
+    }
+  }
+
+  /// extent containing the byte at `offset` of the freespace
+  pub fn extent(&self, offset : u64) -> Option<&FreespaceExtent>
+  {
+    let index = self.extents.partition_point(|extent| extent.offset + extent.size <= offset);
+    self.extents.get(index).filter(|extent| extent.offset <= offset)
+  }
+
+  /// offset in the partition of the byte at `offset` of the freespace
+  pub fn to_partition_offset(&self, offset : u64) -> Option<u64>
+  {
+    self.extent(offset).map(|extent| extent.partition_offset + (offset - extent.offset))
+  }
+
+  /// offset in the freespace of the byte at `partition_offset`, None if it's allocated
+  pub fn to_freespace_offset(&self, partition_offset : u64) -> Option<u64>
+  {
+    let index = self.extents.partition_point(|extent| extent.partition_offset + extent.size <= partition_offset);
+    self.extents.get(index).filter(|extent| extent.partition_offset <= partition_offset)
+                           .map(|extent| extent.offset + (partition_offset - extent.partition_offset))
   }
- 
-  Arc::new(MappedVFileBuilder::new(file_ranges))
 }
 
 
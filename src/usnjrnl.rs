@@ -0,0 +1,319 @@
+//! $UsnJrnl:$J change journal parser, the stream is read sequentially so it can come from a volume,
+//! an extracted file or a carved fragment
+
+use std::io::SeekFrom;
+use std::sync::Arc;
+
+use tap::vfile::{VFile, VFileBuilder};
+#[cfg(feature = "plugin")]
+use tap::reflect::ReflectStruct;
+#[cfg(feature = "plugin")]
+use tap::value::Value;
+use tap::datetime::WindowsTimestamp;
+#[cfg(feature = "plugin")]
+use tap_derive::Reflect;
+
+use anyhow::Result;
+use byteorder::{ByteOrder, LittleEndian};
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+
+use crate::error::NtfsError;
+use crate::deleted::DeletionHints;
+use crate::attributes::FileAttributes;
+use crate::attributecontent::{pad_u64, record_slice, record_utf16};
+#[cfg(feature = "plugin")]
+use crate::attributes::file_attributes_to_value;
+
+/// size of the data read at once, the journal is mostly sparse zeros before the first record
+const CHUNK_SIZE : usize = 1024 * 1024;
+/// header size of a version 2 record, the smallest record
+const USN_RECORD_V2_SIZE : usize = 0x3c;
+/// header size of a version 3 record (128 bits file references)
+const USN_RECORD_V3_SIZE : usize = 0x4c;
+/// largest record, a 255 characters name in a version 3 record rounded to 8 bytes
+const USN_RECORD_MAX_SIZE : usize = 0x250;
+
+bitflags!
+{
+  #[derive(Serialize, Deserialize)]
+  pub struct UsnReason : u32
+  {
+    const DATA_OVERWRITE        = 0x0000_0001;
+    const DATA_EXTEND           = 0x0000_0002;
+    const DATA_TRUNCATION       = 0x0000_0004;
+    const NAMED_DATA_OVERWRITE  = 0x0000_0010;
+    const NAMED_DATA_EXTEND     = 0x0000_0020;
+    const NAMED_DATA_TRUNCATION = 0x0000_0040;
+    const FILE_CREATE           = 0x0000_0100;
+    const FILE_DELETE           = 0x0000_0200;
+    const EA_CHANGE             = 0x0000_0400;
+    const SECURITY_CHANGE       = 0x0000_0800;
+    const RENAME_OLD_NAME       = 0x0000_1000;
+    const RENAME_NEW_NAME       = 0x0000_2000;
+    const INDEXABLE_CHANGE      = 0x0000_4000;
+    const BASIC_INFO_CHANGE     = 0x0000_8000;
+    const HARD_LINK_CHANGE      = 0x0001_0000;
+    const COMPRESSION_CHANGE    = 0x0002_0000;
+    const ENCRYPTION_CHANGE     = 0x0004_0000;
+    const OBJECT_ID_CHANGE      = 0x0008_0000;
+    const REPARSE_POINT_CHANGE  = 0x0010_0000;
+    const STREAM_CHANGE         = 0x0020_0000;
+    const TRANSACTED_CHANGE     = 0x0040_0000;
+    const INTEGRITY_CHANGE      = 0x0080_0000;
+    const CLOSE                 = 0x8000_0000;
+  }
+}
+
+const USN_REASON_NAMES : [(UsnReason, &str); 23] = [
+  (UsnReason::DATA_OVERWRITE, "DATA_OVERWRITE"),
+  (UsnReason::DATA_EXTEND, "DATA_EXTEND"),
+  (UsnReason::DATA_TRUNCATION, "DATA_TRUNCATION"),
+  (UsnReason::NAMED_DATA_OVERWRITE, "NAMED_DATA_OVERWRITE"),
+  (UsnReason::NAMED_DATA_EXTEND, "NAMED_DATA_EXTEND"),
+  (UsnReason::NAMED_DATA_TRUNCATION, "NAMED_DATA_TRUNCATION"),
+  (UsnReason::FILE_CREATE, "FILE_CREATE"),
+  (UsnReason::FILE_DELETE, "FILE_DELETE"),
+  (UsnReason::EA_CHANGE, "EA_CHANGE"),
+  (UsnReason::SECURITY_CHANGE, "SECURITY_CHANGE"),
+  (UsnReason::RENAME_OLD_NAME, "RENAME_OLD_NAME"),
+  (UsnReason::RENAME_NEW_NAME, "RENAME_NEW_NAME"),
+  (UsnReason::INDEXABLE_CHANGE, "INDEXABLE_CHANGE"),
+  (UsnReason::BASIC_INFO_CHANGE, "BASIC_INFO_CHANGE"),
+  (UsnReason::HARD_LINK_CHANGE, "HARD_LINK_CHANGE"),
+  (UsnReason::COMPRESSION_CHANGE, "COMPRESSION_CHANGE"),
+  (UsnReason::ENCRYPTION_CHANGE, "ENCRYPTION_CHANGE"),
+  (UsnReason::OBJECT_ID_CHANGE, "OBJECT_ID_CHANGE"),
+  (UsnReason::REPARSE_POINT_CHANGE, "REPARSE_POINT_CHANGE"),
+  (UsnReason::STREAM_CHANGE, "STREAM_CHANGE"),
+  (UsnReason::TRANSACTED_CHANGE, "TRANSACTED_CHANGE"),
+  (UsnReason::INTEGRITY_CHANGE, "INTEGRITY_CHANGE"),
+  (UsnReason::CLOSE, "CLOSE"),
+];
+
+impl UsnReason
+{
+  /// name of each set flag
+  pub fn names(&self) -> Vec<&'static str>
+  {
+    USN_REASON_NAMES.iter().filter(|(flag, _)| self.contains(*flag)).map(|(_, name)| *name).collect()
+  }
+}
+
+#[cfg(feature = "plugin")]
+fn usn_reason_to_value(reason : &UsnReason) -> Option<Value>
+{
+  Some(Value::String(reason.names().join(", ")))
+}
+
+/**
+ *  UsnRecord
+ *  Version 2 or 3 change journal record, version 3 128 bits references are truncated to their NTFS 64 bits value
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "plugin", derive(Reflect))]
+pub struct UsnRecord
+{
+  /// offset of the record in the stream
+  pub offset : u64,
+  pub major_version : u16,
+  pub entry : u64,
+  pub sequence : u16,
+  pub parent_entry : u64,
+  pub parent_sequence : u16,
+  pub usn : u64,
+  pub timestamp : DateTime<Utc>,
+  #[cfg_attr(feature = "plugin", reflect(with = "usn_reason_to_value"))]
+  pub reason : UsnReason,
+  pub source_info : u32,
+  pub security_id : u32,
+  #[cfg_attr(feature = "plugin", reflect(with = "file_attributes_to_value"))]
+  pub flags : FileAttributes,
+  pub file_name : String,
+}
+
+impl UsnRecord
+{
+  /// parse a record starting at the beginning of `data`, `offset` is its offset in the stream
+  pub fn new(data : &[u8], offset : u64) -> Result<Self>
+  {
+    let header = record_slice(data, 0, 8)?;
+    let major_version = LittleEndian::read_u16(&header[4..6]);
+    let (header_size, parent_offset, fields_offset) = match major_version
+    {
+      2 => (USN_RECORD_V2_SIZE, 16, 24),
+      3 => (USN_RECORD_V3_SIZE, 24, 40),
+      version => return Err(NtfsError::UsnRecordVersion{ offset, version }.into()),
+    };
+    let header = record_slice(data, 0, header_size)?;
+
+    //NTFS only use the low 64 bits of the version 3 references
+    let entry = pad_u64(&header[8..14]);
+    let sequence = LittleEndian::read_u16(&header[14..16]);
+    let parent_entry = pad_u64(&header[parent_offset..parent_offset + 6]);
+    let parent_sequence = LittleEndian::read_u16(&header[parent_offset + 6..parent_offset + 8]);
+
+    let fields = &header[fields_offset..];
+    let usn = LittleEndian::read_u64(&fields[0..8]);
+    let timestamp = WindowsTimestamp(LittleEndian::read_u64(&fields[8..16])).to_datetime()?;
+    let reason = UsnReason::from_bits_truncate(LittleEndian::read_u32(&fields[16..20]));
+    let source_info = LittleEndian::read_u32(&fields[20..24]);
+    let security_id = LittleEndian::read_u32(&fields[24..28]);
+    let flags = FileAttributes::from_bits_truncate(LittleEndian::read_u32(&fields[28..32]));
+    let name_length = LittleEndian::read_u16(&fields[32..34]) as usize;
+    let name_offset = LittleEndian::read_u16(&fields[34..36]) as usize;
+    let file_name = record_utf16(data, name_offset, name_length)?;
+
+    Ok(UsnRecord{
+      offset,
+      major_version,
+      entry,
+      sequence,
+      parent_entry,
+      parent_sequence,
+      usn,
+      timestamp,
+      reason,
+      source_info,
+      security_id,
+      flags,
+      file_name,
+    })
+  }
+
+  /// register the record in the hints used to estimate the deletion time of deleted nodes
+  pub fn add_to_hints(&self, hints : &mut DeletionHints)
+  {
+    if self.reason.contains(UsnReason::FILE_DELETE)
+    {
+      hints.add_usn_delete(self.entry, self.sequence, self.timestamp);
+    }
+  }
+}
+
+/**
+ *  UsnRecords
+ *  Iterator on the records of a $J stream, sparse or zeroed ranges and invalid data are skipped
+ *  8 bytes at a time as records are 8 bytes aligned
+ */
+pub struct UsnRecords
+{
+  file : Box<dyn VFile>,
+  size : u64,
+  buffer : Vec<u8>,
+  /// offset of the buffer in the stream
+  buffer_offset : u64,
+  /// position of the next record in the buffer
+  position : usize,
+  /// number of 8 bytes blocks skipped as they didn't contain a valid record
+  skipped : u64,
+  done : bool,
+}
+
+impl UsnRecords
+{
+  pub fn new(builder : Arc<dyn VFileBuilder>) -> Result<Self>
+  {
+    Ok(UsnRecords{ file : builder.open()?, size : builder.size(), buffer : Vec::new(), buffer_offset : 0, position : 0, skipped : 0, done : false })
+  }
+
+  /// number of non-zero 8 bytes blocks that were not part of a valid record
+  pub fn skipped(&self) -> u64
+  {
+    self.skipped
+  }
+
+  /// read the next chunk starting at the current position, false at the end of the stream
+  fn fill(&mut self) -> Result<bool>
+  {
+    let offset = self.buffer_offset + self.position as u64;
+    if offset >= self.size
+    {
+      return Ok(false)
+    }
+    let size = (self.size - offset).min(CHUNK_SIZE as u64) as usize;
+    self.buffer.resize(size, 0);
+    self.file.seek(SeekFrom::Start(offset))?;
+    self.file.read_exact(&mut self.buffer)?;
+    self.buffer_offset = offset;
+    self.position = 0;
+    Ok(true)
+  }
+
+  fn next_record(&mut self) -> Result<Option<UsnRecord>>
+  {
+    loop
+    {
+      if self.position + 8 > self.buffer.len() && !self.fill()?
+      {
+        return Ok(None)
+      }
+      if self.position + 8 > self.buffer.len()
+      {
+        //less than 8 bytes at the end of the stream
+        return Ok(None)
+      }
+
+      let length = LittleEndian::read_u32(&self.buffer[self.position..self.position + 4]) as usize;
+      if length == 0
+      {
+        //skip the zeroed area up to the next non-zero 8 bytes block
+        let zeros = self.buffer[self.position..].iter().position(|byte| *byte != 0).unwrap_or(self.buffer.len() - self.position);
+        self.position += zeros.max(8) & !7;
+        continue
+      }
+      if length < USN_RECORD_V2_SIZE || length > USN_RECORD_MAX_SIZE || length % 8 != 0
+      {
+        self.skipped += 1;
+        self.position += 8;
+        continue
+      }
+      if self.position + length > self.buffer.len()
+      {
+        //the record is at the end of the chunk, read again from its start
+        if self.position == 0 || !self.fill()?
+        {
+          return Ok(None)
+        }
+        if length > self.buffer.len()
+        {
+          return Ok(None)
+        }
+      }
+
+      let offset = self.buffer_offset + self.position as u64;
+      match UsnRecord::new(&self.buffer[self.position..self.position + length], offset)
+      {
+        Ok(record) =>
+        {
+          self.position += length;
+          return Ok(Some(record))
+        },
+        Err(_) =>
+        {
+          self.skipped += 1;
+          self.position += 8;
+        },
+      }
+    }
+  }
+}
+
+impl Iterator for UsnRecords
+{
+  type Item = Result<UsnRecord>;
+
+  fn next(&mut self) -> Option<Self::Item>
+  {
+    if self.done
+    {
+      return None
+    }
+    match self.next_record()
+    {
+      Ok(Some(record)) => Some(Ok(record)),
+      Ok(None) => { self.done = true; None },
+      Err(err) => { self.done = true; Some(Err(err)) },
+    }
+  }
+}
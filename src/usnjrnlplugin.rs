@@ -0,0 +1,87 @@
+//! tap plugin parsing the change journal found in the data of a node, e.g. an extracted or carved $UsnJrnl:$J stream,
+//! each record is added as a child node
+
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use tap::plugin;
+use tap::config_schema;
+use tap::node::Node;
+use tap::error::RustructError;
+use tap::tree::{TreeNodeId, TreeNodeIdSchema};
+use tap::plugin::{PluginInfo, PluginInstance, PluginConfig, PluginArgument, PluginResult, PluginEnvironment};
+
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use schemars::JsonSchema;
+use chrono::{DateTime, Utc};
+use log::warn;
+
+use crate::usnjrnl::UsnRecords;
+
+plugin!("usnjrnl", "File system", "Parse an extracted $UsnJrnl:$J change journal", UsnJrnlPlugin, Arguments);
+
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Arguments
+{
+  #[schemars(with = "TreeNodeIdSchema")]
+  file : TreeNodeId,
+}
+
+#[derive(Debug, Serialize, Deserialize,Default)]
+pub struct Results
+{
+  ///node containing the records
+  usnjrnl_node : Option<TreeNodeId>,
+  ///number of records parsed
+  records : u64,
+  ///number of non-zero 8 bytes blocks that were not part of a valid record
+  skipped : u64,
+  first_usn : Option<u64>,
+  last_usn : Option<u64>,
+  first_time : Option<DateTime<Utc>>,
+  last_time : Option<DateTime<Utc>>,
+}
+
+#[derive(Default)]
+pub struct UsnJrnlPlugin
+{
+}
+
+impl UsnJrnlPlugin
+{
+  fn run(&mut self, args : Arguments, env : PluginEnvironment) -> Result<Results>
+  {
+    let file_node = env.tree.get_node_from_id(args.file).ok_or(RustructError::ArgumentNotFound("file"))?;
+    file_node.value().add_attribute(self.name(), None, None);
+    let value = file_node.value().get_value("data").ok_or(RustructError::ValueNotFound("data"))?;
+    let journal_builder = value.try_as_vfile_builder().ok_or(RustructError::ValueTypeMismatch)?;
+
+    let usnjrnl_node_id = env.tree.add_child(args.file, Node::new("usnjrnl"))?;
+    let mut results = Results{ usnjrnl_node : Some(usnjrnl_node_id), ..Results::default() };
+    let mut records = UsnRecords::new(journal_builder)?;
+    for record in records.by_ref()
+    {
+      let record = match record
+      {
+        Ok(record) => record,
+        //records read before the error are kept
+        Err(err) => { warn!("Can't read journal : {}", err); break },
+      };
+
+      results.records += 1;
+      results.first_usn.get_or_insert(record.usn);
+      results.last_usn = Some(record.usn);
+      results.first_time = Some(results.first_time.map_or(record.timestamp, |time| time.min(record.timestamp)));
+      results.last_time = Some(results.last_time.map_or(record.timestamp, |time| time.max(record.timestamp)));
+
+      let node = Node::new(format!("{}_{}", record.usn, record.file_name));
+      node.value().add_attribute("usn", Arc::new(record), None);
+      env.tree.add_child(usnjrnl_node_id, node)?;
+    }
+    results.skipped = records.skipped();
+
+    Ok(results)
+  }
+}
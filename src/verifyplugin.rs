@@ -0,0 +1,75 @@
+//! tap plugin checking the integrity of the partition found in the data of a node without creating its tree,
+//! the report is added to a child node
+
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use tap::plugin;
+use tap::config_schema;
+use tap::node::Node;
+use tap::error::RustructError;
+use tap::tree::{TreeNodeId, TreeNodeIdSchema};
+use tap::plugin::{PluginInfo, PluginInstance, PluginConfig, PluginArgument, PluginResult, PluginEnvironment};
+
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use schemars::JsonSchema;
+
+use crate::bootsector::BootSector;
+use crate::mapping::offset_builder;
+use crate::ntfs::Ntfs;
+use crate::ntfs::verify::VerifyReport;
+use crate::options::NtfsOptions;
+
+plugin!("ntfs_verify", "File system", "Check the integrity of a NTFS filesystem", NtfsVerifyPlugin, Arguments);
+
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Arguments
+{
+  #[schemars(with = "TreeNodeIdSchema")]
+  file : TreeNodeId,
+  ///byte offset of the volume in the data of the node, to check a volume inside a disk image (0 by default)
+  offset : Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize,Default)]
+pub struct Results
+{
+  ///node containing the report
+  verify_node : Option<TreeNodeId>,
+  ///no check found an inconsistency
+  clean : bool,
+  report : VerifyReport,
+}
+
+#[derive(Default)]
+pub struct NtfsVerifyPlugin
+{
+}
+
+impl NtfsVerifyPlugin
+{
+  fn run(&mut self, args : Arguments, env : PluginEnvironment) -> Result<Results>
+  {
+    let file_node = env.tree.get_node_from_id(args.file).ok_or(RustructError::ArgumentNotFound("file"))?;
+    file_node.value().add_attribute(self.name(), None, None);
+    let value = file_node.value().get_value("data").ok_or(RustructError::ValueNotFound("data"))?;
+    let partition_builder = value.try_as_vfile_builder().ok_or(RustructError::ValueTypeMismatch)?;
+    let partition_builder = match args.offset
+    {
+      Some(offset) if offset != 0 => offset_builder(partition_builder, offset)?,
+      _ => partition_builder,
+    };
+
+    let boot_sector = BootSector::from_file(&mut partition_builder.open()?)?;
+    let mut ntfs = Ntfs::from_partition(partition_builder, &boot_sector, NtfsOptions::default())?;
+    let report = ntfs.verify(&boot_sector);
+
+    let verify_node = Node::new("ntfs_verify");
+    verify_node.value().add_attribute("verify", Arc::new(report.clone()), None);
+    let verify_node_id = env.tree.add_child(args.file, verify_node)?;
+
+    Ok(Results{ verify_node : Some(verify_node_id), clean : report.is_clean(), report })
+  }
+}